@@ -0,0 +1,90 @@
+// Tiny bot speaking the Tetris Bot Protocol, suggesting the lowest places for the first tetromino
+// of the queue. Play with it using: cargo run -- --bot target/debug/examples/tbp_bot
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+
+const ORIENTATIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+fn get_minos(piece: &str, orientation: usize) -> Vec<(i64, i64)> {
+    let minos = match piece {
+        "I" => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        "O" => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        "T" => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+        "J" => [(-1, 1), (-1, 0), (0, 0), (1, 0)],
+        "L" => [(1, 1), (-1, 0), (0, 0), (1, 0)],
+        "S" => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+        _ => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+    };
+    minos
+        .iter()
+        .map(|&(mut x, mut y)| {
+            for _ in 0..orientation {
+                (x, y) = (y, -x);
+            }
+            (x, y)
+        })
+        .collect()
+}
+
+fn fits(board: &[Vec<bool>], minos: &[(i64, i64)], x: i64, y: i64) -> bool {
+    minos.iter().all(|(dx, dy)| {
+        let (col, row) = (x + dx, y + dy);
+        (0..10).contains(&col) && (0..20).contains(&row) && !board[row as usize][col as usize]
+    })
+}
+
+fn suggest(board: &[Vec<bool>], piece: &str) -> Value {
+    let mut moves = Vec::new();
+    for (orientation, name) in ORIENTATIONS.iter().enumerate() {
+        let minos = get_minos(piece, orientation);
+        for x in -2..12 {
+            // Falls from the top as far as it goes
+            let Some(mut y) = (0..20).rev().find(|y| fits(board, &minos, x, *y)) else {
+                continue;
+            };
+            while y > 0 && fits(board, &minos, x, y - 1) {
+                y -= 1;
+            }
+            let height: i64 = minos.iter().map(|(_, dy)| y + dy).sum();
+            moves.push((
+                height,
+                json!({"location": {"type": piece, "orientation": name, "x": x, "y": y}, "spin": "none"}),
+            ));
+        }
+    }
+    moves.sort_by_key(|(height, _)| *height);
+
+    json!({"type": "suggestion", "moves": moves.into_iter().map(|(_, m)| m).collect::<Vec<_>>()})
+}
+
+fn main() {
+    let mut stdout = std::io::stdout();
+    let mut send = |message: Value| {
+        writeln!(stdout, "{0}", message).unwrap();
+        stdout.flush().unwrap();
+    };
+
+    send(
+        json!({"type": "info", "name": "tbp_bot", "version": "0.1.0", "author": "blocchi", "features": []}),
+    );
+
+    let mut board = vec![vec![false; 10]; 40];
+    let mut piece = String::new();
+    for line in std::io::stdin().lock().lines() {
+        let message: Value = serde_json::from_str(&line.unwrap()).unwrap();
+        match message["type"].as_str() {
+            Some("rules") => send(json!({"type": "ready"})),
+            Some("start") => {
+                for (row, cells) in message["board"].as_array().unwrap().iter().enumerate() {
+                    for (col, cell) in cells.as_array().unwrap().iter().enumerate() {
+                        board[row][col] = !cell.is_null();
+                    }
+                }
+                piece = message["queue"][0].as_str().unwrap().to_string();
+            }
+            Some("suggest") => send(suggest(&board, &piece)),
+            Some("quit") => return,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use blocchi::game::env::Environment;
+use blocchi::game::scoring::GameplaySettings;
+use blocchi::game::tetromino::{MoveStatus, PlacementStep};
+use blocchi::game::{self, GameMode, LockedTetromino};
+use blocchi::seed;
+use pyo3::exceptions::{PyIndexError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+// What happened when the tetromino has been dropped and locked
+#[pyclass(frozen, get_all)]
+struct Locked {
+    cleared_lines: u8,
+    t_spin: bool,
+    perfect_clear: bool,
+    points: u32,
+    game_over: bool,
+}
+
+// Single player game played one command at a time, without gravity nor line clear animation
+#[pyclass]
+struct Game {
+    env: Environment,
+    #[pyo3(get)]
+    seed: String,
+}
+
+#[pymethods]
+impl Game {
+    #[new]
+    #[pyo3(signature = (seed=None, mode="marathon", level=1, level_progression="fixed", gameplay=None))]
+    fn new(
+        seed: Option<String>,
+        mode: &str,
+        level: u16,
+        level_progression: &str,
+        gameplay: Option<&str>,
+    ) -> PyResult<Self> {
+        let mode: GameMode = mode.parse().map_err(PyValueError::new_err)?;
+        if mode == GameMode::Puzzle {
+            return Err(PyValueError::new_err(
+                "Puzzles cannot be played from Python",
+            ));
+        }
+        if level == 0 {
+            return Err(PyValueError::new_err("The level starts from 1"));
+        }
+
+        // Speeds and points written as the gameplay section of the settings file
+        let gameplay: GameplaySettings = match gameplay {
+            Some(gameplay) => ron::from_str(gameplay)
+                .map_err(|error| PyValueError::new_err(format!("Invalid gameplay: {0}", error)))?,
+            None => GameplaySettings::default(),
+        };
+        gameplay.validate().map_err(PyValueError::new_err)?;
+
+        let mut game = Self {
+            env: Environment::new(
+                mode,
+                level,
+                level_progression.parse().map_err(PyValueError::new_err)?,
+                gameplay,
+            ),
+            seed: String::new(),
+        };
+        game.reset(seed);
+        Ok(game)
+    }
+
+    // Starts a new game, dealing the tetrominoes of the real game for the same seed
+    #[pyo3(signature = (seed=None))]
+    fn reset(&mut self, seed: Option<String>) {
+        let seed = seed.unwrap_or_else(|| seed::get_random_seed_name(&mut rand::rng()));
+        self.env.reset(&seed);
+        self.seed = seed;
+    }
+
+    fn move_left(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::Left)
+    }
+
+    fn move_right(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::Right)
+    }
+
+    fn rotate_clockwise(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::RotateClockwise)
+    }
+
+    fn rotate_counter_clockwise(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::RotateCounterClockwise)
+    }
+
+    fn rotate_180(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::RotateHalf)
+    }
+
+    // Moves the tetromino a row down, it only locks with a hard drop
+    fn soft_drop(&mut self) -> PyResult<bool> {
+        self.apply_step(PlacementStep::SoftDrop)
+    }
+
+    fn hard_drop(&mut self) -> PyResult<Locked> {
+        self.check_playing()?;
+        let locked = self.env.hard_drop();
+        Ok(self.get_locked(locked))
+    }
+
+    fn hold(&mut self) -> PyResult<bool> {
+        self.check_playing()?;
+        Ok(self.env.hold() == MoveStatus::Moved)
+    }
+
+    // Cells where the current tetromino can lock, in the order expected by place
+    fn placements(&self) -> Vec<Vec<u16>> {
+        self.env
+            .get_game_board()
+            .find_placements()
+            .into_iter()
+            .map(|placement| get_numbers(placement.cells))
+            .collect()
+    }
+
+    // Takes the tetromino to one of its placements with the moves of a player and drops it there
+    fn place(&mut self, index: usize) -> PyResult<Locked> {
+        self.check_playing()?;
+        let locked = self
+            .env
+            .place(index, false)
+            .map_err(PyIndexError::new_err)?;
+        Ok(self.get_locked(locked))
+    }
+
+    // Rows from the top: 0 empty, 1 locked, 2 garbage. The current tetromino is not included
+    #[getter]
+    fn board(&self) -> Vec<Vec<u16>> {
+        let game_board = self.env.get_game_board();
+        (0..game::NUMBER_OF_ROWS)
+            .map(|row| {
+                (0..game::NUMBER_OF_COLUMNS)
+                    .map(|col| {
+                        game_board
+                            .get_cell(row * game::NUMBER_OF_COLUMNS + col)
+                            .into()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn game_over(&self) -> bool {
+        self.env.is_done()
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.env.get_game_score().score
+    }
+
+    #[getter]
+    fn lines(&self) -> u32 {
+        self.env.get_game_score().filled_up_lines
+    }
+
+    #[getter]
+    fn level(&self) -> u16 {
+        self.env.get_game_score().level
+    }
+
+    #[getter]
+    fn current(&self) -> String {
+        self.env
+            .get_game_board()
+            .get_current_tetromino_type()
+            .to_string()
+    }
+
+    // Cells numbered row by row from the top left one
+    #[getter]
+    fn current_cells(&self) -> Vec<u16> {
+        get_numbers(self.env.get_game_board().get_current_tetromino_cells())
+    }
+
+    #[getter]
+    fn upcoming(&self) -> String {
+        self.env
+            .get_game_board()
+            .get_upcoming_tetromino_type()
+            .to_string()
+    }
+
+    #[getter]
+    fn held(&self) -> Option<String> {
+        self.env
+            .get_game_board()
+            .get_held_tetromino_type()
+            .map(|tetromino| tetromino.to_string())
+    }
+
+    fn __repr__(&self) -> String {
+        let game_score = self.env.get_game_score();
+        format!(
+            "Game(seed='{0}', mode='{1}', score={2}, lines={3}, level={4})",
+            self.seed,
+            self.env.get_mode(),
+            game_score.score,
+            game_score.filled_up_lines,
+            game_score.level
+        )
+    }
+}
+
+impl Game {
+    fn check_playing(&self) -> PyResult<()> {
+        if self.env.is_done() {
+            return Err(PyRuntimeError::new_err("The game is over, reset it first"));
+        }
+        Ok(())
+    }
+
+    fn apply_step(&mut self, step: PlacementStep) -> PyResult<bool> {
+        self.check_playing()?;
+        Ok(self.env.apply_step(step) == MoveStatus::Moved)
+    }
+
+    fn get_locked(&self, locked: LockedTetromino) -> Locked {
+        Locked {
+            cleared_lines: locked.outcome.cleared_lines,
+            t_spin: locked.outcome.t_spin,
+            perfect_clear: locked.outcome.perfect_clear,
+            points: locked.points,
+            game_over: self.env.is_done(),
+        }
+    }
+}
+
+// Lists of bytes would reach Python as bytes objects rather than lists of numbers
+fn get_numbers(cells: Vec<u8>) -> Vec<u16> {
+    cells.into_iter().map(u16::from).collect()
+}
+
+#[pymodule]
+#[pyo3(name = "blocchi")]
+fn blocchi_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Game>()?;
+    module.add_class::<Locked>()?;
+    Ok(())
+}
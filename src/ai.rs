@@ -0,0 +1,90 @@
+use crate::bot;
+use crate::cli::CommandLineArguments;
+use crate::ecs::*;
+use crate::game;
+use crate::game::heuristic;
+use bevy::prelude::*;
+
+// Local player driven by the built-in AI, heading for the cells it has chosen
+#[derive(Component, Default)]
+pub struct AiControls {
+    target: Option<Vec<u8>>,
+}
+
+// Built-in player placing every tetromino where the board scores best, toggled with F2
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostStartup,
+            enable_ai.run_if(|arguments: Res<CommandLineArguments>| arguments.ai),
+        )
+        .add_systems(Update, toggle_ai)
+        .add_systems(FixedUpdate, play_ai_moves.in_set(GameplaySet::Input));
+    }
+}
+
+fn enable_ai(mut commands: Commands, players: Query<Entity, With<LocalControls>>) {
+    for player in players {
+        commands.entity(player).insert(AiControls::default());
+    }
+}
+
+fn toggle_ai(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut game_session: ResMut<GameSession>,
+    players: Query<(Entity, &Player, Has<AiControls>), With<LocalControls>>,
+) {
+    if !keys.just_pressed(KeyCode::F2) {
+        return;
+    }
+
+    for (entity, player, playing) in players {
+        if playing {
+            commands.entity(entity).remove::<AiControls>();
+            info!("Player {0} is back to the controls", player.id + 1);
+        } else {
+            commands.entity(entity).insert(AiControls::default());
+            game_session.assisted = true;
+            info!("The AI plays for player {0}", player.id + 1);
+        }
+    }
+}
+
+fn play_ai_moves(
+    mut players: Query<(
+        &game::GameBoard,
+        &GameSettings,
+        &mut PlayerInput,
+        &mut AiControls,
+    )>,
+) {
+    for (game_board, game_settings, mut input, mut ai) in &mut players {
+        if game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        let target = match ai.target.take() {
+            Some(target) => target,
+            None => match heuristic::choose_placement(game_board) {
+                // The tetromino swapped in gets its own place on the next step
+                Some(choice) if choice.hold => {
+                    input.hold = true;
+                    continue;
+                }
+                Some(choice) => choice.placement.cells,
+                None => continue,
+            },
+        };
+
+        // A target out of reach, such as after a restart, is chosen again on the next step
+        if let Some(placement_input) = bot::get_placement_input(game_board, &target) {
+            if !placement_input.hard_drop {
+                ai.target = Some(target);
+            }
+            input.merge(&placement_input);
+        }
+    }
+}
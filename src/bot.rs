@@ -0,0 +1,436 @@
+use crate::ecs::*;
+use crate::game;
+use crate::game::tetromino::{MoveStatus, PlacementStep, Tetromino, TetrominoType};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+// Time the bot gets to introduce itself and to accept the rules
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Rows of the TBP board, the ones above the visible board are always empty
+const TBP_ROWS: usize = 40;
+
+// Messages sent to the bot, following the Tetris Bot Protocol (TBP)
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    Rules {},
+    Start {
+        hold: Option<TetrominoType>,
+        queue: Vec<TetrominoType>,
+        combo: u8,
+        back_to_back: bool,
+        board: Vec<Vec<Option<String>>>, // Rows from the bottom up
+    },
+    Suggest,
+    Stop,
+    Quit,
+}
+
+// Messages received from the bot, the ones the game has no use for are ignored
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<BotMove>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct BotMove {
+    location: PieceLocation,
+}
+
+// Center of the tetromino in the SRS coordinates, columns from the left and rows from the bottom
+#[derive(Deserialize, Debug)]
+struct PieceLocation {
+    #[serde(rename = "type")]
+    tetromino: TetrominoType,
+    orientation: Orientation,
+    x: i32,
+    y: i32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+// Move of the bot being played, one command per simulation step
+struct BotPlan {
+    hold: bool,
+    cells: Vec<u8>, // Sorted
+}
+
+enum BotState {
+    Idle,
+    Thinking,
+    Playing(BotPlan),
+    Stopped,
+}
+
+// External bot launched as a child process, talking TBP through its standard input and output
+#[derive(Resource)]
+pub struct Bot {
+    process: Child,
+    stdin: ChildStdin,
+    receiver: Mutex<Receiver<Result<BotMessage, String>>>,
+    state: BotState,
+}
+
+impl Bot {
+    // Launches the bot and waits until it is ready to play
+    pub fn launch(command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| String::from("The bot command is empty"))?;
+        let mut process = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("Cannot launch the bot '{0}': {1}", command, error))?;
+
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+
+        // Messages are read in the background and collected every step
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let message = match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => serde_json::from_str(&line)
+                        .map_err(|error| format!("Invalid message from the bot: {0}", error)),
+                    Err(error) => Err(format!("Cannot read from the bot: {0}", error)),
+                };
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+            let _ = sender.send(Err(String::from("The bot has quit")));
+        });
+
+        let mut bot = Self {
+            process,
+            stdin,
+            receiver: Mutex::new(receiver),
+            state: BotState::Idle,
+        };
+
+        match bot.wait_message()? {
+            BotMessage::Info { name, version } => println!("Playing with {0} {1}", name, version),
+            message => return Err(format!("Unexpected message from the bot: {0:?}", message)),
+        }
+        bot.send(&FrontendMessage::Rules {})?;
+        match bot.wait_message()? {
+            BotMessage::Ready => Ok(bot),
+            BotMessage::Error { reason } => {
+                Err(format!("The bot does not accept the rules: {0}", reason))
+            }
+            message => Err(format!("Unexpected message from the bot: {0:?}", message)),
+        }
+    }
+
+    fn wait_message(&self) -> Result<BotMessage, String> {
+        let receiver = self
+            .receiver
+            .lock()
+            .map_err(|_| String::from("Cannot read from the bot"))?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Ok(BotMessage::Unknown)) => continue,
+                Ok(message) => return message,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(String::from("The bot did not answer in time"));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(String::from("The bot has quit"));
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), String> {
+        let line = serde_json::to_string(message).map_err(|error| error.to_string())?;
+        writeln!(self.stdin, "{0}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| format!("Cannot send '{0}' to the bot: {1}", line, error))
+    }
+
+    // Starts the bot from the board as it is, any garbage received in the meantime included
+    fn suggest(&mut self, game_board: &game::GameBoard) -> Result<(), String> {
+        let empty_row = vec![None; game::NUMBER_OF_COLUMNS as usize];
+        let mut board = vec![empty_row; TBP_ROWS];
+        for (tbp_row, cells) in board
+            .iter_mut()
+            .take(game::NUMBER_OF_ROWS as usize)
+            .enumerate()
+        {
+            let row = game::NUMBER_OF_ROWS - 1 - tbp_row as u8;
+            for (col, cell) in cells.iter_mut().enumerate() {
+                let board_cell = Tetromino::get_cell_from_row_and_column(row, col as u8);
+                if game_board.is_cell_occupied(board_cell) {
+                    *cell = Some(String::from("G"));
+                }
+            }
+        }
+
+        self.send(&FrontendMessage::Start {
+            hold: game_board.get_held_tetromino_type().cloned(),
+            queue: vec![
+                game_board.get_current_tetromino_type().clone(),
+                game_board.get_upcoming_tetromino_type().clone(),
+            ],
+            combo: game_board.get_combo(),
+            back_to_back: game_board.is_back_to_back(),
+            board,
+        })?;
+        self.send(&FrontendMessage::Suggest)
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+// Gets the board cells covered by the tetromino at the location given by the bot
+fn get_location_cells(location: &PieceLocation) -> Option<Vec<u8>> {
+    let minos: [(i32, i32); 4] = match location.tetromino {
+        TetrominoType::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        TetrominoType::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        TetrominoType::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+        TetrominoType::J => [(-1, 1), (-1, 0), (0, 0), (1, 0)],
+        TetrominoType::L => [(1, 1), (-1, 0), (0, 0), (1, 0)],
+        TetrominoType::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+        TetrominoType::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+    };
+    let turns = match location.orientation {
+        Orientation::North => 0,
+        Orientation::East => 1,
+        Orientation::South => 2,
+        Orientation::West => 3,
+    };
+
+    let mut cells = Vec::with_capacity(minos.len());
+    for (mut x, mut y) in minos {
+        // Every turn rotates the mino clockwise around the center
+        for _ in 0..turns {
+            (x, y) = (y, -x);
+        }
+        let col = location.x + x;
+        let row = game::NUMBER_OF_ROWS as i32 - 1 - (location.y + y);
+        if !(0..game::NUMBER_OF_COLUMNS as i32).contains(&col)
+            || !(0..game::NUMBER_OF_ROWS as i32).contains(&row)
+        {
+            return None;
+        }
+        cells.push(Tetromino::get_cell_from_row_and_column(
+            row as u8, col as u8,
+        ));
+    }
+    cells.sort();
+    Some(cells)
+}
+
+// Picks the first move suggested that the tetromino can reach, holding it first when needed
+fn choose_move(moves: &[BotMove], game_board: &game::GameBoard) -> Option<BotPlan> {
+    moves.iter().find_map(|suggested| {
+        let cells = get_location_cells(&suggested.location)?;
+        let mut board = game_board.clone();
+        let hold = suggested.location.tetromino != *board.get_current_tetromino_type();
+        // The tetromino drawn when holding the first time is only seen by the copy of the board
+        if hold
+            && (board.hold_tetromino(&mut rand::rng()) == MoveStatus::NotMoved
+                || suggested.location.tetromino != *board.get_current_tetromino_type())
+        {
+            return None;
+        }
+
+        board
+            .find_placements()
+            .iter()
+            .any(|placement| placement.cells == cells)
+            .then_some(BotPlan { hold, cells })
+    })
+}
+
+// Gets the next command taking the current tetromino to the cells, a hard drop once above them.
+// Nothing when the cells cannot be reached anymore, such as when the tetromino fell past them.
+pub fn get_placement_input(game_board: &game::GameBoard, cells: &[u8]) -> Option<PlayerInput> {
+    let placement = game_board
+        .find_placements()
+        .into_iter()
+        .find(|placement| placement.cells == cells)?;
+
+    let mut input = PlayerInput::default();
+    match placement.steps.first() {
+        None => input.hard_drop = true,
+        Some(PlacementStep::Left) => input.left = true,
+        Some(PlacementStep::Right) => input.right = true,
+        Some(PlacementStep::RotateClockwise) => input.rotate = true,
+        Some(PlacementStep::RotateCounterClockwise) => input.rotate_ccw = true,
+        Some(PlacementStep::RotateHalf) => input.rotate_180 = true,
+        Some(PlacementStep::SoftDrop) => input.down = true,
+    }
+    Some(input)
+}
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, play_bot_moves.in_set(GameplaySet::Input));
+    }
+}
+
+fn play_bot_moves(
+    bot: ResMut<Bot>,
+    mut players: Query<(&game::GameBoard, &GameSettings, &mut PlayerInput), With<BotControls>>,
+) {
+    let Ok((game_board, game_settings, mut input)) = players.single_mut() else {
+        return;
+    };
+    let bot = bot.into_inner();
+
+    let messages: Vec<Result<BotMessage, String>> = match bot.receiver.lock() {
+        Ok(receiver) => receiver.try_iter().collect(),
+        Err(_) => return,
+    };
+    for message in messages {
+        match message {
+            Ok(BotMessage::Suggestion { moves }) if matches!(bot.state, BotState::Thinking) => {
+                let plan = choose_move(&moves, game_board).unwrap_or_else(|| {
+                    warn!("The bot suggested no move that can be played, dropping the tetromino");
+                    BotPlan {
+                        hold: false,
+                        cells: game_board.find_placements()[0].cells.clone(),
+                    }
+                });
+                bot.state = BotState::Playing(plan);
+                if let Err(error) = bot.send(&FrontendMessage::Stop) {
+                    error!("{0}", error);
+                    bot.state = BotState::Stopped;
+                }
+            }
+            Ok(BotMessage::Error { reason }) => warn!("The bot reported an error: {0}", reason),
+            Ok(_) => {}
+            Err(error) => {
+                error!("{0}", error);
+                bot.state = BotState::Stopped;
+            }
+        }
+    }
+
+    // The bot is asked again once the filled rows are gone and the next tetromino is in place
+    if game_settings.board_status != BoardStatus::Playing {
+        return;
+    }
+
+    match &mut bot.state {
+        BotState::Idle => {
+            bot.state = match bot.suggest(game_board) {
+                Ok(_) => BotState::Thinking,
+                Err(error) => {
+                    error!("{0}", error);
+                    BotState::Stopped
+                }
+            };
+        }
+        BotState::Playing(plan) if plan.hold => {
+            input.hold = true;
+            plan.hold = false;
+        }
+        BotState::Playing(plan) => match get_placement_input(game_board, &plan.cells) {
+            Some(placement_input) => {
+                if placement_input.hard_drop {
+                    bot.state = BotState::Idle;
+                }
+                input.merge(&placement_input);
+            }
+            // The tetromino fell past the way to get there, the bot is asked again
+            None => bot.state = BotState::Idle,
+        },
+        BotState::Thinking | BotState::Stopped => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tetromino::DroppedStatus;
+    use bevy_prng::ChaCha8Rng;
+    use rand::SeedableRng;
+
+    // The example bot is built along with the tests, next to the directory of the test program
+    fn get_example_bot() -> String {
+        let test_program = std::env::current_exe().unwrap();
+        let target_dir = test_program.parent().unwrap().parent().unwrap();
+        target_dir
+            .join("examples")
+            .join(format!("tbp_bot{0}", std::env::consts::EXE_SUFFIX))
+            .display()
+            .to_string()
+    }
+
+    #[test]
+    fn plays_the_move_suggested_by_the_example_bot() {
+        let mut game_board = game::GameBoard::new(&game::GameMode::Marathon);
+        game_board.init(&mut ChaCha8Rng::from_seed([0; 32]));
+        let mut bot = Bot::launch(&get_example_bot()).unwrap();
+
+        bot.suggest(&game_board).unwrap();
+        let BotMessage::Suggestion { moves } = bot.wait_message().unwrap() else {
+            panic!("The bot did not suggest any move");
+        };
+        let plan = choose_move(&moves, &game_board).unwrap();
+        assert!(!plan.hold);
+
+        // The commands are played one per step, as the game does, until the hard drop
+        loop {
+            let input = get_placement_input(&game_board, &plan.cells).unwrap();
+            if input.hard_drop {
+                break;
+            }
+            let step = input.get_move().unwrap_or(PlacementStep::SoftDrop);
+            assert_eq!(game_board.apply_step(step), MoveStatus::Moved);
+        }
+        while let DroppedStatus::Dropped = game_board.drop_down() {}
+
+        let occupied: Vec<u8> = (0..game::NUMBER_OF_CELLS)
+            .filter(|cell| game_board.is_cell_occupied(*cell))
+            .collect();
+        assert_eq!(occupied, plan.cells);
+        // The example bot suggests the lowest places first
+        assert!(
+            plan.cells
+                .iter()
+                .all(|cell| *cell >= game::NUMBER_OF_CELLS - 2 * game::NUMBER_OF_COLUMNS)
+        );
+    }
+}
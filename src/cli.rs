@@ -0,0 +1,150 @@
+use crate::consts::MAX_LEVEL;
+use crate::game::GameMode;
+use crate::game::fumen::Fumen;
+use crate::game::level::LevelProgression;
+use crate::game::opener::Opener;
+use crate::game::puzzle::Puzzle;
+use crate::replay::Replay;
+use crate::seed;
+use crate::storage;
+use bevy::prelude::Resource;
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Parser, Resource, Debug, Clone)]
+#[command(version, about = "A Tetris clone in rust and Bevy")]
+pub struct CommandLineArguments {
+    // Game mode to play: marathon, big or puzzle
+    #[arg(long, default_value = "marathon")]
+    pub mode: GameMode,
+
+    // Puzzle file with the board, the pieces and the objective (implies the puzzle mode)
+    #[arg(long, value_parser = Puzzle::load, required_if_eq("mode", "puzzle"))]
+    pub puzzle: Option<Puzzle>,
+
+    // Fumen diagram, as a link or its data such as v115@..., the game starts from with its pieces
+    #[arg(long, conflicts_with_all = ["mode", "puzzle", "host", "join", "spectate"])]
+    pub fumen: Option<Fumen>,
+
+    // Position file to edit before playing it, or the fumen to edit, saved to the file with F5
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["mode", "puzzle", "versus", "host", "join", "spectate"])]
+    pub editor: Option<PathBuf>,
+
+    // Opener to practice, tki, dt-cannon, albatross or pco, or an opener file, with its pieces
+    // handed out in order and where they go shown on the board
+    #[arg(long, value_name = "OPENER", value_parser = Opener::find, conflicts_with_all = ["mode", "puzzle", "fumen", "editor", "versus", "host", "join", "spectate"])]
+    pub opener: Option<Opener>,
+
+    // Level the game starts from
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=MAX_LEVEL as i64))]
+    pub level: u16,
+
+    // When the level goes up: fixed, nes or variable
+    #[arg(long, default_value = "fixed")]
+    pub level_progression: LevelProgression,
+
+    // Flags the tetrominoes placed with more moves and rotations than needed
+    #[arg(long)]
+    pub finesse: bool,
+
+    // Two players on a split screen sending garbage to each other
+    #[arg(long, conflicts_with = "puzzle")]
+    pub versus: bool,
+
+    // Address to wait for an opponent on, such as 0.0.0.0:7878, for a versus over the network
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["puzzle", "versus", "join"])]
+    pub host: Option<String>,
+
+    // Address of the opponent hosting a versus over the network
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["puzzle", "versus"])]
+    pub join: Option<String>,
+
+    // Address where spectators can watch the game, such as 0.0.0.0:7879
+    #[arg(long, value_name = "ADDRESS")]
+    pub spectator_server: Option<String>,
+
+    // Address of a game to watch, instead of playing
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["puzzle", "versus", "host", "join", "spectator_server"])]
+    pub spectate: Option<String>,
+
+    // Seed of the sequence of tetrominoes, such as a number or a word, to play the same game again
+    #[arg(long, conflicts_with_all = ["join", "spectate"])]
+    pub seed: Option<String>,
+
+    // Plays the seed of the day, the same for everyone
+    #[arg(long, conflicts_with_all = ["seed", "join", "spectate"])]
+    pub daily: bool,
+
+    // Replay file to watch, instead of playing
+    #[arg(long, value_name = "FILE", value_parser = Replay::load, conflicts_with_all = ["mode", "puzzle", "fumen", "editor", "opener", "level", "level_progression", "versus", "host", "join", "spectate", "seed", "daily"])]
+    pub replay: Option<Replay>,
+
+    // Command launching a bot speaking the Tetris Bot Protocol, playing instead of the keyboard
+    // in a single player game, or as the second player in versus
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["host", "join", "spectate", "replay"])]
+    pub bot: Option<String>,
+
+    // Starts with the built-in AI playing, toggled with F2
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "replay"])]
+    pub ai: bool,
+
+    // Local address, such as 127.0.0.1:7880, where other programs can query and play the game
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["host", "join", "spectate", "replay"])]
+    pub control: Option<String>,
+
+    // Serves a headless environment for learning agents on the standard input and output
+    #[arg(long, conflicts_with_all = ["puzzle", "fumen", "editor", "opener", "finesse", "versus", "host", "join", "spectator_server", "spectate", "replay", "resume", "bot", "ai", "control"])]
+    pub env: bool,
+
+    // Settings file with the speeds, the points, the square size and the colors, reloaded when changed
+    #[arg(long, value_name = "FILE")]
+    pub settings: Option<PathBuf>,
+
+    // Resumes the game saved with F5 or when closing the window
+    #[arg(long, conflicts_with_all = ["mode", "puzzle", "fumen", "editor", "opener", "level", "level_progression", "versus", "host", "join", "spectate", "seed", "daily", "replay"])]
+    pub resume: bool,
+
+    // File the game is saved to and resumed from, save.json in the data directory by default
+    #[arg(long, value_name = "FILE")]
+    pub save_file: Option<PathBuf>,
+
+    // Directory where the replays of the games played are saved, replays in the data directory by default
+    #[arg(long, value_name = "DIR")]
+    pub replay_dir: Option<PathBuf>,
+}
+
+impl CommandLineArguments {
+    pub fn get_mode(&self) -> GameMode {
+        match self.puzzle {
+            Some(_) => GameMode::Puzzle,
+            None => self.mode.clone(),
+        }
+    }
+
+    pub fn get_settings_file(&self) -> PathBuf {
+        self.settings
+            .clone()
+            .unwrap_or_else(|| storage::get_config_dir().join("settings.ron"))
+    }
+
+    pub fn get_save_file(&self) -> PathBuf {
+        self.save_file
+            .clone()
+            .unwrap_or_else(|| storage::get_data_dir().join("save.json"))
+    }
+
+    pub fn get_replay_dir(&self) -> PathBuf {
+        self.replay_dir
+            .clone()
+            .unwrap_or_else(|| storage::get_data_dir().join("replays"))
+    }
+
+    // Gets the seed asked for, a random one is drawn for every game when missing
+    pub fn get_seed(&self) -> Option<String> {
+        if self.daily {
+            Some(seed::get_daily_seed_name())
+        } else {
+            self.seed.clone()
+        }
+    }
+}
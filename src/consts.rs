@@ -1,18 +1,26 @@
-use bevy::color::Color;
+#[cfg(feature = "bevy")]
+pub use colors::*;
+
+// Colors are only needed to draw the game, the game logic builds without Bevy
+#[cfg(feature = "bevy")]
+mod colors {
+    use bevy::color::Color;
+
+    pub const ORANGE: Color = Color::linear_rgb(1.0, 0.647, 0.0);
+    pub const RED: Color = Color::linear_rgb(1.0, 0.0, 0.0);
+    pub const BLUE: Color = Color::linear_rgb(0.0, 0.0, 1.0);
+    pub const DARK_BLUE: Color = Color::linear_rgb(0.0, 0.0, 0.392);
+    pub const GREEN: Color = Color::linear_rgb(0.0, 1.0, 0.0);
+    pub const DARK_GREEN: Color = Color::linear_rgb(0.0, 0.392, 0.0);
+    pub const VIOLET: Color = Color::linear_rgb(0.498, 1.0, 1.0);
+    pub const GRAY: Color = Color::linear_rgb(0.7, 0.7, 0.7);
+    pub const PINK: Color = Color::linear_rgb(1.0, 0.753, 0.796);
+    pub const YELLOW: Color = Color::linear_rgb(1.0, 1.00, 0.00);
+    pub const DARK_GRAY: Color = Color::linear_rgb(0.3, 0.3, 0.3);
+}
 
 pub const CLEAN_UP_OCCUPIED_ROWS_TIME_DELTA_MS: u64 = 5;
 pub const SQUARE_SIZE: f32 = 30.0;
-pub const ORANGE: Color = Color::linear_rgb(1.0, 0.647, 0.0);
-pub const RED: Color = Color::linear_rgb(1.0, 0.0, 0.0);
-pub const BLUE: Color = Color::linear_rgb(0.0, 0.0, 1.0);
-pub const DARK_BLUE: Color = Color::linear_rgb(0.0, 0.0, 0.392);
-pub const GREEN: Color = Color::linear_rgb(0.0, 1.0, 0.0);
-pub const DARK_GREEN: Color = Color::linear_rgb(0.0, 0.392, 0.0);
-pub const VIOLET: Color = Color::linear_rgb(0.498, 1.0, 1.0);
-pub const GRAY: Color = Color::linear_rgb(0.7, 0.7, 0.7);
-pub const PINK: Color = Color::linear_rgb(1.0, 0.753, 0.796);
-pub const YELLOW: Color = Color::linear_rgb(1.0, 1.00, 0.00);
-pub const DARK_GRAY: Color = Color::linear_rgb(0.3, 0.3, 0.3);
 pub const POINTS_FOR_CLEARED_ROW: u32 = 100;
 pub const POINTS_FOR_TETROMINO_DROPPED: u32 = 10;
 pub const CLEARED_UP_LINES_PER_LEVEL: u16 = 10;
@@ -20,3 +28,9 @@ pub const MAX_LEVEL: u16 = 255;
 pub const BASE_SPEED_MS: u64 = 800;
 pub const LEVEL_SPEED_DELTA: u64 = 25;
 pub const MIN_SPEED_MS: u64 = 50;
+pub const VERSUS_COUNTDOWN_SECONDS: u64 = 3;
+pub const VERSUS_BOARD_OFFSETS: [f32; 2] = [-445.0, 165.0];
+pub const GARBAGE_BAR_WIDTH: f32 = 10.0;
+pub const GARBAGE_BAR_X: f32 = 190.0;
+pub const GARBAGE_BAR_BOTTOM: f32 = -300.0;
+pub const SIMULATION_STEPS_PER_SECOND: f64 = 64.0;
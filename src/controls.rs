@@ -0,0 +1,571 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::scores::NameEntry;
+use crate::settings::{Settings, SettingsWatcher};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+// Keys handled outside of the action map, they cannot be bound to any action
+const RESERVED_KEYS: [KeyCode; 9] = [
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::KeyH,
+    KeyCode::Escape,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCW,
+    RotateCCW,
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 10] = [
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::SoftDrop,
+        InputAction::HardDrop,
+        InputAction::RotateCW,
+        InputAction::RotateCCW,
+        InputAction::Rotate180,
+        InputAction::Hold,
+        InputAction::Pause,
+        InputAction::Restart,
+    ];
+}
+
+impl Display for InputAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            InputAction::MoveLeft => write!(f, "Move left"),
+            InputAction::MoveRight => write!(f, "Move right"),
+            InputAction::SoftDrop => write!(f, "Soft drop"),
+            InputAction::HardDrop => write!(f, "Hard drop"),
+            InputAction::RotateCW => write!(f, "Rotate clockwise"),
+            InputAction::RotateCCW => write!(f, "Rotate counterclockwise"),
+            InputAction::Rotate180 => write!(f, "Rotate 180°"),
+            InputAction::Hold => write!(f, "Hold"),
+            InputAction::Pause => write!(f, "Pause"),
+            InputAction::Restart => write!(f, "Restart"),
+        }
+    }
+}
+
+// Keys or gamepad buttons bound to every action, any of them triggers the action
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings<T>(BTreeMap<InputAction, Vec<T>>);
+
+pub type KeyBindings = Bindings<KeyCode>;
+pub type ButtonBindings = Bindings<GamepadButton>;
+
+impl<T: Copy + PartialEq + std::fmt::Debug> Bindings<T> {
+    fn new(bindings: &[(InputAction, &[T])]) -> Self {
+        Self(
+            bindings
+                .iter()
+                .map(|(action, inputs)| (*action, inputs.to_vec()))
+                .collect(),
+        )
+    }
+
+    pub fn get_inputs(&self, action: InputAction) -> &[T] {
+        self.0
+            .get(&action)
+            .map(|inputs| inputs.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn add_input(&mut self, action: InputAction, input: T) {
+        let inputs = self.0.entry(action).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    fn clear_inputs(&mut self, action: InputAction) {
+        self.0.insert(action, Vec::new());
+    }
+
+    // Actions left out of the settings file keep their default inputs
+    fn fill_missing_actions(&mut self, defaults: &Self) {
+        for (action, inputs) in &defaults.0 {
+            self.0.entry(*action).or_insert_with(|| inputs.clone());
+        }
+    }
+
+    // Gets the action the input is bound to, other than the given one
+    fn find_action(&self, input: T, except: Option<InputAction>) -> Option<InputAction> {
+        self.0
+            .iter()
+            .find(|(action, inputs)| inputs.contains(&input) && except != Some(**action))
+            .map(|(action, _)| *action)
+    }
+
+    fn get_inputs_text(&self, action: InputAction) -> String {
+        let inputs: Vec<String> = self
+            .get_inputs(action)
+            .iter()
+            .map(|input| format!("{0:?}", input))
+            .collect();
+        inputs.join(", ")
+    }
+}
+
+impl KeyBindings {
+    pub fn pressed(&self, keys: &ButtonInput<KeyCode>, action: InputAction) -> bool {
+        keys.any_pressed(self.get_inputs(action).iter().copied())
+    }
+
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>, action: InputAction) -> bool {
+        keys.any_just_pressed(self.get_inputs(action).iter().copied())
+    }
+
+    pub fn just_released(&self, keys: &ButtonInput<KeyCode>, action: InputAction) -> bool {
+        keys.any_just_released(self.get_inputs(action).iter().copied())
+    }
+
+    // Taps act once the key is released, the soft drop goes on while the key is held down
+    pub fn get_player_input(&self, keys: &ButtonInput<KeyCode>) -> PlayerInput {
+        PlayerInput {
+            left: self.just_released(keys, InputAction::MoveLeft),
+            right: self.just_released(keys, InputAction::MoveRight),
+            rotate: self.just_released(keys, InputAction::RotateCW),
+            down: self.pressed(keys, InputAction::SoftDrop),
+            rotate_ccw: self.just_released(keys, InputAction::RotateCCW),
+            rotate_180: self.just_released(keys, InputAction::Rotate180),
+            hard_drop: self.just_released(keys, InputAction::HardDrop),
+            hold: self.just_released(keys, InputAction::Hold),
+            repeat: false,
+        }
+    }
+}
+
+impl ButtonBindings {
+    pub fn pressed(&self, gamepad: &Gamepad, action: InputAction) -> bool {
+        gamepad.any_pressed(self.get_inputs(action).iter().copied())
+    }
+
+    pub fn just_pressed(&self, gamepad: &Gamepad, action: InputAction) -> bool {
+        gamepad.any_just_pressed(self.get_inputs(action).iter().copied())
+    }
+}
+
+// Keys of the single player games, and of the two players sharing the keyboard in versus.
+// The gamepad buttons are the same for every player, each one playing with its own gamepad.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlsSettings {
+    pub solo: KeyBindings,
+    pub versus: [KeyBindings; 2],
+    pub gamepad: ButtonBindings,
+    pub das_ms: u64, // Delay before a direction held down on the gamepad starts repeating the move
+    pub arr_ms: u64, // Delay between the moves repeated while the direction is held down
+    pub pointer: bool, // Swipes and taps with the mouse or on a touchscreen
+    pub touch_buttons: bool, // On-screen buttons beside the board in solo games
+}
+
+impl Default for ControlsSettings {
+    fn default() -> Self {
+        use InputAction::*;
+
+        Self {
+            solo: KeyBindings::new(&[
+                (MoveLeft, &[KeyCode::ArrowLeft]),
+                (MoveRight, &[KeyCode::ArrowRight]),
+                (SoftDrop, &[KeyCode::ArrowDown]),
+                (HardDrop, &[KeyCode::Enter]),
+                (RotateCW, &[KeyCode::ArrowUp, KeyCode::KeyX]),
+                (RotateCCW, &[KeyCode::KeyZ]),
+                (Rotate180, &[KeyCode::KeyA]),
+                (Hold, &[KeyCode::KeyC]),
+                (Pause, &[KeyCode::Space, KeyCode::KeyP]),
+                (Restart, &[KeyCode::KeyN]),
+            ]),
+            versus: [
+                KeyBindings::new(&[
+                    (MoveLeft, &[KeyCode::KeyA]),
+                    (MoveRight, &[KeyCode::KeyD]),
+                    (SoftDrop, &[KeyCode::KeyS]),
+                    (HardDrop, &[KeyCode::KeyE]),
+                    (RotateCW, &[KeyCode::KeyW]),
+                    (RotateCCW, &[KeyCode::KeyQ]),
+                    (Rotate180, &[]),
+                    (Hold, &[KeyCode::KeyR]),
+                    (Pause, &[KeyCode::Space]),
+                    (Restart, &[KeyCode::KeyN]),
+                ]),
+                KeyBindings::new(&[
+                    (MoveLeft, &[KeyCode::ArrowLeft]),
+                    (MoveRight, &[KeyCode::ArrowRight]),
+                    (SoftDrop, &[KeyCode::ArrowDown]),
+                    (HardDrop, &[KeyCode::Enter]),
+                    (RotateCW, &[KeyCode::ArrowUp]),
+                    (RotateCCW, &[KeyCode::ShiftRight]),
+                    (Rotate180, &[]),
+                    (Hold, &[KeyCode::ControlRight]),
+                    (Pause, &[KeyCode::KeyP]),
+                    (Restart, &[]),
+                ]),
+            ],
+            gamepad: ButtonBindings::new(&[
+                (MoveLeft, &[GamepadButton::DPadLeft]),
+                (MoveRight, &[GamepadButton::DPadRight]),
+                (SoftDrop, &[GamepadButton::DPadDown]),
+                (HardDrop, &[GamepadButton::DPadUp, GamepadButton::North]),
+                (RotateCW, &[GamepadButton::South]),
+                (RotateCCW, &[GamepadButton::East]),
+                (Rotate180, &[]),
+                (
+                    Hold,
+                    &[
+                        GamepadButton::West,
+                        GamepadButton::LeftTrigger,
+                        GamepadButton::RightTrigger,
+                    ],
+                ),
+                (Pause, &[GamepadButton::Start]),
+                (Restart, &[GamepadButton::Select]),
+            ]),
+            das_ms: 167,
+            arr_ms: 33,
+            pointer: true,
+            touch_buttons: false,
+        }
+    }
+}
+
+impl ControlsSettings {
+    // Gets the bindings used together, one per player
+    pub fn get_layout(&self, versus: bool) -> &[KeyBindings] {
+        match versus {
+            true => &self.versus,
+            false => std::slice::from_ref(&self.solo),
+        }
+    }
+
+    fn get_layout_mut(&mut self, versus: bool) -> &mut [KeyBindings] {
+        match versus {
+            true => &mut self.versus,
+            false => std::slice::from_mut(&mut self.solo),
+        }
+    }
+
+    pub fn get_bindings(&self, versus: bool, player: u8) -> &KeyBindings {
+        &self.get_layout(versus)[player as usize]
+    }
+
+    // Pausing and restarting are shared, any player can trigger them
+    pub fn just_pressed(
+        &self,
+        keys: &ButtonInput<KeyCode>,
+        versus: bool,
+        action: InputAction,
+    ) -> bool {
+        self.get_layout(versus)
+            .iter()
+            .any(|bindings| bindings.just_pressed(keys, action))
+    }
+
+    // Gets the player and the action the key is bound to, other than the given ones
+    fn find_binding(
+        &self,
+        versus: bool,
+        key: KeyCode,
+        except: Option<(usize, InputAction)>,
+    ) -> Option<(usize, InputAction)> {
+        self.get_layout(versus)
+            .iter()
+            .enumerate()
+            .find_map(|(player, bindings)| {
+                let except = except
+                    .filter(|(except_player, _)| *except_player == player)
+                    .map(|(_, action)| action);
+                Some((player, bindings.find_action(key, except)?))
+            })
+    }
+
+    pub fn fill_missing_actions(&mut self) {
+        let defaults = Self::default();
+        self.solo.fill_missing_actions(&defaults.solo);
+        for (bindings, defaults) in self.versus.iter_mut().zip(&defaults.versus) {
+            bindings.fill_missing_actions(defaults);
+        }
+        self.gamepad.fill_missing_actions(&defaults.gamepad);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for versus in [false, true] {
+            let layout_name = if versus { "versus" } else { "solo" };
+            for (player, bindings) in self.get_layout(versus).iter().enumerate() {
+                for (action, keys) in &bindings.0 {
+                    for key in keys {
+                        if RESERVED_KEYS.contains(key) {
+                            return Err(format!(
+                                "controls.{0}: {1:?} is reserved and cannot be bound to {2}",
+                                layout_name, key, action
+                            ));
+                        }
+
+                        if let Some((other_player, other_action)) =
+                            self.find_binding(versus, *key, Some((player, *action)))
+                        {
+                            return Err(format!(
+                                "controls.{0}: {1:?} is bound to both {2} and {3}",
+                                layout_name,
+                                key,
+                                get_binding_name(versus, player, *action),
+                                get_binding_name(versus, other_player, other_action)
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (action, buttons) in &self.gamepad.0 {
+            for button in buttons {
+                if let Some(other_action) = self.gamepad.find_action(*button, Some(*action)) {
+                    return Err(format!(
+                        "controls.gamepad: {0:?} is bound to both {1} and {2}",
+                        button, action, other_action
+                    ));
+                }
+            }
+        }
+
+        if self.arr_ms == 0 {
+            return Err(String::from("controls.arr_ms must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+fn get_binding_name(versus: bool, player: usize, action: InputAction) -> String {
+    match versus {
+        true => format!("Player {0} {1}", player + 1, action),
+        false => action.to_string(),
+    }
+}
+
+// Options screen rebinding the keys, open while the game is paused
+#[derive(Resource)]
+pub struct ControlsScreen {
+    player: usize,
+    action: usize,
+    listening: bool,
+    message: String,
+    changed: bool,
+    paused_status: Option<GameStatus>, // Status to resume once closed, when opened while playing
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                open_controls_screen.run_if(
+                    not(resource_exists::<ControlsScreen>).and(not(resource_exists::<NameEntry>)),
+                ),
+                edit_controls.run_if(resource_exists::<ControlsScreen>),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn open_controls_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameStatus>>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+) {
+    if !keys.just_pressed(KeyCode::F1) {
+        return;
+    }
+
+    let paused_status = match state.get() {
+        GameStatus::Running | GameStatus::Countdown => {
+            next_state.set(GameStatus::Pause);
+            Some(state.get().clone())
+        }
+        _ => None,
+    };
+
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+            font_size: 20.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::Center,
+        TextColor(GREEN),
+        Transform::from_translation(Vec3::new(0.00, 0.00, 20.0)),
+        ControlsScreenText,
+    ));
+    commands.insert_resource(ControlsScreen {
+        player: 0,
+        action: 0,
+        listening: false,
+        message: String::new(),
+        changed: false,
+        paused_status,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn edit_controls(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut screen: ResMut<ControlsScreen>,
+    mut settings: ResMut<Settings>,
+    watcher: Res<SettingsWatcher>,
+    game_session: Res<GameSession>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    controls_text: Single<(Entity, &mut Text2d), With<ControlsScreenText>>,
+) {
+    let (entity, mut text) = controls_text.into_inner();
+    let versus = game_session.versus;
+    let players = settings.controls.get_layout(versus).len();
+    let action = InputAction::ALL[screen.action];
+
+    // The key opening the screen must not close it straight away
+    if screen.is_added() {
+        text.0 = get_controls_text(&screen, &settings, versus);
+        return;
+    }
+
+    let button = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.get_just_pressed().next().copied());
+
+    if screen.listening
+        && let Some(button) = button
+    {
+        screen.listening = false;
+        screen.message = match settings.controls.gamepad.find_action(button, Some(action)) {
+            Some(other_action) => format!(
+                "{0:?} is already bound to {1}, clear it first",
+                button, other_action
+            ),
+            None => {
+                settings.controls.gamepad.add_input(action, button);
+                screen.changed = true;
+                String::new()
+            }
+        };
+    } else if screen.listening {
+        let Some(key) = keys.get_just_pressed().next().copied() else {
+            return;
+        };
+
+        screen.listening = false;
+        screen.message = if key == KeyCode::Escape {
+            String::new()
+        } else if RESERVED_KEYS.contains(&key) {
+            format!("{0:?} is reserved", key)
+        } else {
+            match settings
+                .controls
+                .find_binding(versus, key, Some((screen.player, action)))
+            {
+                Some((other_player, other_action)) => format!(
+                    "{0:?} is already bound to {1}, clear it first",
+                    key,
+                    get_binding_name(versus, other_player, other_action)
+                ),
+                None => {
+                    settings.controls.get_layout_mut(versus)[screen.player].add_input(action, key);
+                    screen.changed = true;
+                    String::new()
+                }
+            }
+        };
+    } else if keys.any_just_pressed([KeyCode::F1, KeyCode::Escape]) {
+        if screen.changed
+            && let Err(error) = settings.save(watcher.get_path())
+        {
+            error!("{0}", error);
+        }
+        if let Some(paused_status) = &screen.paused_status {
+            next_state.set(paused_status.clone());
+        }
+
+        commands.entity(entity).despawn();
+        commands.remove_resource::<ControlsScreen>();
+        return;
+    } else if keys.just_pressed(KeyCode::ArrowUp) {
+        screen.action = (screen.action + InputAction::ALL.len() - 1) % InputAction::ALL.len();
+    } else if keys.just_pressed(KeyCode::ArrowDown) {
+        screen.action = (screen.action + 1) % InputAction::ALL.len();
+    } else if keys.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowRight]) {
+        screen.player = (screen.player + 1) % players;
+    } else if keys.just_pressed(KeyCode::Enter) {
+        screen.listening = true;
+        screen.message = format!(
+            "Press the key or the gamepad button for {0}, Escape to cancel",
+            get_binding_name(versus, screen.player, action)
+        );
+    } else if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        // The gamepad buttons are shared by all the players, so they are cleared on their own
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            settings.controls.gamepad.clear_inputs(action);
+        } else {
+            settings.controls.get_layout_mut(versus)[screen.player].clear_inputs(action);
+        }
+        screen.changed = true;
+        screen.message.clear();
+    }
+
+    if screen.is_changed() || settings.is_changed() {
+        text.0 = get_controls_text(&screen, &settings, versus);
+    }
+}
+
+fn get_controls_text(screen: &ControlsScreen, settings: &Settings, versus: bool) -> String {
+    let mut text = match versus {
+        true => format!("Controls - Player {0}\n\n", screen.player + 1),
+        false => String::from("Controls\n\n"),
+    };
+
+    let bindings = settings.controls.get_bindings(versus, screen.player as u8);
+    for (index, action) in InputAction::ALL.iter().enumerate() {
+        text.push_str(&format!(
+            "{0} {1:<24} {2:<28} {3}\n",
+            if index == screen.action { ">" } else { " " },
+            action.to_string(),
+            bindings.get_inputs_text(*action),
+            settings.controls.gamepad.get_inputs_text(*action)
+        ));
+    }
+
+    text.push_str("\n↑ ↓ select, Enter add a key or a button, Delete clear the keys, Shift + Delete clear the buttons");
+    if versus {
+        text.push_str(", ← → change player");
+    }
+    text.push_str(", F1 close\n");
+    text.push_str(&screen.message);
+    text
+}
@@ -1,4 +1,15 @@
-use bevy::prelude::{Component, Resource, States, Timer};
+use crate::consts::*;
+use crate::game::LockOutcome;
+use crate::game::level::LevelProgression;
+use crate::game::scoring::{GameScore, GameplaySettings};
+use crate::game::tetromino::TetrominoType;
+use bevy::prelude::{Component, Entity, Event, Resource, States, SystemSet, Timer, TimerMode};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::Entropy;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub use crate::game::input::PlayerInput;
 
 #[derive(Component)]
 pub struct TetrominoCell;
@@ -6,6 +17,9 @@ pub struct TetrominoCell;
 #[derive(Component)]
 pub struct UpcomingTetrominoCell;
 
+#[derive(Component)]
+pub struct HeldTetrominoCell;
+
 #[derive(Component)]
 pub struct OccupiedCell;
 
@@ -27,22 +41,173 @@ pub struct DropDownMsText;
 #[derive(Component)]
 pub struct PausedText;
 
+#[derive(Component)]
+pub struct PuzzleText;
+
+#[derive(Component)]
+pub struct PuzzleStatusText;
+
+#[derive(Component)]
+pub struct OpenerText;
+
+#[derive(Component)]
+pub struct CountdownText;
+
+#[derive(Component)]
+pub struct WinnerText;
+
+#[derive(Component)]
+pub struct IncomingGarbageBar;
+
+#[derive(Component)]
+pub struct NetworkStatusText;
+
+#[derive(Component)]
+pub struct PlaybackText;
+
+#[derive(Component)]
+pub struct SeedText;
+
+#[derive(Component)]
+pub struct GameOverText;
+
+#[derive(Component)]
+pub struct HighScoresText;
+
+#[derive(Component)]
+pub struct NameEntryText;
+
+#[derive(Component)]
+pub struct ControlsScreenText;
+
+#[derive(Component)]
+pub struct EditorText;
+
+// Every player owns a board, its settings and all the cells and texts displayed as its children
+#[derive(Component)]
+pub struct Player {
+    pub id: u8,
+}
+
+// Players driven by the keys and the gamepad buttons bound in the settings
+#[derive(Component)]
+pub struct LocalControls;
+
+// Player driven by the moves suggested by an external bot
+#[derive(Component)]
+pub struct BotControls;
+
+// Draws the holes of the garbage a player receives, apart from its tetrominoes so that taking
+// garbage does not change the sequence of tetrominoes shared with the opponents
+#[derive(Component, Clone)]
+pub struct GarbageRng(pub Entropy<ChaCha8Rng>);
+
+// Texts displaying the player's scores
+#[derive(Component)]
+pub struct PlayerHud {
+    pub score: Entity,
+    pub level: Entity,
+    pub cleared: Entity,
+    pub drop_down_ms: Entity,
+}
+
+#[derive(Event)]
+pub struct TetrominoLocked {
+    pub player: Entity,
+    pub outcome: LockOutcome,
+    pub attack_lines: u8, // Garbage lines sent to the opponents
+    pub tetromino: TetrominoType,
+    pub cells: Vec<u8>, // Where the tetromino has been locked
+}
+
+// Pausing, resuming or restarting the game without the keys, such as from the remote control
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub enum StatusRequest {
+    Pause,
+    Resume,
+    Restart,
+}
+
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum GameStatus {
+    Countdown,
     #[default]
     Running,
-    RemovingFilledRows,
     GameOver,
     Pause,
+    Editor,
+}
+
+// Steps of the game simulation, run at a fixed rate
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameplaySet {
+    Input,
+    Play,
+    Garbage,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardStatus {
+    #[default]
+    Playing,
+    RemovingFilledRows,
+    ToppedOut,
+}
+
+// Steps simulated since the game started, the game logic never reads the time of the frame
+#[derive(Resource)]
+pub struct SimulationClock {
+    pub tick: u64,
+    pub step: Duration,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            step: Duration::from_secs_f64(1.0 / SIMULATION_STEPS_PER_SECOND),
+        }
+    }
 }
 
 #[derive(Resource)]
+pub struct GameSession {
+    pub versus: bool,
+    pub seed: Option<String>, // Name of the seed shared by the players, random when missing
+    pub current_seed: String, // Name of the seed of the game being played
+    pub countdown_timer: Timer,
+    pub last_status: Option<GameStatus>,
+    pub assisted: bool, // Whether the built-in AI has played, keeping the scores out of the high scores
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct GameSettings {
     pub descend_timer: Timer,
     pub last_despawned_cell: Option<u8>,
     pub remove_filled_cells_times: Timer,
-    pub level: u16,
-    pub filled_up_lines: u32,
-    pub score: u32,
-    pub last_status: Option<GameStatus>,
+    #[serde(flatten)]
+    pub game_score: GameScore,
+    pub board_status: BoardStatus,
+}
+
+impl GameSettings {
+    pub fn new(
+        start_level: u16,
+        level_progression: LevelProgression,
+        gameplay: &GameplaySettings,
+    ) -> Self {
+        Self {
+            descend_timer: Timer::new(
+                Duration::from_millis(gameplay.get_drop_down_ms(start_level)),
+                TimerMode::Repeating,
+            ),
+            last_despawned_cell: None,
+            remove_filled_cells_times: Timer::new(
+                Duration::from_millis(gameplay.clean_up_occupied_rows_ms),
+                TimerMode::Repeating,
+            ),
+            game_score: GameScore::new(start_level, level_progression),
+            board_status: BoardStatus::Playing,
+        }
+    }
 }
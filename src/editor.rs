@@ -0,0 +1,320 @@
+use crate::consts::*;
+use crate::controls;
+use crate::ecs::*;
+use crate::game;
+use crate::game::fumen::{FUMEN_URL, Fumen};
+use crate::game::tetromino::TetrominoType;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+use std::path::{Path, PathBuf};
+
+// Keys choosing the color painted, in the order of TetrominoType::ALL, then gray
+const COLOR_KEYS: [KeyCode; 7] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+];
+const GRAY_KEY: KeyCode = KeyCode::Digit8;
+
+// Keys adding a tetromino to the queue, in the order of TetrominoType::ALL
+const QUEUE_KEYS: [KeyCode; 7] = [
+    KeyCode::KeyI,
+    KeyCode::KeyO,
+    KeyCode::KeyT,
+    KeyCode::KeyJ,
+    KeyCode::KeyL,
+    KeyCode::KeyS,
+    KeyCode::KeyZ,
+];
+
+// Position set up before playing it: the cells are painted on the board of the player
+#[derive(Resource)]
+pub struct Editor {
+    file: PathBuf,
+    paint: Option<TetrominoType>, // Gray when missing
+    queue: Vec<TetrominoType>,
+    held: Option<TetrominoType>,
+}
+
+// Paints the board with the mouse, sets the tetrominoes to play and starts the game from there
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_editor_text)
+            .add_systems(
+                Update,
+                (edit_position, show_position).chain().run_if(
+                    in_state(GameStatus::Editor)
+                        .and(not(resource_exists::<controls::ControlsScreen>)),
+                ),
+            )
+            .add_systems(Update, (back_to_editor, update_editor_text));
+    }
+}
+
+impl Editor {
+    pub fn new(file: PathBuf, position: Option<&Fumen>) -> Self {
+        Self {
+            file,
+            paint: Some(TetrominoType::I),
+            queue: position.map(Fumen::get_queue).unwrap_or_default(),
+            held: position.and_then(Fumen::get_hold),
+        }
+    }
+
+    fn save(&self, board: [u8; game::NUMBER_OF_CELLS as usize]) {
+        let fumen = Fumen::from_position(board, self.held.as_ref(), &self.queue);
+        let content = format!("{0}{1}\n", FUMEN_URL, fumen.encode());
+
+        let path = &self.file;
+        let result = match path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => {
+                std::fs::create_dir_all(directory)
+            }
+            _ => Ok(()),
+        }
+        .and_then(|_| std::fs::write(path, &content));
+        match result {
+            Ok(_) => println!("Position saved to {0}: {1}", path.display(), content.trim()),
+            Err(error) => eprintln!(
+                "Cannot save the position to '{0}': {1}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+// Reads the position saved by the editor as a fumen link, there is none until it is saved
+pub fn load_position(path: &Path) -> Result<Option<Fumen>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read the position '{0}': {1}", path.display(), error))?;
+    content
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|error| format!("Cannot read the position '{0}': {1}", path.display(), error))
+}
+
+fn setup_editor_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+            font_size: 20.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::TopLeft,
+        Transform::from_translation(Vec3::new(200.00, 150.00, 0.0)),
+        Visibility::Hidden,
+        EditorText,
+    ));
+}
+
+fn get_editor_text(editor: &Editor) -> String {
+    let paint = match &editor.paint {
+        Some(tetromino) => tetromino.to_string(),
+        None => String::from("gray"),
+    };
+    let queue: String = editor.queue.iter().map(|next| next.to_string()).collect();
+    let held = match &editor.held {
+        Some(tetromino) => tetromino.to_string(),
+        None => String::from("-"),
+    };
+
+    format!(
+        "Editor\n\
+         Color: {0} (1-7 I O T J L S Z, 8 gray)\n\
+         Queue: {1} (I O T J L S Z, Backspace)\n\
+         Hold: {2} (Tab)\n\n\
+         Left click paints, right click erases\n\
+         Delete clears the board\n\
+         Enter plays, F5 saves, F7 edits again",
+        paint, queue, held
+    )
+}
+
+fn update_editor_text(
+    state: Res<State<GameStatus>>,
+    editor: Res<Editor>,
+    mut editor_text: Single<(&mut Text2d, &mut Visibility), With<EditorText>>,
+) {
+    let (text, visibility) = &mut *editor_text;
+    let shown = match state.get() {
+        GameStatus::Editor => Visibility::Inherited,
+        _ => Visibility::Hidden,
+    };
+    if **visibility != shown {
+        **visibility = shown;
+    }
+
+    let content = get_editor_text(&editor);
+    if text.0 != content {
+        text.0 = content;
+    }
+}
+
+fn edit_position(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut editor: ResMut<Editor>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    mut players: Query<(&GlobalTransform, &mut game::GameBoard), With<Player>>,
+    mut status_requests: EventWriter<StatusRequest>,
+) {
+    for (key, tetromino) in COLOR_KEYS.iter().zip(TetrominoType::ALL) {
+        if keys.just_pressed(*key) {
+            editor.paint = Some(tetromino);
+        }
+    }
+    if keys.just_pressed(GRAY_KEY) {
+        editor.paint = None;
+    }
+
+    for (key, tetromino) in QUEUE_KEYS.iter().zip(TetrominoType::ALL) {
+        if keys.just_pressed(*key) {
+            editor.queue.push(tetromino);
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        editor.queue.pop();
+    }
+
+    // Tab goes through the tetrominoes to hold, and back to none
+    if keys.just_pressed(KeyCode::Tab) {
+        editor.held = match &editor.held {
+            Some(held) => TetrominoType::ALL
+                .iter()
+                .skip_while(|tetromino| *tetromino != held)
+                .nth(1)
+                .cloned(),
+            None => Some(TetrominoType::ALL[0].clone()),
+        };
+    }
+
+    let Ok((player_transform, mut game_board)) = players.single_mut() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Delete) {
+        game_board.clear();
+    }
+
+    // Dragging paints or erases every cell the pointer goes through
+    let value = if mouse_buttons.pressed(MouseButton::Left) {
+        match &editor.paint {
+            Some(tetromino) => Some(game::get_colored_cell(tetromino)),
+            None => Some(game::GARBAGE_CELL),
+        }
+    } else if mouse_buttons.pressed(MouseButton::Right) {
+        Some(0)
+    } else {
+        None
+    };
+    let (camera, camera_transform) = *camera;
+    if let Some(value) = value
+        && let Some(position) = window.cursor_position()
+        && let Ok(position) = camera.viewport_to_world_2d(camera_transform, position)
+        && let Some(cell) =
+            crate::get_board_cell_by_position(position - player_transform.translation().truncate())
+        && game_board.get_cell(cell) != value
+    {
+        game_board.set_cell(cell, value);
+    }
+
+    if keys.just_pressed(KeyCode::F5) {
+        editor.save(game_board.get_board());
+    }
+
+    // The game starts from the position, and from there again when restarted
+    if keys.just_pressed(KeyCode::Enter) {
+        let board = game_board.get_board();
+        game_board.set_initial_board(board);
+        game_board.set_script(editor.queue.clone());
+        game_board.set_initial_held(editor.held.clone());
+        status_requests.write(StatusRequest::Restart);
+    }
+}
+
+// Keeps the cells displayed in line with the board being painted, without the tetrominoes
+#[allow(clippy::type_complexity)]
+fn show_position(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+    players: Query<(Entity, Ref<game::GameBoard>, &Children), With<Player>>,
+    cells: Query<
+        Entity,
+        Or<(
+            With<TetrominoCell>,
+            With<OccupiedCell>,
+            With<UpcomingTetrominoCell>,
+            With<HeldTetrominoCell>,
+        )>,
+    >,
+) {
+    for (player, game_board, children) in players {
+        if !game_board.is_changed() {
+            continue;
+        }
+
+        for child in children.iter() {
+            if cells.contains(child) {
+                commands.entity(child).despawn();
+            }
+        }
+
+        let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+        crate::spawn_occupied_cells(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape,
+            &settings.display,
+        );
+    }
+}
+
+// Leaves the game for the position it started from
+#[allow(clippy::type_complexity)]
+fn back_to_editor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameStatus>>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    mut game_session: ResMut<GameSession>,
+    mut players: Query<(&mut game::GameBoard, &mut Entropy<ChaCha8Rng>), With<Player>>,
+    overlay_texts: Query<Entity, Or<(With<PausedText>, With<GameOverText>)>>,
+) {
+    if !keys.just_pressed(KeyCode::F7) || *state.get() == GameStatus::Editor {
+        return;
+    }
+
+    next_state.set(GameStatus::Editor);
+    game_session.last_status = None;
+
+    for entity in overlay_texts {
+        commands.entity(entity).despawn();
+    }
+
+    for (mut game_board, mut rng) in &mut players {
+        game_board.reset(&mut *rng);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::game::env::{Action, Environment, Observation, StepResult};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+// Requests read from the agent, one JSON message per line
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Reset { seed: Option<String> },
+    Step { action: Action },
+    Close,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Observation { observation: Observation },
+    Step(StepResult),
+    Error { reason: String },
+}
+
+// Serves the environment on the standard input and output until closed
+pub fn serve(mut env: Environment) -> Result<(), String> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    let mut games = 0_u64;
+
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|error| format!("Cannot read the request: {0}", error))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Reset { seed }) => {
+                games += 1;
+                let seed = seed.unwrap_or_else(|| games.to_string());
+                Response::Observation {
+                    observation: env.reset(&seed),
+                }
+            }
+            Ok(Request::Step { action }) => match env.step(&action) {
+                Ok(result) => Response::Step(result),
+                Err(reason) => Response::Error { reason },
+            },
+            Ok(Request::Close) => return Ok(()),
+            Err(error) => Response::Error {
+                reason: format!("Invalid request: {0}", error),
+            },
+        };
+
+        let response = serde_json::to_string(&response).map_err(|error| error.to_string())?;
+        writeln!(stdout, "{0}", response)
+            .and_then(|_| stdout.flush())
+            .map_err(|error| format!("Cannot send the response: {0}", error))?;
+    }
+
+    Ok(())
+}
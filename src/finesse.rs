@@ -0,0 +1,172 @@
+use crate::ecs::*;
+use crate::game;
+use crate::game::tetromino;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+// Placements of a player judged against the fewest presses reaching them
+#[derive(Component)]
+pub struct FinesseTracker {
+    board: [u8; game::NUMBER_OF_CELLS as usize], // Before the current tetromino locks
+    presses: usize,                              // Since the current tetromino spawned
+    judged: u32,
+    faults: u32,
+    last_fault: Option<String>,
+    text: Entity,
+}
+
+// Flags the tetrominoes placed with more inputs than needed, training to place them efficiently
+pub struct FinessePlugin;
+
+impl Plugin for FinessePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_finesse)
+            .add_systems(
+                FixedUpdate,
+                (
+                    track_placement.before(crate::hold_tetromino),
+                    count_presses
+                        .after(crate::hold_tetromino)
+                        .before(crate::move_and_rotate_tetromino),
+                )
+                    .in_set(GameplaySet::Play),
+            )
+            .add_systems(FixedUpdate, judge_placements.in_set(GameplaySet::Garbage))
+            .add_systems(Update, update_finesse_text);
+    }
+}
+
+impl FinesseTracker {
+    fn reset(&mut self) {
+        self.presses = 0;
+        self.judged = 0;
+        self.faults = 0;
+        self.last_fault = None;
+    }
+}
+
+fn setup_finesse(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Query<Entity, With<Player>>,
+) {
+    let text_font = TextFont {
+        font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    for player in players {
+        let text = commands
+            .spawn((
+                Text2d::new(""),
+                text_font.clone(),
+                TextLayout::new_with_justify(JustifyText::Left),
+                Anchor::TopLeft,
+                Transform::from_translation(Vec3::new(200.00, -200.00, 0.0)),
+                ChildOf(player),
+            ))
+            .id();
+
+        commands.entity(player).insert(FinesseTracker {
+            board: [0; game::NUMBER_OF_CELLS as usize],
+            presses: 0,
+            judged: 0,
+            faults: 0,
+            last_fault: None,
+            text,
+        });
+    }
+}
+
+// Keeps the board the current tetromino is placed on, starting again from its spawn on a hold
+fn track_placement(
+    clock: Res<SimulationClock>,
+    mut players: Query<(
+        &game::GameBoard,
+        &GameSettings,
+        &PlayerInput,
+        &mut FinesseTracker,
+    )>,
+) {
+    for (game_board, game_settings, input, mut tracker) in &mut players {
+        // A new game starts a new tally
+        if clock.tick == 0 {
+            tracker.reset();
+        }
+
+        if game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        tracker.board = game_board.get_board();
+
+        // The tetromino coming out of the hold starts from where it spawns
+        if input.hold && game_board.can_hold_tetromino() {
+            tracker.presses = 0;
+        }
+    }
+}
+
+// Counts the keys and the buttons pressed for the moves and the rotations, also the ones
+// blocked by the walls or the cells, a direction held down counting once
+fn count_presses(mut players: Query<(&GameSettings, &PlayerInput, &mut FinesseTracker)>) {
+    for (game_settings, input, mut tracker) in &mut players {
+        if game_settings.board_status == BoardStatus::Playing && input.is_move_pressed() {
+            tracker.presses += 1;
+        }
+    }
+}
+
+fn judge_placements(
+    mut locked_events: EventReader<TetrominoLocked>,
+    mut players: Query<(&game::GameBoard, &mut FinesseTracker)>,
+) {
+    for event in locked_events.read() {
+        let Ok((game_board, mut tracker)) = players.get_mut(event.player) else {
+            continue;
+        };
+
+        let presses = std::mem::take(&mut tracker.presses);
+        let Some(fewest) = tetromino::get_fewest_steps(
+            &event.tetromino,
+            game_board.get_scale(),
+            &tracker.board,
+            &event.cells,
+        ) else {
+            continue;
+        };
+
+        tracker.judged += 1;
+        if presses > fewest {
+            tracker.faults += 1;
+            tracker.last_fault = Some(format!(
+                "{0} placed with {1} inputs, {2} needed",
+                event.tetromino, presses, fewest
+            ));
+        }
+    }
+}
+
+fn get_finesse_text(tracker: &FinesseTracker) -> String {
+    let mut text = format!("Finesse faults: {0}/{1}", tracker.faults, tracker.judged);
+    if let Some(last_fault) = &tracker.last_fault {
+        text.push('\n');
+        text.push_str(last_fault);
+    }
+    text
+}
+
+fn update_finesse_text(
+    players: Query<&FinesseTracker, Changed<FinesseTracker>>,
+    mut texts: Query<&mut Text2d>,
+) {
+    for tracker in players {
+        if let Ok(mut text) = texts.get_mut(tracker.text) {
+            let content = get_finesse_text(tracker);
+            if text.0 != content {
+                text.0 = content;
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::ecs::*;
+use crate::game;
+use crate::game::fumen::{FUMEN_URL, Fumen, FumenPage, FumenPiece};
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+// Records every tetromino locked as a page of a fumen diagram, printed with F6
+pub struct FumenPlugin;
+
+#[derive(Resource, Default)]
+pub struct FumenRecorder {
+    boards: BTreeMap<u8, [u8; game::NUMBER_OF_CELLS as usize]>, // Before the step being played
+    pages: BTreeMap<u8, Vec<FumenPage>>,
+}
+
+impl Plugin for FumenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FumenRecorder>()
+            .add_systems(FixedUpdate, record_boards.in_set(GameplaySet::Input))
+            .add_systems(FixedUpdate, record_pages.in_set(GameplaySet::Garbage))
+            .add_systems(Update, export_fumen);
+    }
+}
+
+fn record_boards(
+    mut recorder: ResMut<FumenRecorder>,
+    clock: Res<SimulationClock>,
+    players: Query<(&Player, &game::GameBoard)>,
+) {
+    // A new game starts a new diagram
+    if clock.tick == 0 {
+        recorder.pages.clear();
+    }
+
+    for (player, game_board) in &players {
+        recorder.boards.insert(player.id, game_board.get_board());
+    }
+}
+
+fn record_pages(
+    mut recorder: ResMut<FumenRecorder>,
+    mut locked_events: EventReader<TetrominoLocked>,
+    players: Query<&Player>,
+) {
+    for event in locked_events.read() {
+        let Ok(player) = players.get(event.player) else {
+            continue;
+        };
+        let Some(board) = recorder.boards.get(&player.id).copied() else {
+            continue;
+        };
+
+        let page = get_page(board, event.tetromino.clone(), event.cells.clone());
+        recorder.pages.entry(player.id).or_default().push(page);
+    }
+}
+
+fn export_fumen(
+    keys: Res<ButtonInput<KeyCode>>,
+    recorder: Res<FumenRecorder>,
+    players: Query<(&Player, &game::GameBoard, &GameSettings)>,
+) {
+    if !keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    let mut players: Vec<_> = players.iter().collect();
+    players.sort_by_key(|(player, _, _)| player.id);
+
+    for (player, game_board, game_settings) in players {
+        let mut pages = recorder.pages.get(&player.id).cloned().unwrap_or_default();
+
+        // The tetromino being played ends the diagram, where it stands
+        if game_settings.board_status == BoardStatus::Playing {
+            pages.push(get_page(
+                game_board.get_board(),
+                game_board.get_current_tetromino_type().clone(),
+                game_board.get_current_tetromino_cells(),
+            ));
+        }
+        if pages.is_empty() {
+            continue;
+        }
+
+        println!(
+            "Fumen of player {0}: {1}{2}",
+            player.id + 1,
+            FUMEN_URL,
+            Fumen::new(pages).encode()
+        );
+    }
+}
+
+fn get_page(
+    board: [u8; game::NUMBER_OF_CELLS as usize],
+    tetromino: game::tetromino::TetrominoType,
+    mut cells: Vec<u8>,
+) -> FumenPage {
+    cells.sort();
+    FumenPage {
+        board,
+        piece: Some(FumenPiece { tetromino, cells }),
+        comment: String::new(),
+    }
+}
@@ -1,33 +1,158 @@
-use crate::game::tetromino::{DroppedStatus, MoveDirection, TetrominoType};
-use bevy::prelude::*;
+use crate::game::scoring::{GameScore, GameplaySettings};
+use crate::game::tetromino::{
+    DroppedStatus, MoveDirection, Placement, PlacementStep, RotationDirection, TetrominoType,
+};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
 
+pub mod attack;
+pub mod env;
+pub mod fumen;
+pub mod heuristic;
+pub mod input;
+pub mod level;
+pub mod opener;
+pub mod puzzle;
+pub mod scoring;
 pub mod tetromino;
 
 pub const NUMBER_OF_ROWS: u8 = 20;
 pub const NUMBER_OF_COLUMNS: u8 = 10;
 pub const NUMBER_OF_CELLS: u8 = NUMBER_OF_ROWS * NUMBER_OF_COLUMNS;
+pub const LOCKED_CELL: u8 = 1;
+pub const GARBAGE_CELL: u8 = 2;
+// Cells locked with the color of a tetromino, such as the painted ones, in the order of TetrominoType::ALL
+pub const COLORED_CELL: u8 = 3;
 
-#[derive(Debug, Resource)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Marathon,
+    Big,
+    Puzzle,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
 pub struct GameBoard {
+    #[serde(with = "board_cells")]
     board: [u8; NUMBER_OF_COLUMNS as usize * NUMBER_OF_ROWS as usize],
     provider: Option<tetromino::TetrominoProvider>,
+    scale: u8,
+    // Board and tetromino sequence the game starts from, also restored on reset
+    #[serde(with = "board_cells")]
+    initial_board: [u8; NUMBER_OF_CELLS as usize],
+    script: Vec<TetrominoType>,
+    #[serde(default)]
+    initial_held: Option<TetrominoType>,
+    attack: attack::AttackState,
+}
+
+// Board, tetrominoes and attack of a game, copied to go back to them later
+#[derive(Clone, Debug)]
+pub struct GameBoardSnapshot {
+    board: [u8; NUMBER_OF_CELLS as usize],
+    provider: tetromino::TetrominoProvider,
+    attack: attack::AttackState,
+}
+
+// What happened when a tetromino has been locked on the board
+#[derive(Clone, Debug)]
+pub struct LockOutcome {
+    pub cleared_lines: u8,
+    pub t_spin: bool,
+    pub perfect_clear: bool,
+    pub garbage_left: bool,
+    pub topped_out: bool,
+}
+
+// Tetromino just locked, once scored
+#[derive(Clone, Debug)]
+pub struct LockedTetromino {
+    pub outcome: LockOutcome,
+    pub attack_lines: u8, // Garbage lines sent to the opponents
+    pub points: u32,
+    pub garbage_added: bool, // Whether the board has been pushed up by the garbage received
+}
+
+// Boards are serialized as a list of cells, as serde handles arrays of up to 32 items only
+mod board_cells {
+    use super::NUMBER_OF_CELLS;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        cells: &[u8; NUMBER_OF_CELLS as usize],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        cells.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[u8; NUMBER_OF_CELLS as usize], D::Error> {
+        let cells = Vec::<u8>::deserialize(deserializer)?;
+        cells
+            .try_into()
+            .map_err(|_| D::Error::custom("Invalid number of board cells"))
+    }
+}
+
+impl GameMode {
+    // Gets the number of board cells per side covered by every mino
+    pub fn get_scale(&self) -> u8 {
+        match self {
+            GameMode::Marathon => 1,
+            GameMode::Big => 2,
+            GameMode::Puzzle => 1,
+        }
+    }
 }
 
 impl GameBoard {
-    pub fn new() -> Self {
+    pub fn new(mode: &GameMode) -> Self {
         GameBoard {
             board: [0; NUMBER_OF_CELLS as usize],
             provider: None,
+            scale: mode.get_scale(),
+            initial_board: [0; NUMBER_OF_CELLS as usize],
+            script: Vec::new(),
+            initial_held: None,
+            attack: attack::AttackState::new(),
         }
     }
 
+    pub fn get_scale(&self) -> u8 {
+        self.scale
+    }
+
+    pub fn set_initial_board(&mut self, board: [u8; NUMBER_OF_CELLS as usize]) {
+        self.initial_board = board;
+        self.board = board;
+    }
+
+    pub fn set_script(&mut self, script: Vec<TetrominoType>) {
+        self.script = script;
+    }
+
+    pub fn set_initial_held(&mut self, held: Option<TetrominoType>) {
+        self.initial_held = held;
+    }
+
     pub fn init<R>(&mut self, rng: &mut R)
     where
         R: Rng + ?Sized,
     {
         if self.provider.is_none() {
-            self.provider = Some(tetromino::TetrominoProvider::new(rng));
+            self.provider = Some(tetromino::TetrominoProvider::new(
+                rng,
+                self.scale,
+                &self.script,
+                self.initial_held.clone(),
+            ));
         }
     }
 
@@ -46,10 +171,15 @@ impl GameBoard {
     where
         R: Rng + ?Sized,
     {
-        if let Some(provider) = &mut self.provider {
-            self.board = [0; NUMBER_OF_ROWS as usize * NUMBER_OF_COLUMNS as usize];
-            provider.next(rng, &self.board);
-            provider.next(rng, &self.board);
+        if self.provider.is_some() {
+            self.board = self.initial_board;
+            self.attack.reset();
+            self.provider = Some(tetromino::TetrominoProvider::new(
+                rng,
+                self.scale,
+                &self.script,
+                self.initial_held.clone(),
+            ));
         } else {
             panic!("Provider has not been initialized.");
         }
@@ -71,7 +201,7 @@ impl GameBoard {
         }
     }
 
-    pub fn get_current_tetromino_cells(&self) -> [u8; 4] {
+    pub fn get_current_tetromino_cells(&self) -> Vec<u8> {
         if let Some(provider) = &self.provider {
             provider.get_current_tetromino_cells()
         } else {
@@ -79,7 +209,7 @@ impl GameBoard {
         }
     }
 
-    pub fn get_upcoming_tetromino_cells(&self) -> [u8; 4] {
+    pub fn get_upcoming_tetromino_cells(&self) -> Vec<u8> {
         if let Some(provider) = &self.provider {
             provider.get_upcoming_tetromino_cells()
         } else {
@@ -91,13 +221,10 @@ impl GameBoard {
         if let Some(provider) = &mut self.provider {
             let dropped_status = provider.drop_down(&self.board);
 
-            match dropped_status {
-                DroppedStatus::NotDropped(occupied_cells) => {
-                    for cell in occupied_cells {
-                        self.board[cell as usize] = 1;
-                    }
+            if let DroppedStatus::NotDropped(occupied_cells) = &dropped_status {
+                for cell in occupied_cells {
+                    self.board[*cell as usize] = LOCKED_CELL;
                 }
-                _ => {}
             }
 
             dropped_status
@@ -106,10 +233,37 @@ impl GameBoard {
         }
     }
 
+    pub fn get_board(&self) -> [u8; NUMBER_OF_CELLS as usize] {
+        self.board
+    }
+
+    // Gets what fills the cell: 0 when empty, LOCKED_CELL, GARBAGE_CELL or a colored cell
+    pub fn get_cell(&self, cell: u8) -> u8 {
+        self.board[cell as usize]
+    }
+
+    pub fn set_cell(&mut self, cell: u8, value: u8) {
+        self.board[cell as usize] = value;
+    }
+
+    pub fn clear(&mut self) {
+        self.board = [0; NUMBER_OF_CELLS as usize];
+    }
+
     pub fn is_cell_occupied(&self, cell: u8) -> bool {
         self.board[cell as usize] != 0
     }
 
+    // Plays a command of a player on the current tetromino, a soft drop stopping on the floor
+    // rather than locking it
+    pub fn apply_step(&mut self, step: PlacementStep) -> tetromino::MoveStatus {
+        if let Some(provider) = &mut self.provider {
+            provider.apply_current_step(step, &self.board)
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
     pub fn move_tetromino(&mut self, direction: MoveDirection) -> tetromino::MoveStatus {
         if let Some(provider) = &mut self.provider {
             provider.move_current(direction, &self.board)
@@ -152,6 +306,16 @@ impl GameBoard {
         result
     }
 
+    fn is_row_empty(&self, row: u8) -> bool {
+        for cell in Self::get_row_cells(row) {
+            if self.is_cell_occupied(cell) {
+                return false;
+            }
+        }
+
+        true
+    }
+
     fn is_row_filled(&self, row: u8) -> bool {
         for cell in Self::get_row_cells(row) {
             if !self.is_cell_occupied(cell) {
@@ -162,6 +326,11 @@ impl GameBoard {
         true
     }
 
+    // Gets the number of logical lines filled up, where a scaled line spans several rows
+    pub fn get_number_of_filled_lines(&self) -> u8 {
+        self.get_number_of_filled_rows() / self.scale
+    }
+
     pub fn get_number_of_filled_rows(&self) -> u8 {
         let mut number_of_filled_rows = 0;
         for row in 0..NUMBER_OF_ROWS {
@@ -173,6 +342,174 @@ impl GameBoard {
         number_of_filled_rows
     }
 
+    // Checks whether the board will be empty once the filled rows are collapsed
+    pub fn is_perfect_clear(&self) -> bool {
+        for row in 0..NUMBER_OF_ROWS {
+            if !self.is_row_filled(row) && !self.is_row_empty(row) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Checks whether any garbage cell will survive the collapse of the filled rows
+    pub fn has_garbage_left(&self) -> bool {
+        for row in 0..NUMBER_OF_ROWS {
+            if self.is_row_filled(row) {
+                continue;
+            }
+
+            for cell in Self::get_row_cells(row) {
+                if self.board[cell as usize] == GARBAGE_CELL {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    pub fn is_current_tetromino_t_spin(&self) -> bool {
+        if let Some(provider) = &self.provider {
+            provider.is_current_t_spin(&self.board)
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    // Pushes the board up adding rows of garbage with a single hole at the bottom,
+    // returns false when occupied cells have been pushed out of the board
+    pub fn add_garbage_rows<R>(&mut self, lines: u8, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let rows = std::cmp::min(NUMBER_OF_ROWS, lines.saturating_mul(self.scale));
+        let hole_col = rng.random_range(0..(NUMBER_OF_COLUMNS / self.scale)) * self.scale;
+
+        let mut fits = true;
+        for row in 0..rows {
+            if !self.is_row_empty(row) {
+                fits = false;
+            }
+        }
+
+        for row in 0..NUMBER_OF_ROWS {
+            for col in 0..NUMBER_OF_COLUMNS {
+                let target_cell =
+                    tetromino::Tetromino::get_cell_from_row_and_column(row, col) as usize;
+                self.board[target_cell] = if row + rows < NUMBER_OF_ROWS {
+                    let source_cell =
+                        tetromino::Tetromino::get_cell_from_row_and_column(row + rows, col);
+                    self.board[source_cell as usize]
+                } else if col >= hole_col && col < hole_col + self.scale {
+                    0
+                } else {
+                    GARBAGE_CELL
+                };
+            }
+        }
+
+        fits
+    }
+
+    // Scores the tetromino just locked, lets in the garbage ready when no line has been cleared
+    // and brings in the next tetromino. The filled rows are left on the board to be collapsed.
+    pub fn lock_tetromino<R, G>(
+        &mut self,
+        rng: &mut R,
+        garbage_rng: &mut G,
+        game_score: &mut GameScore,
+        gameplay: &GameplaySettings,
+    ) -> LockedTetromino
+    where
+        R: Rng + ?Sized,
+        G: Rng + ?Sized,
+    {
+        let mut outcome = LockOutcome {
+            cleared_lines: self.get_number_of_filled_lines(),
+            t_spin: self.is_current_tetromino_t_spin(),
+            perfect_clear: self.is_perfect_clear(),
+            garbage_left: self.has_garbage_left(),
+            topped_out: false,
+        };
+        let attack_lines = self.record_attack(&outcome);
+
+        let garbage_added = outcome.cleared_lines == 0 && self.get_ready_garbage() > 0;
+        let garbage_fits = !garbage_added || self.add_ready_garbage(garbage_rng);
+
+        let points = game_score.record_lock(outcome.cleared_lines, gameplay);
+
+        let can_spawn_more_tetromino = self.next_tetromino(rng);
+        outcome.topped_out = !garbage_fits
+            || matches!(
+                can_spawn_more_tetromino,
+                tetromino::CanSpawnMoreTetromino::No
+            );
+
+        LockedTetromino {
+            outcome,
+            attack_lines,
+            points,
+            garbage_added,
+        }
+    }
+
+    // Gets the lines sent to the opponents by the tetromino just locked
+    pub fn record_attack(&mut self, outcome: &LockOutcome) -> u8 {
+        self.attack.record_lock(outcome)
+    }
+
+    pub fn receive_garbage(&mut self, lines: u8) {
+        self.attack.receive(lines);
+    }
+
+    pub fn tick_garbage(&mut self, elapsed: Duration) {
+        self.attack.tick(elapsed);
+    }
+
+    pub fn get_combo(&self) -> u8 {
+        self.attack.get_combo()
+    }
+
+    pub fn is_back_to_back(&self) -> bool {
+        self.attack.is_back_to_back()
+    }
+
+    pub fn get_incoming_garbage(&self) -> u8 {
+        self.attack.get_incoming_lines()
+    }
+
+    pub fn get_ready_garbage(&self) -> u8 {
+        self.attack.get_ready_lines()
+    }
+
+    // Pushes the garbage whose delay has expired into the board, returns false when it does not fit
+    pub fn add_ready_garbage<R>(&mut self, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let lines = self.attack.take_ready_garbage();
+        lines == 0 || self.add_garbage_rows(lines, rng)
+    }
+
+    // Gets a fingerprint of the board and of the tetrominoes, equal on every machine playing the same game
+    pub fn get_hash(&self) -> u64 {
+        // Only bytes are hashed, so the fingerprint does not depend on the platform
+        let mut bytes = self.board.to_vec();
+        if let Some(provider) = &self.provider {
+            bytes.push(provider.get_current_tetromino_type().clone() as u8);
+            bytes.extend(provider.get_current_tetromino_cells());
+            bytes.push(provider.get_upcoming_tetromino_type().clone() as u8);
+            bytes.push(match provider.get_held_tetromino_type() {
+                Some(held) => held.clone() as u8,
+                None => u8::MAX,
+            });
+        }
+        bytes.push(self.get_incoming_garbage());
+        crate::seed::get_fnv_hash(bytes)
+    }
+
     pub fn collapse_filled_rows(&mut self) {
         for row in (0..NUMBER_OF_ROWS).rev() {
             while self.is_row_filled(row) {
@@ -202,11 +539,173 @@ impl GameBoard {
         }
     }
 
-    pub fn rotate_tetromino(&mut self) -> tetromino::MoveStatus {
+    // Takes what changes while playing, the filled rows being already collapsed, and
+    // leaves out the position the game starts from
+    pub fn snapshot(&self) -> GameBoardSnapshot {
+        let Some(provider) = &self.provider else {
+            panic!("Provider has not been initialized.");
+        };
+
+        let board = if self.get_number_of_filled_rows() > 0 {
+            let mut collapsed = self.clone();
+            collapsed.collapse_filled_rows();
+            collapsed.board
+        } else {
+            self.board
+        };
+
+        GameBoardSnapshot {
+            board,
+            provider: provider.clone(),
+            attack: self.attack.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &GameBoardSnapshot) {
+        self.board = snapshot.board;
+        self.provider = Some(snapshot.provider.clone());
+        self.attack = snapshot.attack.clone();
+    }
+
+    pub fn hold_tetromino<R>(&mut self, rng: &mut R) -> tetromino::MoveStatus
+    where
+        R: Rng + ?Sized,
+    {
+        if let Some(provider) = &mut self.provider {
+            provider.hold_current(rng, &self.board)
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    // Whether the current tetromino has not been swapped with the held one yet
+    pub fn can_hold_tetromino(&self) -> bool {
+        if let Some(provider) = &self.provider {
+            provider.can_hold()
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    pub fn get_held_tetromino_type(&self) -> Option<&TetrominoType> {
+        if let Some(provider) = &self.provider {
+            provider.get_held_tetromino_type()
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    pub fn get_held_tetromino_cells(&self) -> Vec<u8> {
+        if let Some(provider) = &self.provider {
+            provider.get_held_tetromino_cells()
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    // Gets every place where the current tetromino can lock, and how to get there
+    pub fn find_placements(&self) -> Vec<Placement> {
+        if let Some(provider) = &self.provider {
+            provider.find_current_placements(&self.board)
+        } else {
+            panic!("Provider has not been initialized.");
+        }
+    }
+
+    pub fn rotate_tetromino(&mut self, direction: RotationDirection) -> tetromino::MoveStatus {
         if let Some(provider) = &mut self.provider {
-            provider.rotate_current(&self.board)
+            provider.rotate_current(direction, &self.board)
         } else {
             panic!("Provider has not been initialized.");
         }
     }
 }
+
+pub fn get_colored_cell(tetromino: &TetrominoType) -> u8 {
+    let index = TetrominoType::ALL
+        .iter()
+        .position(|candidate| candidate == tetromino)
+        .unwrap_or_default();
+    COLORED_CELL + index as u8
+}
+
+// Gets the tetromino whose color fills the cell, if any
+pub fn get_cell_tetromino(value: u8) -> Option<TetrominoType> {
+    value
+        .checked_sub(COLORED_CELL)
+        .and_then(|index| TetrominoType::ALL.get(index as usize))
+        .cloned()
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "marathon" => Ok(GameMode::Marathon),
+            "big" => Ok(GameMode::Big),
+            "puzzle" => Ok(GameMode::Puzzle),
+            _ => Err(format!("Unknown game mode '{0}'", value)),
+        }
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            GameMode::Marathon => write!(f, "Marathon"),
+            GameMode::Big => write!(f, "Big"),
+            GameMode::Puzzle => write!(f, "Puzzle"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tetromino::MoveStatus;
+    use bevy_prng::ChaCha8Rng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn soft_drop_stops_on_the_floor() {
+        let mut game_board = GameBoard::new(&GameMode::Marathon);
+        game_board.init(&mut ChaCha8Rng::from_seed([0; 32]));
+
+        while game_board.apply_step(PlacementStep::SoftDrop) == MoveStatus::Moved {}
+        let cells = game_board.get_current_tetromino_cells();
+        assert_eq!(
+            game_board.apply_step(PlacementStep::SoftDrop),
+            MoveStatus::NotMoved
+        );
+        assert_eq!(game_board.get_board(), [0; NUMBER_OF_CELLS as usize]);
+
+        // The tetromino is still played, away from the cells it was dropped on
+        let moved = [PlacementStep::Left, PlacementStep::Right]
+            .into_iter()
+            .any(|step| game_board.apply_step(step) == MoveStatus::Moved);
+        assert!(moved);
+        assert_ne!(game_board.get_current_tetromino_cells(), cells);
+        assert_eq!(game_board.get_board(), [0; NUMBER_OF_CELLS as usize]);
+    }
+
+    #[test]
+    fn sliding_to_the_wall_takes_a_single_press() {
+        let mut game_board = GameBoard::new(&GameMode::Marathon);
+        game_board.init(&mut ChaCha8Rng::from_seed([0; 32]));
+        let tetromino = game_board.get_current_tetromino_type().clone();
+
+        let mut moves = 0;
+        while game_board.apply_step(PlacementStep::Left) == MoveStatus::Moved {
+            moves += 1;
+        }
+        while game_board.apply_step(PlacementStep::SoftDrop) == MoveStatus::Moved {}
+        let cells = game_board.get_current_tetromino_cells();
+
+        assert!(moves > 1);
+        assert_eq!(
+            tetromino::get_fewest_steps(&tetromino, 1, &game_board.get_board(), &cells),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::game::LockOutcome;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Time the received garbage waits before it can enter the board
+pub const GARBAGE_DELAY_MS: u64 = 1000;
+pub const PERFECT_CLEAR_ATTACK: u8 = 10;
+pub const BACK_TO_BACK_ATTACK: u8 = 1;
+
+// Extra lines sent by consecutive clears, the last value applies to any longer combo
+const COMBO_ATTACK: [u8; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct IncomingGarbage {
+    lines: u8,
+    remaining: Duration,
+}
+
+// Attack sent and garbage received by a board, independent of how the game is displayed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AttackState {
+    combo: u8, // Number of consecutive locks clearing lines
+    back_to_back: bool,
+    incoming: VecDeque<IncomingGarbage>,
+}
+
+// Gets the lines sent for the clear alone, without combo and back to back bonuses
+pub fn get_clear_attack(cleared_lines: u8, t_spin: bool) -> u8 {
+    match (cleared_lines, t_spin) {
+        (0, _) => 0,
+        (1, false) => 0,
+        (2, false) => 1,
+        (3, false) => 2,
+        (_, false) => 4,
+        (1, true) => 2,
+        (2, true) => 4,
+        (_, true) => 6,
+    }
+}
+
+// Clears keeping the back to back chain alive
+fn is_difficult_clear(outcome: &LockOutcome) -> bool {
+    outcome.cleared_lines >= 4 || (outcome.t_spin && outcome.cleared_lines > 0)
+}
+
+impl AttackState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Gets the lines attacking the opponents once the incoming garbage has been cancelled
+    pub fn record_lock(&mut self, outcome: &LockOutcome) -> u8 {
+        if outcome.cleared_lines == 0 {
+            self.combo = 0;
+            return 0;
+        }
+
+        let difficult_clear = is_difficult_clear(outcome);
+        let mut attack = get_clear_attack(outcome.cleared_lines, outcome.t_spin);
+        attack += COMBO_ATTACK[std::cmp::min(self.combo as usize, COMBO_ATTACK.len() - 1)];
+        if difficult_clear && self.back_to_back {
+            attack += BACK_TO_BACK_ATTACK;
+        }
+        if outcome.perfect_clear {
+            attack += PERFECT_CLEAR_ATTACK;
+        }
+
+        self.combo = self.combo.saturating_add(1);
+        self.back_to_back = difficult_clear;
+
+        self.cancel(attack)
+    }
+
+    // Cancels the oldest incoming garbage first, returns the attack left
+    fn cancel(&mut self, mut attack: u8) -> u8 {
+        while attack > 0 {
+            let Some(garbage) = self.incoming.front_mut() else {
+                break;
+            };
+
+            let cancelled = std::cmp::min(attack, garbage.lines);
+            garbage.lines -= cancelled;
+            attack -= cancelled;
+            if garbage.lines == 0 {
+                self.incoming.pop_front();
+            }
+        }
+
+        attack
+    }
+
+    pub fn receive(&mut self, lines: u8) {
+        if lines > 0 {
+            self.incoming.push_back(IncomingGarbage {
+                lines,
+                remaining: Duration::from_millis(GARBAGE_DELAY_MS),
+            });
+        }
+    }
+
+    pub fn tick(&mut self, elapsed: Duration) {
+        for garbage in self.incoming.iter_mut() {
+            garbage.remaining = garbage.remaining.saturating_sub(elapsed);
+        }
+    }
+
+    // Removes the garbage whose delay has expired, returning its lines
+    pub fn take_ready_garbage(&mut self) -> u8 {
+        let mut lines: u8 = 0;
+        while let Some(garbage) = self.incoming.front() {
+            if !garbage.remaining.is_zero() {
+                break;
+            }
+
+            lines = lines.saturating_add(garbage.lines);
+            self.incoming.pop_front();
+        }
+
+        lines
+    }
+
+    pub fn get_combo(&self) -> u8 {
+        self.combo
+    }
+
+    pub fn is_back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    pub fn get_incoming_lines(&self) -> u8 {
+        self.incoming
+            .iter()
+            .fold(0, |lines: u8, garbage| lines.saturating_add(garbage.lines))
+    }
+
+    pub fn get_ready_lines(&self) -> u8 {
+        self.incoming
+            .iter()
+            .filter(|garbage| garbage.remaining.is_zero())
+            .fold(0, |lines: u8, garbage| lines.saturating_add(garbage.lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(cleared_lines: u8, t_spin: bool) -> LockOutcome {
+        LockOutcome {
+            cleared_lines,
+            t_spin,
+            perfect_clear: false,
+            garbage_left: false,
+            topped_out: false,
+        }
+    }
+
+    #[test]
+    fn clears_attack_by_lines_and_t_spin() {
+        let expected = [
+            ((0, false), 0),
+            ((1, false), 0),
+            ((2, false), 1),
+            ((3, false), 2),
+            ((4, false), 4),
+            ((0, true), 0),
+            ((1, true), 2),
+            ((2, true), 4),
+            ((3, true), 6),
+        ];
+        for ((cleared_lines, t_spin), attack) in expected {
+            assert_eq!(get_clear_attack(cleared_lines, t_spin), attack);
+            assert_eq!(
+                AttackState::new().record_lock(&clear(cleared_lines, t_spin)),
+                attack
+            );
+        }
+    }
+
+    #[test]
+    fn consecutive_clears_add_the_combo() {
+        let mut state = AttackState::new();
+        let attacks: Vec<u8> = (0..13)
+            .map(|_| state.record_lock(&clear(1, false)))
+            .collect();
+        assert_eq!(attacks, [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5, 5, 5]);
+        assert_eq!(state.get_combo(), 13);
+
+        // A lock clearing no line ends the combo
+        assert_eq!(state.record_lock(&clear(0, false)), 0);
+        assert_eq!(state.get_combo(), 0);
+        assert_eq!(state.record_lock(&clear(2, false)), 1);
+    }
+
+    #[test]
+    fn difficult_clears_in_a_row_add_the_back_to_back() {
+        let mut state = AttackState::new();
+        assert_eq!(state.record_lock(&clear(4, false)), 4);
+        assert!(state.is_back_to_back());
+
+        // Placing a tetromino without clearing keeps the chain alive
+        state.record_lock(&clear(0, false));
+        assert_eq!(state.record_lock(&clear(2, true)), 4 + BACK_TO_BACK_ATTACK);
+        assert!(state.is_back_to_back());
+
+        // An easy clear breaks the chain
+        state.record_lock(&clear(0, false));
+        assert_eq!(state.record_lock(&clear(2, false)), 1);
+        assert!(!state.is_back_to_back());
+        state.record_lock(&clear(0, false));
+        assert_eq!(state.record_lock(&clear(4, false)), 4);
+    }
+
+    #[test]
+    fn perfect_clears_add_their_bonus() {
+        let mut outcome = clear(4, false);
+        outcome.perfect_clear = true;
+        assert_eq!(
+            AttackState::new().record_lock(&outcome),
+            4 + PERFECT_CLEAR_ATTACK
+        );
+    }
+
+    #[test]
+    fn attacks_cancel_the_oldest_incoming_garbage_first() {
+        let mut state = AttackState::new();
+        state.receive(0);
+        state.receive(3);
+        state.receive(2);
+        assert_eq!(state.get_incoming_lines(), 5);
+
+        // The tetris cancels the first garbage and part of the second one
+        assert_eq!(state.record_lock(&clear(4, false)), 0);
+        assert_eq!(state.get_incoming_lines(), 1);
+
+        // What is left of the back to back tetris goes to the opponents
+        state.record_lock(&clear(0, false));
+        assert_eq!(
+            state.record_lock(&clear(4, false)),
+            4 + BACK_TO_BACK_ATTACK - 1
+        );
+        assert_eq!(state.get_incoming_lines(), 0);
+    }
+
+    #[test]
+    fn garbage_is_ready_after_the_entry_delay() {
+        let mut state = AttackState::new();
+        state.receive(2);
+        state.tick(Duration::from_millis(GARBAGE_DELAY_MS / 2));
+        state.receive(3);
+        assert_eq!(state.get_ready_lines(), 0);
+        assert_eq!(state.take_ready_garbage(), 0);
+
+        state.tick(Duration::from_millis(GARBAGE_DELAY_MS / 2));
+        assert_eq!(state.get_ready_lines(), 2);
+        assert_eq!(state.take_ready_garbage(), 2);
+        assert_eq!(state.get_incoming_lines(), 3);
+
+        state.tick(Duration::from_millis(GARBAGE_DELAY_MS));
+        assert_eq!(state.take_ready_garbage(), 3);
+        assert_eq!(state.get_incoming_lines(), 0);
+    }
+
+    #[test]
+    fn ready_garbage_saturates() {
+        let mut state = AttackState::new();
+        for _ in 0..3 {
+            state.receive(100);
+        }
+        state.tick(Duration::from_millis(GARBAGE_DELAY_MS));
+        assert_eq!(state.get_incoming_lines(), u8::MAX);
+        assert_eq!(state.take_ready_garbage(), u8::MAX);
+    }
+}
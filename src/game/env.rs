@@ -0,0 +1,398 @@
+use crate::consts::SIMULATION_STEPS_PER_SECOND;
+use crate::game;
+use crate::game::input::PlayerInput;
+use crate::game::level::LevelProgression;
+use crate::game::scoring::{GameScore, GameplaySettings};
+use crate::game::tetromino::{DroppedStatus, MoveStatus, PlacementStep, TetrominoType};
+use crate::game::{GameBoard, GameMode, LockedTetromino};
+use crate::seed;
+use bevy_prng::ChaCha8Rng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// What the agent does during a step: the keys pressed for a single simulation step, with the bits
+// of the replays, or a place picked among the observed ones, reached and dropped at once
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Input(u8),
+    Placement { index: usize, hold: bool },
+}
+
+#[derive(Serialize, Debug)]
+pub struct ObservedTetromino {
+    pub tetromino: TetrominoType,
+    pub cells: Vec<u8>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Observation {
+    pub board: Vec<Vec<u8>>, // Rows from the top: 0 empty, 1 locked, 2 garbage
+    pub current: ObservedTetromino,
+    pub queue: Vec<TetrominoType>,
+    pub hold: Option<TetrominoType>,
+    // Cells where the current tetromino can lock, and the tetromino swapped in by holding
+    pub placements: Vec<Vec<u8>>,
+    pub held_placements: Vec<Vec<u8>>,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u16,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct StepInfo {
+    pub cleared_lines: u8,
+    pub t_spin: bool,
+    pub perfect_clear: bool,
+    pub locked: bool,
+    pub ticks: u64, // Simulation steps since the reset
+}
+
+#[derive(Serialize, Debug)]
+pub struct StepResult {
+    pub observation: Observation,
+    pub reward: f64, // Points scored during the step
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+// Single player game simulated step by step without Bevy, for agents learning to play.
+// Line clears happen at once, without the animation of the real game.
+pub struct Environment {
+    mode: GameMode,
+    level: u16,
+    level_progression: LevelProgression,
+    gameplay: GameplaySettings,
+    game_board: GameBoard,
+    game_score: GameScore,
+    rng: ChaCha8Rng,
+    garbage_rng: ChaCha8Rng,
+    step: Duration,
+    descend_countdown: Duration, // Left before gravity moves the tetromino a row down
+    ticks: u64,
+    done: bool,
+}
+
+impl Environment {
+    pub fn new(
+        mode: GameMode,
+        level: u16,
+        level_progression: LevelProgression,
+        gameplay: GameplaySettings,
+    ) -> Self {
+        let mut env = Self {
+            game_board: GameBoard::new(&mode),
+            game_score: GameScore::new(level, level_progression.clone()),
+            mode,
+            level,
+            level_progression,
+            gameplay,
+            rng: ChaCha8Rng::from_seed(seed::get_seed("0")),
+            garbage_rng: ChaCha8Rng::from_seed(seed::get_seed("0")),
+            step: Duration::from_secs_f64(1.0 / SIMULATION_STEPS_PER_SECOND),
+            descend_countdown: Duration::ZERO,
+            ticks: 0,
+            done: false,
+        };
+        env.reset("0");
+        env
+    }
+
+    // Starts a new game with the tetrominoes the real game deals for the same seed
+    pub fn reset(&mut self, seed: &str) -> Observation {
+        let seed = seed::get_seed(seed);
+        self.rng = ChaCha8Rng::from_seed(seed);
+        self.garbage_rng = ChaCha8Rng::from_seed(seed::get_garbage_seed(seed));
+        self.game_board = GameBoard::new(&self.mode);
+        self.game_board.init(&mut self.rng);
+        self.game_score = GameScore::new(self.level, self.level_progression.clone());
+        self.descend_countdown = self.get_descend_period();
+        self.ticks = 0;
+        self.done = false;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: &Action) -> Result<StepResult, String> {
+        if self.done {
+            return Err(String::from("The game is over, reset it first"));
+        }
+
+        let score = self.game_score.score;
+        let locked = match action {
+            Action::Input(bits) => self.play_input(&PlayerInput::from_bits(*bits)),
+            Action::Placement { index, hold } => Some(self.place(*index, *hold)?),
+        };
+
+        let mut info = StepInfo {
+            ticks: self.ticks,
+            ..StepInfo::default()
+        };
+        if let Some(locked) = locked {
+            info.cleared_lines = locked.outcome.cleared_lines;
+            info.t_spin = locked.outcome.t_spin;
+            info.perfect_clear = locked.outcome.perfect_clear;
+            info.locked = true;
+        }
+
+        Ok(StepResult {
+            observation: self.observe(),
+            reward: (self.game_score.score - score) as f64,
+            done: self.done,
+            info,
+        })
+    }
+
+    pub fn observe(&self) -> Observation {
+        let board = (0..game::NUMBER_OF_ROWS)
+            .map(|row| {
+                (0..game::NUMBER_OF_COLUMNS)
+                    .map(|col| {
+                        self.game_board
+                            .get_cell(row * game::NUMBER_OF_COLUMNS + col)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Holding draws the upcoming tetromino the first time, the copy of the board does not
+        // touch the generator of the game
+        let mut held_board = self.game_board.clone();
+        let held_placements = match held_board.hold_tetromino(&mut rand::rng()) {
+            MoveStatus::Moved => held_board.find_placements(),
+            MoveStatus::NotMoved => Vec::new(),
+        };
+
+        Observation {
+            board,
+            current: ObservedTetromino {
+                tetromino: self.game_board.get_current_tetromino_type().clone(),
+                cells: self.game_board.get_current_tetromino_cells(),
+            },
+            queue: vec![self.game_board.get_upcoming_tetromino_type().clone()],
+            hold: self.game_board.get_held_tetromino_type().cloned(),
+            placements: get_cells(self.game_board.find_placements()),
+            held_placements: get_cells(held_placements),
+            score: self.game_score.score,
+            lines: self.game_score.filled_up_lines,
+            level: self.game_score.level,
+        }
+    }
+
+    pub fn get_game_board(&self) -> &GameBoard {
+        &self.game_board
+    }
+
+    pub fn get_game_score(&self) -> &GameScore {
+        &self.game_score
+    }
+
+    pub fn get_mode(&self) -> &GameMode {
+        &self.mode
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // Plays a move, a rotation or a soft drop, the tetromino never locking
+    pub fn apply_step(&mut self, step: PlacementStep) -> MoveStatus {
+        self.game_board.apply_step(step)
+    }
+
+    pub fn hold(&mut self) -> MoveStatus {
+        self.game_board.hold_tetromino(&mut self.rng)
+    }
+
+    pub fn hard_drop(&mut self) -> LockedTetromino {
+        while let DroppedStatus::Dropped = self.game_board.drop_down() {}
+        self.lock()
+    }
+
+    // Takes the tetromino to the place with the commands of a player and drops it there
+    pub fn place(&mut self, index: usize, hold: bool) -> Result<LockedTetromino, String> {
+        let mut game_board = self.game_board.clone();
+        let mut rng = self.rng.clone();
+        if hold && game_board.hold_tetromino(&mut rng) == MoveStatus::NotMoved {
+            return Err(String::from("The tetromino cannot be held again"));
+        }
+        let placement = game_board
+            .find_placements()
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| format!("There is no placement {0}", index))?;
+
+        self.game_board = game_board;
+        self.rng = rng;
+        for step in placement.steps {
+            self.ticks += 1;
+            self.apply_step(step);
+        }
+        self.ticks += 1;
+        Ok(self.hard_drop())
+    }
+
+    // Simulates a step of the game as the real one does: hold, move or rotate, and fall
+    fn play_input(&mut self, input: &PlayerInput) -> Option<LockedTetromino> {
+        self.ticks += 1;
+
+        if input.hold {
+            self.hold();
+        }
+        if let Some(step) = input.get_move() {
+            self.apply_step(step);
+        }
+
+        let descended = self.tick_descend();
+        if !descended && !input.down && !input.hard_drop {
+            return None;
+        }
+
+        let mut dropped = self.game_board.drop_down();
+        while input.hard_drop && matches!(dropped, DroppedStatus::Dropped) {
+            dropped = self.game_board.drop_down();
+        }
+
+        let locked = match dropped {
+            DroppedStatus::Dropped => None,
+            DroppedStatus::NotDropped(_) => Some(self.lock()),
+        };
+        if !descended {
+            self.descend_countdown = self.get_descend_period();
+        }
+        locked
+    }
+
+    // Counts down a simulation step as the repeating timer of the real game does, returns
+    // whether the tetromino falls a row
+    fn tick_descend(&mut self) -> bool {
+        if self.step < self.descend_countdown {
+            self.descend_countdown -= self.step;
+            return false;
+        }
+
+        let period = self.get_descend_period();
+        let overshoot = (self.step - self.descend_countdown).as_nanos() % period.as_nanos();
+        self.descend_countdown = period - Duration::from_nanos(overshoot as u64);
+        true
+    }
+
+    fn get_descend_period(&self) -> Duration {
+        Duration::from_millis(self.gameplay.get_drop_down_ms(self.game_score.level))
+    }
+
+    // Scores the tetromino just locked, removes the filled rows and brings in the next tetromino
+    fn lock(&mut self) -> LockedTetromino {
+        let locked = self.game_board.lock_tetromino(
+            &mut self.rng,
+            &mut self.garbage_rng,
+            &mut self.game_score,
+            &self.gameplay,
+        );
+        self.game_board.collapse_filled_rows();
+        self.descend_countdown = self.get_descend_period();
+        self.done = locked.outcome.topped_out;
+        locked
+    }
+}
+
+fn get_cells(placements: Vec<game::tetromino::Placement>) -> Vec<Vec<u8>> {
+    placements
+        .into_iter()
+        .map(|placement| placement.cells)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_environment() -> Environment {
+        Environment::new(
+            GameMode::Marathon,
+            1,
+            LevelProgression::Fixed,
+            GameplaySettings::default(),
+        )
+    }
+
+    #[test]
+    fn deals_the_same_tetrominoes_for_the_same_seed() {
+        let mut env = get_environment();
+        let first = env.reset("seed");
+        env.step(&Action::Placement {
+            index: 0,
+            hold: false,
+        })
+        .unwrap();
+        let second = env.reset("seed");
+        assert_eq!(first.current.cells, second.current.cells);
+        assert_eq!(first.queue, second.queue);
+    }
+
+    #[test]
+    fn falls_a_row_when_the_drop_down_time_has_passed() {
+        let mut env = get_environment();
+        let cells = env.observe().current.cells;
+        // 800 ms at the first level, simulated 64 steps a second
+        for _ in 0..51 {
+            let result = env.step(&Action::Input(0)).unwrap();
+            assert_eq!(result.observation.current.cells, cells);
+        }
+        let result = env.step(&Action::Input(0)).unwrap();
+        assert_ne!(result.observation.current.cells, cells);
+    }
+
+    #[test]
+    fn soft_drop_on_the_floor_leaves_the_tetromino_playable() {
+        let mut env = get_environment();
+        while env.apply_step(PlacementStep::SoftDrop) == MoveStatus::Moved {}
+        assert_eq!(
+            env.apply_step(PlacementStep::SoftDrop),
+            MoveStatus::NotMoved
+        );
+
+        let moved = [PlacementStep::Left, PlacementStep::Right]
+            .into_iter()
+            .any(|step| env.apply_step(step) == MoveStatus::Moved);
+        assert!(moved);
+        assert!(env.observe().board.iter().flatten().all(|cell| *cell == 0));
+
+        let locked = env.hard_drop();
+        assert_eq!(locked.outcome.cleared_lines, 0);
+        assert_eq!(env.get_game_score().score, locked.points);
+        assert_eq!(
+            env.observe()
+                .board
+                .iter()
+                .flatten()
+                .filter(|cell| **cell != 0)
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn places_the_tetromino_and_scores_it() {
+        let mut env = get_environment();
+        let placements = env.observe().placements;
+        let result = env
+            .step(&Action::Placement {
+                index: placements.len() - 1,
+                hold: false,
+            })
+            .unwrap();
+        assert!(result.info.locked);
+        assert_eq!(
+            result.reward,
+            GameplaySettings::default().points_for_tetromino_dropped as f64
+        );
+        assert!(
+            env.step(&Action::Placement {
+                index: usize::MAX,
+                hold: false
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,843 @@
+use crate::game::tetromino::{Tetromino, TetrominoType};
+use crate::game::{
+    GARBAGE_CELL, LOCKED_CELL, NUMBER_OF_CELLS, NUMBER_OF_COLUMNS, NUMBER_OF_ROWS,
+    get_cell_tetromino, get_colored_cell,
+};
+use std::str::FromStr;
+
+// Fumen (https://fumen.zui.jp) diagrams are shared as links such as https://fumen.zui.jp/?v115@vhAAgH
+pub const FUMEN_URL: &str = "https://fumen.zui.jp/?";
+const VERSION: &str = "v115@";
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const COMMENT_CHARS: u32 = 96; // Printable ASCII characters, from ' ' to '~'
+const MAX_COMMENT_LENGTH: usize = 4095;
+const WIDTH: i32 = NUMBER_OF_COLUMNS as i32;
+const EMPTY: u8 = 0;
+const GRAY: u8 = 8;
+
+// Fumen rotations are numbered from the reversed one, going clockwise
+const REVERSE: u8 = 0;
+const RIGHT: u8 = 1;
+const SPAWN: u8 = 2;
+const LEFT: u8 = 3;
+
+// Tetromino drawn on a page, as the cells of the board it covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FumenPiece {
+    pub tetromino: TetrominoType,
+    pub cells: Vec<u8>, // Sorted
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FumenPage {
+    pub board: [u8; NUMBER_OF_CELLS as usize],
+    pub piece: Option<FumenPiece>,
+    pub comment: String,
+}
+
+// Diagram made of pages, every page locking its tetromino on the board of the following one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fumen {
+    pages: Vec<FumenPage>,
+}
+
+// Piece as encoded in a fumen: SRS rotation and position of its center, from the bottom left
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Operation {
+    piece: u8,
+    rotation: u8,
+    x: i32,
+    y: i32,
+}
+
+struct Action {
+    operation: Operation,
+    rise: bool,
+    mirror: bool,
+    comment: bool,
+    lock: bool,
+}
+
+// Field of a fumen, from the top row down to the garbage line below the floor
+#[derive(Clone)]
+struct Field {
+    top: i32, // Rows above the floor, 23 in v115
+    cells: Vec<u8>,
+}
+
+// Base 64 digits, least significant first
+struct Values {
+    digits: Vec<u32>,
+    position: usize,
+}
+
+impl Fumen {
+    pub fn new(pages: Vec<FumenPage>) -> Self {
+        Self { pages }
+    }
+
+    pub fn get_pages(&self) -> &[FumenPage] {
+        &self.pages
+    }
+
+    // The game starts from the board of the first page
+    pub fn get_board(&self) -> [u8; NUMBER_OF_CELLS as usize] {
+        self.pages
+            .first()
+            .map(|page| page.board)
+            .unwrap_or([0; NUMBER_OF_CELLS as usize])
+    }
+
+    // Single page with the board, and the tetrominoes to play as a quiz such as "#Q=[I](T)SZ"
+    pub fn from_position(
+        board: [u8; NUMBER_OF_CELLS as usize],
+        held: Option<&TetrominoType>,
+        queue: &[TetrominoType],
+    ) -> Self {
+        let comment = if held.is_none() && queue.is_empty() {
+            String::new()
+        } else {
+            let held = held.map(|held| held.to_string()).unwrap_or_default();
+            let current = queue.first().map(|current| current.to_string());
+            let next: String = queue.iter().skip(1).map(|next| next.to_string()).collect();
+            format!("#Q=[{0}]({1}){2}", held, current.unwrap_or_default(), next)
+        };
+
+        Self::new(vec![FumenPage {
+            board,
+            piece: None,
+            comment,
+        }])
+    }
+
+    // Held tetromino of a quiz such as "#Q=[I](T)SZ"
+    pub fn get_hold(&self) -> Option<TetrominoType> {
+        let (held, _) = self.get_quiz()?.strip_prefix('[')?.split_once(']')?;
+        held.parse().ok()
+    }
+
+    // Tetrominoes of a quiz such as "#Q=[I](T)SZ" after the held one, otherwise the ones drawn
+    // on the pages in order
+    pub fn get_queue(&self) -> Vec<TetrominoType> {
+        if let Some(quiz) = self.get_quiz() {
+            let queue = match quiz.strip_prefix('[').and_then(|quiz| quiz.split_once(']')) {
+                Some((_, queue)) => queue,
+                None => quiz,
+            };
+            return queue
+                .chars()
+                .filter_map(|piece| piece.to_string().parse().ok())
+                .collect();
+        }
+
+        self.pages
+            .iter()
+            .filter_map(|page| page.piece.as_ref())
+            .map(|piece| piece.tetromino.clone())
+            .collect()
+    }
+
+    fn get_quiz(&self) -> Option<&str> {
+        self.pages
+            .first()
+            .and_then(|page| page.comment.strip_prefix("#Q="))
+    }
+
+    // Encodes the pages as the data of a v115 link, such as v115@vhAAgH
+    pub fn encode(&self) -> String {
+        let mut values = Values::new(Vec::new());
+        let mut previous = Field::new(23);
+        let mut previous_comment = "";
+        let mut repeat_index: Option<usize> = None;
+
+        for (index, page) in self.pages.iter().enumerate() {
+            let field = previous.with_board(&page.board);
+
+            // Pages keeping the field of the previous one are counted after the first of them
+            let unchanged = values.push_field(&previous, &field);
+            match repeat_index {
+                Some(repeat) if unchanged && values.digits[repeat] < 63 => {
+                    values.digits.truncate(values.digits.len() - 2);
+                    values.digits[repeat] += 1;
+                }
+                _ if unchanged => {
+                    values.push(0, 1);
+                    repeat_index = Some(values.digits.len() - 1);
+                }
+                _ => repeat_index = None,
+            }
+
+            let operation = page
+                .piece
+                .as_ref()
+                .and_then(Operation::from_piece)
+                .unwrap_or(Operation::EMPTY);
+            let comment = page.comment != previous_comment;
+            values.push_action(&operation, index == 0, comment, field.top);
+            if comment {
+                values.push_comment(&page.comment);
+            }
+            previous_comment = &page.comment;
+
+            previous = field;
+            if let Some(cells) = operation.get_cells() {
+                previous.lock(&operation, &cells);
+            }
+        }
+
+        let data = values.to_string();
+        // Long links are split every 47 characters after the first 42, as fumen itself does
+        let mut chunks = vec![&data[..data.len().min(42)]];
+        let mut rest = &data[data.len().min(42)..];
+        while !rest.is_empty() {
+            let (chunk, tail) = rest.split_at(rest.len().min(47));
+            chunks.push(chunk);
+            rest = tail;
+        }
+        format!("{0}{1}", VERSION, chunks.join("?"))
+    }
+
+    fn decode(data: &str, top: i32) -> Result<Self, String> {
+        let mut values = Values::parse(data)?;
+        let mut pages = Vec::new();
+        let mut previous = Field::new(top);
+        let mut repeat_count = 0;
+        let mut comment = String::new();
+
+        while !values.is_empty() {
+            let page_number = pages.len() + 1;
+            let mut field = previous.clone();
+            if repeat_count > 0 {
+                repeat_count -= 1;
+            } else {
+                let mut index = 0;
+                while index < field.cells.len() {
+                    let value = values.poll(2)? as usize;
+                    let diff = value / field.cells.len();
+                    let count = value % field.cells.len() + 1;
+                    if diff == 8 && count == field.cells.len() {
+                        repeat_count = values.poll(1)?;
+                    }
+                    if index + count > field.cells.len() {
+                        return Err(format!("Page {0} has too many cells", page_number));
+                    }
+                    for cell in &mut field.cells[index..index + count] {
+                        *cell = (*cell as usize + diff)
+                            .checked_sub(8)
+                            .filter(|cell| *cell <= GRAY as usize)
+                            .ok_or_else(|| format!("Page {0} has invalid cells", page_number))?
+                            as u8;
+                    }
+                    index += count;
+                }
+            }
+
+            let action = Action::decode(values.poll(3)?, &field);
+            if action.comment {
+                comment = values.poll_comment()?;
+            }
+
+            let cells = action.operation.get_cells();
+            let board = field.get_board().ok_or_else(|| {
+                format!(
+                    "Page {0} does not fit the {1} rows of the board",
+                    page_number, NUMBER_OF_ROWS
+                )
+            })?;
+            let piece = match (action.operation.get_tetromino(), &cells) {
+                (Some(tetromino), Some(cells)) => Some(FumenPiece {
+                    tetromino,
+                    cells: get_board_cells(cells).ok_or_else(|| {
+                        format!(
+                            "The piece of page {0} does not fit the {1} rows of the board",
+                            page_number, NUMBER_OF_ROWS
+                        )
+                    })?,
+                }),
+                _ => None,
+            };
+            pages.push(FumenPage {
+                board,
+                piece,
+                comment: comment.clone(),
+            });
+
+            if action.lock {
+                if let Some(cells) = &cells {
+                    if !field.fits(cells) {
+                        return Err(format!(
+                            "The piece of page {0} is off the field",
+                            page_number
+                        ));
+                    }
+                    field.lock(&action.operation, cells);
+                } else {
+                    field.clear_lines();
+                }
+                if action.rise {
+                    field.rise();
+                }
+                if action.mirror {
+                    field.mirror();
+                }
+            }
+            previous = field;
+        }
+
+        if pages.is_empty() {
+            return Err(String::from("The fumen has no page"));
+        }
+        Ok(Self { pages })
+    }
+}
+
+// Reads fumen data or links, such as https://fumen.zui.jp/?v115@vhAAgH, in version 115 or 110
+impl FromStr for Fumen {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.split('&').next().unwrap_or_default();
+        for (version, top) in [("115@", 23), ("110@", 21)] {
+            if let Some(start) = value.find(version)
+                && start > 0
+                && matches!(&value[start - 1..start], "v" | "m" | "d" | "V" | "M" | "D")
+            {
+                let data: String = value[start + version.len()..]
+                    .chars()
+                    .filter(|c| *c != '?' && !c.is_whitespace())
+                    .collect();
+                return Self::decode(&data, top)
+                    .map_err(|error| format!("Invalid fumen: {0}", error));
+            }
+        }
+
+        Err(format!(
+            "Invalid fumen '{0}': expected data such as v115@vhAAgH",
+            value
+        ))
+    }
+}
+
+impl Operation {
+    const EMPTY: Operation = Operation {
+        piece: EMPTY,
+        rotation: REVERSE,
+        x: 0,
+        y: 22,
+    };
+
+    // Finds the SRS position covering the cells, the tetrominoes of the big mode have none
+    fn from_piece(piece: &FumenPiece) -> Option<Self> {
+        let number = get_piece_number(&piece.tetromino);
+        for rotation in [SPAWN, RIGHT, REVERSE, LEFT] {
+            for y in 0..NUMBER_OF_ROWS as i32 {
+                for x in 0..WIDTH {
+                    let operation = Operation {
+                        piece: number,
+                        rotation,
+                        x,
+                        y,
+                    };
+                    if operation
+                        .get_cells()
+                        .and_then(|cells| get_board_cells(&cells))
+                        .is_some_and(|cells| cells == piece.cells)
+                    {
+                        return Some(operation);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn get_tetromino(&self) -> Option<TetrominoType> {
+        get_piece_tetromino(self.piece)
+    }
+
+    // Columns and rows from the bottom of the minos, as rotated by SRS around the center
+    fn get_cells(&self) -> Option<[(i32, i32); 4]> {
+        let minos = match self.piece {
+            1 => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+            2 => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+            3 => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            4 => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+            5 => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+            6 => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+            7 => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+            _ => return None,
+        };
+        Some(minos.map(|(x, y)| {
+            let (x, y) = match self.rotation {
+                SPAWN => (x, y),
+                RIGHT => (y, -x),
+                REVERSE => (-x, -y),
+                _ => (-y, x),
+            };
+            (self.x + x, self.y + y)
+        }))
+    }
+
+    // Fumen places some tetrominoes by another center than SRS
+    fn get_center_shift(&self) -> (i32, i32) {
+        match (self.piece, self.rotation) {
+            (3, LEFT) => (1, -1),
+            (3, REVERSE) => (1, 0),
+            (3, SPAWN) => (0, -1),
+            (1, REVERSE) => (1, 0),
+            (1, LEFT) => (0, -1),
+            (7, SPAWN) => (0, -1),
+            (7, RIGHT) => (-1, 0),
+            (4, SPAWN) => (0, -1),
+            (4, LEFT) => (1, 0),
+            _ => (0, 0),
+        }
+    }
+}
+
+impl Action {
+    fn decode(value: u32, field: &Field) -> Self {
+        let blocks = field.cells.len() as u32;
+        let piece = (value % 8) as u8;
+        let rotation = (value / 8 % 4) as u8;
+        let position = value / 32 % blocks;
+        let flags = value / 32 / blocks;
+
+        let mut operation = Operation {
+            piece,
+            rotation,
+            x: (position % WIDTH as u32) as i32,
+            y: field.top - (position / WIDTH as u32) as i32 - 1,
+        };
+        let (shift_x, shift_y) = operation.get_center_shift();
+        operation.x += shift_x;
+        operation.y += shift_y;
+
+        Self {
+            operation,
+            rise: flags & 1 != 0,
+            mirror: flags & 2 != 0,
+            comment: flags & 8 != 0,
+            lock: flags & 16 == 0,
+        }
+    }
+}
+
+impl Field {
+    fn new(top: i32) -> Self {
+        Self {
+            top,
+            cells: vec![EMPTY; ((top + 1) * WIDTH) as usize],
+        }
+    }
+
+    fn get_index(&self, x: i32, y: i32) -> Option<usize> {
+        ((0..WIDTH).contains(&x) && (-1..self.top).contains(&y))
+            .then_some(((self.top - y - 1) * WIDTH + x) as usize)
+    }
+
+    fn fits(&self, cells: &[(i32, i32)]) -> bool {
+        cells
+            .iter()
+            .all(|(x, y)| *y >= 0 && self.get_index(*x, *y).is_some())
+    }
+
+    // Field of the board, keeping the colors of the tetrominoes still in place
+    fn with_board(&self, board: &[u8; NUMBER_OF_CELLS as usize]) -> Self {
+        let mut field = Field::new(self.top);
+        for (cell, value) in board.iter().enumerate() {
+            let (row, col) = Tetromino::get_row_and_column_by_cell(cell as u8);
+            let Some(index) = field.get_index(col as i32, NUMBER_OF_ROWS as i32 - row as i32 - 1)
+            else {
+                continue;
+            };
+            field.cells[index] = match (*value, get_cell_tetromino(*value)) {
+                (EMPTY, _) => EMPTY,
+                (_, Some(tetromino)) => get_piece_number(&tetromino),
+                (LOCKED_CELL, _) if (1..GRAY).contains(&self.cells[index]) => self.cells[index],
+                _ => GRAY,
+            };
+        }
+        field
+    }
+
+    fn get_board(&self) -> Option<[u8; NUMBER_OF_CELLS as usize]> {
+        let mut board = [EMPTY; NUMBER_OF_CELLS as usize];
+        for y in 0..self.top {
+            for x in 0..WIDTH {
+                let value = self.cells[self.get_index(x, y)?];
+                if value == EMPTY {
+                    continue;
+                }
+                let cell = get_board_cells(&[(x, y)])?[0];
+                board[cell as usize] = match get_piece_tetromino(value) {
+                    Some(tetromino) => get_colored_cell(&tetromino),
+                    None => GARBAGE_CELL,
+                };
+            }
+        }
+        Some(board)
+    }
+
+    fn lock(&mut self, operation: &Operation, cells: &[(i32, i32)]) {
+        for (x, y) in cells {
+            if let Some(index) = self.get_index(*x, *y) {
+                self.cells[index] = operation.piece;
+            }
+        }
+        self.clear_lines();
+    }
+
+    // Filled rows above the garbage line are removed, the ones above fall down
+    fn clear_lines(&mut self) {
+        let width = WIDTH as usize;
+        let garbage = self.cells.split_off(self.cells.len() - width);
+        let rows: Vec<&[u8]> = self
+            .cells
+            .chunks(width)
+            .filter(|row| row.contains(&EMPTY))
+            .collect();
+        let mut cells = vec![EMPTY; self.cells.len() - rows.len() * width];
+        cells.extend(rows.concat());
+        cells.extend(garbage);
+        self.cells = cells;
+    }
+
+    // The garbage line enters the field from the bottom, pushing it up
+    fn rise(&mut self) {
+        let width = WIDTH as usize;
+        self.cells.drain(..width);
+        self.cells.extend(vec![EMPTY; width]);
+    }
+
+    fn mirror(&mut self) {
+        let width = WIDTH as usize;
+        let garbage = self.cells.len() - width;
+        for row in self.cells[..garbage].chunks_mut(width) {
+            row.reverse();
+        }
+    }
+}
+
+impl Values {
+    fn new(digits: Vec<u32>) -> Self {
+        Self {
+            digits,
+            position: 0,
+        }
+    }
+
+    fn parse(data: &str) -> Result<Self, String> {
+        data.bytes()
+            .map(|byte| {
+                BASE64
+                    .iter()
+                    .position(|digit| *digit == byte)
+                    .map(|digit| digit as u32)
+                    .ok_or_else(|| format!("Unexpected character '{0}'", byte as char))
+            })
+            .collect::<Result<Vec<u32>, String>>()
+            .map(Self::new)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.digits.len()
+    }
+
+    fn poll(&mut self, count: usize) -> Result<u32, String> {
+        let digits = self
+            .digits
+            .get(self.position..self.position + count)
+            .ok_or_else(|| String::from("The data ends too early"))?;
+        self.position += count;
+        Ok(digits
+            .iter()
+            .rev()
+            .fold(0, |value, digit| value * 64 + digit))
+    }
+
+    fn poll_comment(&mut self) -> Result<String, String> {
+        let length = self.poll(2)? as usize;
+        let mut escaped = String::new();
+        for _ in 0..length.div_ceil(4) {
+            let mut value = self.poll(5)?;
+            for _ in 0..4 {
+                escaped.push((b' ' + (value % COMMENT_CHARS) as u8) as char);
+                value /= COMMENT_CHARS;
+            }
+        }
+        escaped.truncate(length);
+        Ok(unescape(&escaped))
+    }
+
+    fn push(&mut self, mut value: u32, count: usize) {
+        for _ in 0..count {
+            self.digits.push(value % 64);
+            value /= 64;
+        }
+    }
+
+    // Pushes the differences between the fields as runs of cells, telling whether there is none
+    fn push_field(&mut self, previous: &Field, field: &Field) -> bool {
+        let blocks = field.cells.len() as u32;
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for (before, after) in previous.cells.iter().zip(&field.cells) {
+            let diff = *after as u32 + 8 - *before as u32;
+            match runs.last_mut() {
+                Some((last, count)) if *last == diff => *count += 1,
+                _ => runs.push((diff, 1)),
+            }
+        }
+        for (diff, count) in &runs {
+            self.push(diff * blocks + count - 1, 2);
+        }
+        runs == [(8, blocks)]
+    }
+
+    fn push_action(&mut self, operation: &Operation, first: bool, comment: bool, top: i32) {
+        let blocks = ((top + 1) * WIDTH) as u32;
+        let (shift_x, shift_y) = operation.get_center_shift();
+        let position = (top - (operation.y - shift_y) - 1) * WIDTH + operation.x - shift_x;
+        // Every tetromino locks, and the first page asks for the guideline colors
+        let flags = (comment as u32) << 3 | (first as u32) << 2;
+        let value = ((flags * blocks + position as u32) * 4 + operation.rotation as u32) * 8
+            + operation.piece as u32;
+        self.push(value, 3);
+    }
+
+    fn push_comment(&mut self, comment: &str) {
+        let escaped: Vec<u8> = escape(comment).bytes().take(MAX_COMMENT_LENGTH).collect();
+        self.push(escaped.len() as u32, 2);
+        for chunk in escaped.chunks(4) {
+            let value = chunk.iter().rev().fold(0, |value, byte| {
+                value * COMMENT_CHARS + (byte - b' ') as u32
+            });
+            self.push(value, 5);
+        }
+    }
+}
+
+impl std::fmt::Display for Values {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for digit in &self.digits {
+            write!(f, "{0}", BASE64[*digit as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_piece_number(tetromino: &TetrominoType) -> u8 {
+    match tetromino {
+        TetrominoType::I => 1,
+        TetrominoType::L => 2,
+        TetrominoType::O => 3,
+        TetrominoType::Z => 4,
+        TetrominoType::T => 5,
+        TetrominoType::J => 6,
+        TetrominoType::S => 7,
+    }
+}
+
+fn get_piece_tetromino(number: u8) -> Option<TetrominoType> {
+    match number {
+        1 => Some(TetrominoType::I),
+        2 => Some(TetrominoType::L),
+        3 => Some(TetrominoType::O),
+        4 => Some(TetrominoType::Z),
+        5 => Some(TetrominoType::T),
+        6 => Some(TetrominoType::J),
+        7 => Some(TetrominoType::S),
+        _ => None,
+    }
+}
+
+// Cells of the board numbered from the top left, for columns and rows from the bottom left
+fn get_board_cells(cells: &[(i32, i32)]) -> Option<Vec<u8>> {
+    let mut board_cells = cells
+        .iter()
+        .map(|(x, y)| {
+            ((0..WIDTH).contains(x) && (0..NUMBER_OF_ROWS as i32).contains(y)).then(|| {
+                Tetromino::get_cell_from_row_and_column(
+                    (NUMBER_OF_ROWS as i32 - y - 1) as u8,
+                    *x as u8,
+                )
+            })
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    board_cells.sort();
+    Some(board_cells)
+}
+
+// Comments are escaped as the escape function of JavaScript does, keeping them in printable ASCII
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for unit in text.encode_utf16() {
+        match char::from_u32(unit as u32) {
+            Some(c) if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) => escaped.push(c),
+            _ if unit < 256 => escaped.push_str(&format!("%{0:02X}", unit)),
+            _ => escaped.push_str(&format!("%u{0:04X}", unit)),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut units = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let escaped = match rest.strip_prefix("%u") {
+            Some(tail) => tail.get(..4).map(|hex| (hex, 6)),
+            None => rest
+                .strip_prefix('%')
+                .and_then(|tail| tail.get(..2))
+                .map(|hex| (hex, 3)),
+        };
+        match escaped.and_then(|(hex, length)| Some((u16::from_str_radix(hex, 16).ok()?, length))) {
+            Some((unit, length)) => {
+                units.push(unit);
+                rest = &rest[length..];
+            }
+            None => {
+                units.push(c as u16);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_piece(tetromino: TetrominoType, rotation: u8, x: i32, y: i32) -> FumenPiece {
+        let operation = Operation {
+            piece: get_piece_number(&tetromino),
+            rotation,
+            x,
+            y,
+        };
+        FumenPiece {
+            tetromino,
+            cells: get_board_cells(&operation.get_cells().unwrap()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn decodes_an_empty_page() {
+        for link in [
+            "v115@vhAAgH",
+            "https://fumen.zui.jp/?v115@vhAAgH",
+            "D115@vhAAgH",
+        ] {
+            let fumen: Fumen = link.parse().unwrap();
+            assert_eq!(
+                fumen,
+                Fumen::from_position([0; NUMBER_OF_CELLS as usize], None, &[])
+            );
+        }
+        assert_eq!(
+            Fumen::from_position([0; NUMBER_OF_CELLS as usize], None, &[]).encode(),
+            "v115@vhAAgH"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        assert!("vhAAgH".parse::<Fumen>().is_err());
+        assert!("v115@".parse::<Fumen>().is_err());
+        assert!("v115@vhA".parse::<Fumen>().is_err());
+        assert!("v115@vh!AgH".parse::<Fumen>().is_err());
+    }
+
+    #[test]
+    fn round_trips_pages_with_pieces_and_comments() {
+        let mut board = [0; NUMBER_OF_CELLS as usize];
+        for col in 1..NUMBER_OF_COLUMNS {
+            board[Tetromino::get_cell_from_row_and_column(NUMBER_OF_ROWS - 1, col) as usize] =
+                GARBAGE_CELL;
+        }
+        let first = FumenPage {
+            board,
+            piece: Some(get_piece(TetrominoType::T, SPAWN, 4, 1)),
+            comment: String::from("#Q=[I](T)SZ"),
+        };
+
+        let mut board = first.board;
+        for cell in &first.piece.as_ref().unwrap().cells {
+            board[*cell as usize] = get_colored_cell(&TetrominoType::T);
+        }
+        let second = FumenPage {
+            board,
+            piece: Some(get_piece(TetrominoType::I, RIGHT, 0, 2)),
+            comment: String::from("Tスピン 100% «ok»"),
+        };
+
+        // The vertical I fills the hole of the garbage row, which is cleared
+        let mut board = [0; NUMBER_OF_CELLS as usize];
+        for cell in &first.piece.as_ref().unwrap().cells {
+            board[*cell as usize + NUMBER_OF_COLUMNS as usize] =
+                get_colored_cell(&TetrominoType::T);
+        }
+        for row in NUMBER_OF_ROWS - 3..NUMBER_OF_ROWS {
+            board[Tetromino::get_cell_from_row_and_column(row, 0) as usize] =
+                get_colored_cell(&TetrominoType::I);
+        }
+        let third = FumenPage {
+            board,
+            piece: None,
+            comment: second.comment.clone(),
+        };
+
+        let fumen = Fumen::new(vec![first, second, third]);
+        let data = fumen.encode();
+        let decoded: Fumen = data.parse().unwrap();
+        assert_eq!(decoded, fumen);
+        assert_eq!(decoded.get_hold(), Some(TetrominoType::I));
+        assert_eq!(
+            decoded.get_queue(),
+            [TetrominoType::T, TetrominoType::S, TetrominoType::Z]
+        );
+    }
+
+    #[test]
+    fn round_trips_pieces_in_every_rotation() {
+        for tetromino in TetrominoType::ALL {
+            for rotation in [SPAWN, RIGHT, REVERSE, LEFT] {
+                let fumen = Fumen::new(vec![FumenPage {
+                    board: [0; NUMBER_OF_CELLS as usize],
+                    piece: Some(get_piece(tetromino.clone(), rotation, 4, 5)),
+                    comment: String::new(),
+                }]);
+                assert_eq!(fumen.encode().parse::<Fumen>().unwrap(), fumen);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_more_than_63_repeated_pages() {
+        let mut board = [0; NUMBER_OF_CELLS as usize];
+        board[(NUMBER_OF_CELLS - 1) as usize] = GARBAGE_CELL;
+        let pages: Vec<FumenPage> = (0..150)
+            .map(|_| FumenPage {
+                board,
+                piece: None,
+                comment: String::new(),
+            })
+            .collect();
+
+        let fumen = Fumen::new(pages);
+        let data = fumen.encode();
+        let decoded: Fumen = data.parse().unwrap();
+        assert_eq!(decoded.get_pages().len(), 150);
+        assert_eq!(decoded, fumen);
+    }
+
+    #[test]
+    fn escapes_comments_as_javascript() {
+        let comment = "a b+c/é€";
+        assert_eq!(escape(comment), "a%20b+c/%E9%u20AC");
+        assert_eq!(unescape(&escape(comment)), comment);
+    }
+}
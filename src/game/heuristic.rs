@@ -0,0 +1,187 @@
+use crate::game::tetromino::{MoveStatus, Placement, Tetromino};
+use crate::game::{GameBoard, NUMBER_OF_CELLS, NUMBER_OF_COLUMNS, NUMBER_OF_ROWS};
+
+// Weights of the features of Pierre Dellacherie, as tuned by El-Tetris
+const LANDING_HEIGHT_WEIGHT: f64 = -4.500158825082766;
+const ERODED_CELLS_WEIGHT: f64 = 3.4181268101392694;
+const ROW_TRANSITIONS_WEIGHT: f64 = -3.2178882868487753;
+const COLUMN_TRANSITIONS_WEIGHT: f64 = -9.348695305445199;
+const HOLES_WEIGHT: f64 = -7.899265427351652;
+const WELLS_WEIGHT: f64 = -3.3855972247263626;
+
+// Best place found for the current tetromino, and whether the held one does better
+pub struct Choice {
+    pub hold: bool,
+    pub placement: Placement,
+}
+
+// Scores the board left once the tetromino has been locked and the filled rows removed
+pub fn evaluate(game_board: &GameBoard, placement: &Placement) -> f64 {
+    let mut cells = [false; NUMBER_OF_CELLS as usize];
+    for (cell, occupied) in cells.iter_mut().enumerate() {
+        *occupied = game_board.is_cell_occupied(cell as u8);
+    }
+    for cell in &placement.cells {
+        cells[*cell as usize] = true;
+    }
+
+    let rows: Vec<u8> = placement
+        .cells
+        .iter()
+        .map(|cell| Tetromino::get_row_and_column_by_cell(*cell).0)
+        .collect();
+    let top = *rows.iter().min().unwrap_or(&0) as f64;
+    let bottom = *rows.iter().max().unwrap_or(&0) as f64;
+    let landing_height = NUMBER_OF_ROWS as f64 - (top + bottom) / 2.0;
+
+    // Filled rows are collapsed, the remaining ones keep their order from the bottom up. The lines
+    // cleared count as many times as the cells of the tetromino removed with them.
+    let mut board: Vec<[bool; NUMBER_OF_COLUMNS as usize]> = Vec::new();
+    let mut cleared_lines = 0;
+    let mut cleared_cells = 0;
+    for row in (0..NUMBER_OF_ROWS).rev() {
+        let mut line = [false; NUMBER_OF_COLUMNS as usize];
+        for (col, occupied) in line.iter_mut().enumerate() {
+            *occupied = cells[Tetromino::get_cell_from_row_and_column(row, col as u8) as usize];
+        }
+        if line.iter().all(|occupied| *occupied) {
+            cleared_lines += 1;
+            cleared_cells += rows.iter().filter(|cell_row| **cell_row == row).count();
+        } else {
+            board.push(line);
+        }
+    }
+    board.resize(NUMBER_OF_ROWS as usize, [false; NUMBER_OF_COLUMNS as usize]);
+
+    let is_occupied = |row: i32, col: i32| {
+        col < 0 || col >= NUMBER_OF_COLUMNS as i32 || row < 0 || board[row as usize][col as usize]
+    };
+
+    let mut row_transitions = 0;
+    for row in 0..NUMBER_OF_ROWS as i32 {
+        for col in 0..=NUMBER_OF_COLUMNS as i32 {
+            if is_occupied(row, col - 1) != is_occupied(row, col) {
+                row_transitions += 1;
+            }
+        }
+    }
+
+    let mut column_transitions = 0;
+    let mut holes = 0;
+    let mut wells = 0;
+    for col in 0..NUMBER_OF_COLUMNS as i32 {
+        let mut covered = false;
+        let mut well_depth = 0;
+        for row in (0..NUMBER_OF_ROWS as i32).rev() {
+            let occupied = is_occupied(row, col);
+            if occupied != is_occupied(row - 1, col) {
+                column_transitions += 1;
+            }
+            if occupied {
+                covered = true;
+                well_depth = 0;
+            } else {
+                if covered {
+                    holes += 1;
+                }
+                // Every cell deeper in a well counts more than the one above it
+                if is_occupied(row, col - 1) && is_occupied(row, col + 1) {
+                    well_depth += 1;
+                    wells += well_depth;
+                } else {
+                    well_depth = 0;
+                }
+            }
+        }
+    }
+
+    landing_height * LANDING_HEIGHT_WEIGHT
+        + (cleared_lines * cleared_cells) as f64 * ERODED_CELLS_WEIGHT
+        + row_transitions as f64 * ROW_TRANSITIONS_WEIGHT
+        + column_transitions as f64 * COLUMN_TRANSITIONS_WEIGHT
+        + holes as f64 * HOLES_WEIGHT
+        + wells as f64 * WELLS_WEIGHT
+}
+
+fn find_best_placement(game_board: &GameBoard) -> Option<(f64, Placement)> {
+    game_board
+        .find_placements()
+        .into_iter()
+        .map(|placement| (evaluate(game_board, &placement), placement))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+}
+
+// Finds the best place for the current tetromino, or for the one swapped in by holding it
+pub fn choose_placement(game_board: &GameBoard) -> Option<Choice> {
+    let (score, placement) = find_best_placement(game_board)?;
+    let mut choice = Choice {
+        hold: false,
+        placement,
+    };
+
+    // The tetromino drawn when holding the first time is only seen by the copy of the board
+    let mut held_board = game_board.clone();
+    if held_board.hold_tetromino(&mut rand::rng()) == MoveStatus::Moved
+        && let Some((held_score, held_placement)) = find_best_placement(&held_board)
+        && held_score > score
+    {
+        choice = Choice {
+            hold: true,
+            placement: held_placement,
+        };
+    }
+
+    Some(choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameMode, LOCKED_CELL};
+
+    fn get_placement(cells: &[(u8, u8)]) -> Placement {
+        let mut cells: Vec<u8> = cells
+            .iter()
+            .map(|(row, col)| Tetromino::get_cell_from_row_and_column(*row, *col))
+            .collect();
+        cells.sort();
+        Placement {
+            cells,
+            steps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn filling_the_hole_scores_higher_than_covering_it() {
+        // The bottom row is full but for its first column
+        let mut game_board = GameBoard::new(&GameMode::Marathon);
+        for col in 1..NUMBER_OF_COLUMNS {
+            game_board.set_cell(
+                Tetromino::get_cell_from_row_and_column(NUMBER_OF_ROWS - 1, col),
+                LOCKED_CELL,
+            );
+        }
+
+        let filling = get_placement(&[(16, 0), (17, 0), (18, 0), (19, 0)]);
+        let covering = get_placement(&[(18, 0), (18, 1), (18, 2), (18, 3)]);
+        assert!(evaluate(&game_board, &filling) > evaluate(&game_board, &covering));
+    }
+
+    #[test]
+    fn clearing_more_of_the_tetromino_scores_higher() {
+        // The two bottom rows are full but for their first two columns
+        let mut game_board = GameBoard::new(&GameMode::Marathon);
+        for row in NUMBER_OF_ROWS - 2..NUMBER_OF_ROWS {
+            for col in 2..NUMBER_OF_COLUMNS {
+                game_board.set_cell(
+                    Tetromino::get_cell_from_row_and_column(row, col),
+                    LOCKED_CELL,
+                );
+            }
+        }
+
+        let square = get_placement(&[(18, 0), (18, 1), (19, 0), (19, 1)]);
+        let column = get_placement(&[(16, 0), (17, 0), (18, 0), (19, 0)]);
+        assert!(evaluate(&game_board, &square) > evaluate(&game_board, &column));
+    }
+}
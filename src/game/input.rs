@@ -0,0 +1,90 @@
+use crate::game::tetromino::PlacementStep;
+
+// Commands driving a board during the next simulation step, whatever their source
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Component))]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub rotate: bool, // Clockwise
+    pub down: bool,
+    pub rotate_ccw: bool,
+    pub rotate_180: bool,
+    pub hard_drop: bool,
+    pub hold: bool,
+    pub repeat: bool, // The move comes from a direction held down, not pressed again
+}
+
+impl PlayerInput {
+    // Keeps the commands received since the last simulation step
+    pub fn merge(&mut self, other: &PlayerInput) {
+        // The move stays a repeat only if none of the inputs pressed it
+        let pressed = |input: &PlayerInput| (input.left || input.right) && !input.repeat;
+        let repeat = !pressed(self) && !pressed(other);
+        self.left |= other.left;
+        self.right |= other.right;
+        self.rotate |= other.rotate;
+        self.down |= other.down;
+        self.rotate_ccw |= other.rotate_ccw;
+        self.rotate_180 |= other.rotate_180;
+        self.hard_drop |= other.hard_drop;
+        self.hold |= other.hold;
+        self.repeat = repeat && (self.left || self.right);
+    }
+
+    // Move or rotation played during the step, a single one is played at a time
+    pub fn get_move(&self) -> Option<PlacementStep> {
+        if self.right {
+            Some(PlacementStep::Right)
+        } else if self.left {
+            Some(PlacementStep::Left)
+        } else if self.rotate {
+            Some(PlacementStep::RotateClockwise)
+        } else if self.rotate_ccw {
+            Some(PlacementStep::RotateCounterClockwise)
+        } else if self.rotate_180 {
+            Some(PlacementStep::RotateHalf)
+        } else {
+            None
+        }
+    }
+
+    // Whether a key or a button was pressed for the move, the repeats of a held direction
+    // being part of the press that started them
+    pub fn is_move_pressed(&self) -> bool {
+        match self.get_move() {
+            Some(PlacementStep::Left | PlacementStep::Right) => !self.repeat,
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_bits() == 0
+    }
+
+    pub fn to_bits(&self) -> u8 {
+        (self.left as u8)
+            | (self.right as u8) << 1
+            | (self.rotate as u8) << 2
+            | (self.down as u8) << 3
+            | (self.rotate_ccw as u8) << 4
+            | (self.rotate_180 as u8) << 5
+            | (self.hard_drop as u8) << 6
+            | (self.hold as u8) << 7
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            left: bits & 1 != 0,
+            right: bits & (1 << 1) != 0,
+            rotate: bits & (1 << 2) != 0,
+            down: bits & (1 << 3) != 0,
+            rotate_ccw: bits & (1 << 4) != 0,
+            rotate_180: bits & (1 << 5) != 0,
+            hard_drop: bits & (1 << 6) != 0,
+            hold: bits & (1 << 7) != 0,
+            repeat: false,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::consts::{CLEARED_UP_LINES_PER_LEVEL, MAX_LEVEL};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Rules deciding when the level goes up as lines get cleared
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LevelProgression {
+    #[default]
+    Fixed, // A level every CLEARED_UP_LINES_PER_LEVEL lines
+    Nes,      // The first level up depends on the starting level, then every 10 lines
+    Variable, // Guideline variable goal: 5 lines times the current level
+}
+
+impl LevelProgression {
+    pub fn get_level(&self, start_level: u16, cleared_lines: u32) -> u16 {
+        let start_level = start_level as u32;
+        let level = match self {
+            LevelProgression::Fixed => {
+                start_level + cleared_lines / CLEARED_UP_LINES_PER_LEVEL as u32
+            }
+            LevelProgression::Nes => {
+                // NES levels are zero based, hence the formula works on the previous level
+                let nes_level = start_level - 1;
+                let first_level_up_lines = std::cmp::min(
+                    nes_level * 10 + 10,
+                    std::cmp::max(100, (nes_level * 10).saturating_sub(50)),
+                );
+
+                if cleared_lines < first_level_up_lines {
+                    start_level
+                } else {
+                    start_level + 1 + (cleared_lines - first_level_up_lines) / 10
+                }
+            }
+            LevelProgression::Variable => {
+                let mut level = start_level;
+                let mut remaining_lines = cleared_lines;
+                while level < MAX_LEVEL as u32 && remaining_lines >= 5 * level {
+                    remaining_lines -= 5 * level;
+                    level += 1;
+                }
+
+                level
+            }
+        };
+
+        std::cmp::min(MAX_LEVEL as u32, level) as u16
+    }
+}
+
+impl FromStr for LevelProgression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "fixed" => Ok(LevelProgression::Fixed),
+            "nes" => Ok(LevelProgression::Nes),
+            "variable" => Ok(LevelProgression::Variable),
+            _ => Err(format!("Unknown level progression '{0}'", value)),
+        }
+    }
+}
+
+impl Display for LevelProgression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            LevelProgression::Fixed => write!(f, "Fixed"),
+            LevelProgression::Nes => write!(f, "NES"),
+            LevelProgression::Variable => write!(f, "Variable"),
+        }
+    }
+}
@@ -0,0 +1,619 @@
+use crate::game::tetromino::{self, Tetromino, TetrominoType};
+use crate::game::{NUMBER_OF_CELLS, NUMBER_OF_COLUMNS, NUMBER_OF_ROWS};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+// Directory of the openers shipped with the game, found by their name
+pub const OPENERS_DIRECTORY: &str = "assets/openers";
+
+// Cells a tetromino of the opener is expected to lock in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenerPlacement {
+    pub tetromino: TetrominoType,
+    pub cells: Vec<u8>, // Sorted
+    pub stage: usize,   // Board of the opener drawing the tetromino
+}
+
+// Letter a tetromino is drawn with on the board of an opener, and its sorted cells
+type DrawnTetromino = (char, Vec<u8>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpenerStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Opener {
+    name: String,
+    pieces: Vec<TetrominoType>,
+    placements: Vec<OpenerPlacement>, // In the order of the pieces
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Resource))]
+pub struct OpenerProgress {
+    opener: Opener,
+    placed: Vec<bool>,
+    status: OpenerStatus,
+    // Last tetromino locked, and whether it was where the opener expected it
+    last_lock: Option<(TetrominoType, bool)>,
+}
+
+impl Opener {
+    // Loads an opener from a file made of a header and the board it builds, such as:
+    //
+    //   name: Stack
+    //   pieces: I O i
+    //   board:
+    //   i.........
+    //   i.........
+    //   i.....OO..
+    //   i.IIIIOO..
+    //
+    // Every tetromino is drawn with its letter where it is expected to lock, in upper case
+    // for the first bag and in lower case for the next one, so two tetrominoes of the same
+    // type touching each other can be told apart, and the pieces are written the same way.
+    // The pieces are handed out in their order and every tetromino must be placeable after
+    // the ones before it, only the last one of a board being allowed to clear lines, as the
+    // following ones would be moved down. Openers going on after a line clear draw another
+    // board for the next pieces, with an X for every cell left by the boards before it.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read opener '{0}': {1}", path, error))?;
+        content.parse()
+    }
+
+    // Loads one of the openers shipped with the game by its name, such as tki, or an opener file
+    pub fn find(value: &str) -> Result<Self, String> {
+        let path = Path::new(value);
+        if path.extension().is_none() && path.components().count() == 1 {
+            return Self::load(&format!(
+                "{0}/{1}.txt",
+                OPENERS_DIRECTORY,
+                value.to_lowercase()
+            ));
+        }
+        Self::load(value)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_pieces(&self) -> &[TetrominoType] {
+        &self.pieces
+    }
+
+    pub fn get_placements(&self) -> &[OpenerPlacement] {
+        &self.placements
+    }
+
+    // Splits the board into the tetrominoes drawn on it, by letter and by connected cells, and
+    // the cells left by the boards before it
+    fn parse_board(rows: &[&str]) -> Result<(Vec<DrawnTetromino>, Vec<u8>), String> {
+        if rows.len() > NUMBER_OF_ROWS as usize {
+            return Err(format!(
+                "Opener board has {0} rows but at most {1} are allowed",
+                rows.len(),
+                NUMBER_OF_ROWS
+            ));
+        }
+
+        let mut letters = [None; NUMBER_OF_CELLS as usize];
+        let mut stack = Vec::new();
+        let first_row = NUMBER_OF_ROWS as usize - rows.len();
+        for (index, row) in rows.iter().enumerate() {
+            let cells: Vec<char> = row.chars().collect();
+            if cells.len() != NUMBER_OF_COLUMNS as usize {
+                return Err(format!(
+                    "Opener board row '{0}' must have exactly {1} cells",
+                    row, NUMBER_OF_COLUMNS
+                ));
+            }
+
+            for (col, cell) in cells.into_iter().enumerate() {
+                let board_cell =
+                    Tetromino::get_cell_from_row_and_column((first_row + index) as u8, col as u8);
+                match cell {
+                    '.' => {}
+                    'X' => stack.push(board_cell),
+                    _ if cell.to_string().parse::<TetrominoType>().is_ok() => {
+                        letters[board_cell as usize] = Some(cell)
+                    }
+                    _ => return Err(format!("Invalid opener board cell '{0}'", cell)),
+                }
+            }
+        }
+
+        let mut shapes = Vec::new();
+        let mut visited = [false; NUMBER_OF_CELLS as usize];
+        for start in 0..NUMBER_OF_CELLS {
+            let Some(letter) = letters[start as usize] else {
+                continue;
+            };
+            if visited[start as usize] {
+                continue;
+            }
+
+            visited[start as usize] = true;
+            let mut cells = vec![start];
+            let mut current = 0;
+            while current < cells.len() {
+                let (row, col) = Tetromino::get_row_and_column_by_cell(cells[current]);
+                let neighbors = [
+                    (row as i8 - 1, col as i8),
+                    (row as i8 + 1, col as i8),
+                    (row as i8, col as i8 - 1),
+                    (row as i8, col as i8 + 1),
+                ];
+                for (row, col) in neighbors {
+                    if row < 0
+                        || row >= NUMBER_OF_ROWS as i8
+                        || col < 0
+                        || col >= NUMBER_OF_COLUMNS as i8
+                    {
+                        continue;
+                    }
+                    let cell = Tetromino::get_cell_from_row_and_column(row as u8, col as u8);
+                    if !visited[cell as usize] && letters[cell as usize] == Some(letter) {
+                        visited[cell as usize] = true;
+                        cells.push(cell);
+                    }
+                }
+                current += 1;
+            }
+
+            if cells.len() != 4 {
+                return Err(format!(
+                    "Opener tetromino '{0}' is drawn with {1} cells instead of 4",
+                    letter,
+                    cells.len()
+                ));
+            }
+            cells.sort();
+            shapes.push((letter, cells));
+        }
+
+        Ok((shapes, stack))
+    }
+
+    // Matches the pieces to the tetrominoes drawn with the same letter, in the order they are drawn
+    fn match_pieces(
+        pieces: &[char],
+        shapes: Vec<DrawnTetromino>,
+        stage: usize,
+    ) -> Result<Vec<OpenerPlacement>, String> {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        let mut placements = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            let count = counts.entry(*piece).or_default();
+            let Some((_, cells)) = shapes
+                .iter()
+                .filter(|(letter, _)| letter == piece)
+                .nth(*count)
+            else {
+                return Err(format!(
+                    "Opener piece '{0}' is not drawn on its board",
+                    piece
+                ));
+            };
+            *count += 1;
+            placements.push(OpenerPlacement {
+                tetromino: piece.to_string().parse()?,
+                cells: cells.clone(),
+                stage,
+            });
+        }
+
+        Ok(placements)
+    }
+
+    // Checks every tetromino can be brought where it is expected, once the previous ones are,
+    // and every board draws the stack left by the boards before it once their lines are cleared
+    fn check_placements(placements: &[OpenerPlacement], stacks: &[Vec<u8>]) -> Result<(), String> {
+        let mut board = [0; NUMBER_OF_CELLS as usize];
+        for (index, placement) in placements.iter().enumerate() {
+            let first_of_stage = index == 0 || placements[index - 1].stage != placement.stage;
+            if first_of_stage {
+                let stack: Vec<u8> = (0..NUMBER_OF_CELLS)
+                    .filter(|cell| board[*cell as usize] != 0)
+                    .collect();
+                if stack != stacks[placement.stage] {
+                    return Err(format!(
+                        "Opener board {0} does not draw the cells left by the boards before it",
+                        placement.stage + 1
+                    ));
+                }
+            }
+
+            if tetromino::get_fewest_steps(&placement.tetromino, 1, &board, &placement.cells)
+                .is_none()
+            {
+                return Err(format!(
+                    "Opener piece {0} cannot be placed after the pieces before it",
+                    placement.tetromino
+                ));
+            }
+
+            for cell in &placement.cells {
+                board[*cell as usize] = crate::game::LOCKED_CELL;
+            }
+
+            let clears_lines = placement.cells.iter().any(|cell| {
+                let (row, _) = Tetromino::get_row_and_column_by_cell(*cell);
+                is_row_filled(&board, row)
+            });
+            let last_of_stage = placements
+                .get(index + 1)
+                .is_none_or(|next| next.stage != placement.stage);
+            if clears_lines && !last_of_stage {
+                return Err(format!(
+                    "Opener piece {0} clears lines before the last piece of its board",
+                    placement.tetromino
+                ));
+            }
+            if last_of_stage {
+                collapse_filled_rows(&mut board);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_row_filled(board: &[u8; NUMBER_OF_CELLS as usize], row: u8) -> bool {
+    (0..NUMBER_OF_COLUMNS)
+        .all(|col| board[Tetromino::get_cell_from_row_and_column(row, col) as usize] != 0)
+}
+
+// Removes the filled rows, moving down the ones above them
+fn collapse_filled_rows(board: &mut [u8; NUMBER_OF_CELLS as usize]) {
+    let mut target_row = NUMBER_OF_ROWS;
+    for row in (0..NUMBER_OF_ROWS).rev() {
+        if is_row_filled(board, row) {
+            continue;
+        }
+
+        target_row -= 1;
+        for col in 0..NUMBER_OF_COLUMNS {
+            let source = Tetromino::get_cell_from_row_and_column(row, col) as usize;
+            let target = Tetromino::get_cell_from_row_and_column(target_row, col) as usize;
+            board[target] = board[source];
+        }
+    }
+    for row in 0..target_row {
+        for col in 0..NUMBER_OF_COLUMNS {
+            board[Tetromino::get_cell_from_row_and_column(row, col) as usize] = 0;
+        }
+    }
+}
+
+impl OpenerProgress {
+    pub fn new(opener: Opener) -> Self {
+        let placed = vec![false; opener.placements.len()];
+        Self {
+            opener,
+            placed,
+            status: OpenerStatus::InProgress,
+            last_lock: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.placed.fill(false);
+        self.status = OpenerStatus::InProgress;
+        self.last_lock = None;
+    }
+
+    pub fn get_opener(&self) -> &Opener {
+        &self.opener
+    }
+
+    pub fn get_status(&self) -> &OpenerStatus {
+        &self.status
+    }
+
+    pub fn get_last_lock(&self) -> Option<&(TetrominoType, bool)> {
+        self.last_lock.as_ref()
+    }
+
+    pub fn get_placed_count(&self) -> usize {
+        self.placed.iter().filter(|placed| **placed).count()
+    }
+
+    // Gets the board of the opener being built, the first one with placements still expected
+    fn get_stage(&self) -> Option<usize> {
+        self.opener
+            .placements
+            .iter()
+            .zip(&self.placed)
+            .find(|(_, placed)| !**placed)
+            .map(|(placement, _)| placement.stage)
+    }
+
+    // Gets the placements still expected on the board being built, to be shown on the board
+    pub fn get_remaining_placements(&self) -> impl Iterator<Item = &OpenerPlacement> {
+        let stage = self.get_stage();
+        self.opener
+            .placements
+            .iter()
+            .zip(&self.placed)
+            .filter(move |(placement, placed)| !**placed && Some(placement.stage) == stage)
+            .map(|(placement, _)| placement)
+    }
+
+    // Validates a locked tetromino against the placements still expected of its type on the
+    // board being built, in any order as holding changes the order the tetrominoes are placed in
+    pub fn record_lock(&mut self, tetromino: &TetrominoType, cells: &[u8]) -> &OpenerStatus {
+        if self.status != OpenerStatus::InProgress {
+            return &self.status;
+        }
+
+        let mut cells = cells.to_vec();
+        cells.sort();
+        let stage = self.get_stage();
+        let expected =
+            self.opener
+                .placements
+                .iter()
+                .zip(&self.placed)
+                .position(|(placement, placed)| {
+                    !*placed
+                        && Some(placement.stage) == stage
+                        && placement.tetromino == *tetromino
+                        && placement.cells == cells
+                });
+
+        self.last_lock = Some((tetromino.clone(), expected.is_some()));
+        match expected {
+            Some(index) => {
+                self.placed[index] = true;
+                if self.placed.iter().all(|placed| *placed) {
+                    self.status = OpenerStatus::Completed;
+                }
+            }
+            None => self.status = OpenerStatus::Failed,
+        }
+
+        &self.status
+    }
+}
+
+impl FromStr for Opener {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut name = String::from("Opener");
+        let mut pieces = Vec::new();
+        let mut boards: Vec<Vec<&str>> = Vec::new();
+
+        for line in value.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Rows follow their board key, up to the next board
+            if let Some(rows) = boards.last_mut()
+                && !line.contains(':')
+            {
+                rows.push(line);
+                continue;
+            }
+
+            let Some((key, content)) = line.split_once(':') else {
+                return Err(format!("Invalid opener line '{0}'", line));
+            };
+
+            let content = content.trim();
+            match key.trim() {
+                "name" => name = content.to_string(),
+                "pieces" => {
+                    for piece in content.chars().filter(|piece| !piece.is_whitespace()) {
+                        piece.to_string().parse::<TetrominoType>()?;
+                        pieces.push(piece);
+                    }
+                }
+                "board" => boards.push(Vec::new()),
+                _ => return Err(format!("Unknown opener key '{0}'", key)),
+            }
+        }
+
+        if pieces.is_empty() {
+            return Err(String::from("Opener has no pieces"));
+        }
+
+        let boards = boards
+            .iter()
+            .map(|rows| Self::parse_board(rows))
+            .collect::<Result<Vec<_>, _>>()?;
+        let drawn = boards.iter().map(|(shapes, _)| shapes.len()).sum::<usize>();
+        if drawn != pieces.len() {
+            return Err(format!(
+                "Opener has {0} pieces but {1} tetrominoes are drawn on its boards",
+                pieces.len(),
+                drawn
+            ));
+        }
+
+        // Every board takes the pieces drawn on it, in their order
+        let mut placements: Vec<OpenerPlacement> = Vec::with_capacity(pieces.len());
+        let mut stacks = Vec::with_capacity(boards.len());
+        for (stage, (shapes, stack)) in boards.into_iter().enumerate() {
+            if shapes.is_empty() {
+                return Err(format!("Opener board {0} draws no tetromino", stage + 1));
+            }
+
+            let start = placements.len();
+            let end = start + shapes.len();
+            placements.extend(Self::match_pieces(&pieces[start..end], shapes, stage)?);
+            stacks.push(stack);
+        }
+        Self::check_placements(&placements, &stacks)?;
+
+        Ok(Self {
+            name,
+            pieces: placements
+                .iter()
+                .map(|placement| placement.tetromino.clone())
+                .collect(),
+            placements,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::LOCKED_CELL;
+    use crate::game::tetromino::{DroppedStatus, TetrominoProvider};
+    use bevy_prng::ChaCha8Rng;
+    use rand::SeedableRng;
+
+    fn load_openers() -> Vec<Opener> {
+        let mut paths: Vec<_> = std::fs::read_dir(OPENERS_DIRECTORY)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                let path = path.to_str().unwrap();
+                Opener::load(path).unwrap_or_else(|error| panic!("{0}", error))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loads_every_opener_shipped() {
+        let openers = load_openers();
+        assert_eq!(openers.len(), 4);
+        for opener in &openers {
+            assert_eq!(opener.get_pieces().len(), opener.get_placements().len());
+        }
+        for name in ["tki", "dt-cannon", "albatross", "pco"] {
+            assert!(Opener::find(name).is_ok(), "{0}", name);
+        }
+    }
+
+    #[test]
+    fn completes_the_openers_placed_as_drawn() {
+        for opener in load_openers() {
+            let mut progress = OpenerProgress::new(opener.clone());
+            for (index, placement) in opener.get_placements().iter().enumerate() {
+                let status = progress.record_lock(&placement.tetromino, &placement.cells);
+                match index + 1 == opener.get_placements().len() {
+                    true => assert_eq!(*status, OpenerStatus::Completed),
+                    false => assert_eq!(*status, OpenerStatus::InProgress),
+                }
+            }
+            assert_eq!(progress.get_placed_count(), opener.get_placements().len());
+            let last = opener.get_placements().last().unwrap();
+            assert_eq!(
+                progress.get_last_lock(),
+                Some(&(last.tetromino.clone(), true))
+            );
+        }
+    }
+
+    #[test]
+    fn fails_the_opener_placed_elsewhere() {
+        let opener = Opener::find("tki").unwrap();
+        let mut progress = OpenerProgress::new(opener.clone());
+        let placements = opener.get_placements();
+        progress.record_lock(&placements[0].tetromino, &placements[0].cells);
+
+        // The right tetromino in the cells of another one
+        let status = progress.record_lock(&placements[1].tetromino, &placements[2].cells);
+        assert_eq!(*status, OpenerStatus::Failed);
+        assert_eq!(
+            progress.get_last_lock(),
+            Some(&(placements[1].tetromino.clone(), false))
+        );
+
+        // Placing the rest as drawn does not bring it back
+        for placement in &placements[1..] {
+            progress.record_lock(&placement.tetromino, &placement.cells);
+        }
+        assert_eq!(*progress.get_status(), OpenerStatus::Failed);
+
+        progress.reset();
+        assert_eq!(*progress.get_status(), OpenerStatus::InProgress);
+        assert_eq!(
+            progress.get_remaining_placements().count(),
+            placements.len()
+        );
+    }
+
+    #[test]
+    fn expects_the_next_board_once_its_lines_are_cleared() {
+        let opener = Opener::find("dt-cannon").unwrap();
+        let placements = opener.get_placements();
+        let (last, first_board) = placements.split_last().unwrap();
+        assert!(first_board.iter().all(|placement| placement.stage == 0));
+        assert_eq!(last.stage, 1);
+
+        let mut progress = OpenerProgress::new(opener.clone());
+        assert_eq!(
+            progress.get_remaining_placements().count(),
+            first_board.len()
+        );
+        assert_eq!(
+            *progress.record_lock(&last.tetromino, &last.cells),
+            OpenerStatus::Failed
+        );
+
+        progress.reset();
+        for placement in first_board {
+            progress.record_lock(&placement.tetromino, &placement.cells);
+        }
+        assert_eq!(
+            progress.get_remaining_placements().collect::<Vec<_>>(),
+            vec![last]
+        );
+    }
+
+    #[test]
+    fn kicks_the_t_spins_of_the_openers() {
+        for name in ["dt-cannon", "albatross"] {
+            let opener = Opener::find(name).unwrap();
+            let placements = opener.get_placements();
+            let mut board = [0; NUMBER_OF_CELLS as usize];
+            let mut t_spins = 0;
+            for (index, placement) in placements.iter().enumerate() {
+                if placement.tetromino == TetrominoType::T {
+                    let mut rng = ChaCha8Rng::from_seed([0; 32]);
+                    let mut provider =
+                        TetrominoProvider::new(&mut rng, 1, &[TetrominoType::T], None);
+                    let found = provider
+                        .find_current_placements(&board)
+                        .into_iter()
+                        .find(|found| found.cells == placement.cells)
+                        .unwrap();
+                    for step in found.steps {
+                        provider.apply_current_step(step, &board);
+                    }
+                    while let DroppedStatus::Dropped = provider.drop_down(&board) {}
+                    assert!(provider.is_current_t_spin(&board), "{0}", name);
+                    t_spins += 1;
+                }
+
+                for cell in &placement.cells {
+                    board[*cell as usize] = LOCKED_CELL;
+                }
+                if placements
+                    .get(index + 1)
+                    .is_none_or(|next| next.stage != placement.stage)
+                {
+                    collapse_filled_rows(&mut board);
+                }
+            }
+            assert_eq!(t_spins, 2);
+            assert!(board.iter().filter(|cell| **cell != 0).count() < 10);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::game::tetromino::{Tetromino, TetrominoType};
+use crate::game::{GARBAGE_CELL, LockOutcome, NUMBER_OF_CELLS, NUMBER_OF_COLUMNS, NUMBER_OF_ROWS};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleObjective {
+    ClearAllLines,
+    TSpinTriple,
+    PerfectClear(u8), // Number of pieces allowed to reach the perfect clear
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleStatus {
+    InProgress,
+    Solved,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    name: String,
+    objective: PuzzleObjective,
+    board: [u8; NUMBER_OF_CELLS as usize],
+    pieces: Vec<TetrominoType>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy::prelude::Resource))]
+pub struct PuzzleProgress {
+    puzzle: Puzzle,
+    used_pieces: u8,
+    status: PuzzleStatus,
+}
+
+impl Puzzle {
+    // Loads a puzzle from a file made of a header and a board, such as:
+    //
+    //   name: Three lines
+    //   objective: t-spin-triple
+    //   pieces: T
+    //   board:
+    //   XXXXXXX...
+    //   XXXXXXX.XX
+    //   XXXXXXX..X
+    //
+    // Board rows are aligned to the bottom of the board, 'X' marks a garbage cell
+    // and '.' an empty one. Lines starting with '#' in the header are comments.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read puzzle '{0}': {1}", path, error))?;
+        content.parse()
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_objective(&self) -> &PuzzleObjective {
+        &self.objective
+    }
+
+    pub fn get_board(&self) -> [u8; NUMBER_OF_CELLS as usize] {
+        self.board
+    }
+
+    pub fn get_pieces(&self) -> &[TetrominoType] {
+        &self.pieces
+    }
+
+    // Gets the number of pieces that can be used before the puzzle is failed
+    fn get_pieces_limit(&self) -> u8 {
+        let available_pieces = self.pieces.len() as u8;
+        match self.objective {
+            PuzzleObjective::PerfectClear(pieces) => std::cmp::min(pieces, available_pieces),
+            _ => available_pieces,
+        }
+    }
+
+    fn parse_board(rows: &[&str]) -> Result<[u8; NUMBER_OF_CELLS as usize], String> {
+        if rows.len() > NUMBER_OF_ROWS as usize {
+            return Err(format!(
+                "Puzzle board has {0} rows but at most {1} are allowed",
+                rows.len(),
+                NUMBER_OF_ROWS
+            ));
+        }
+
+        let mut board = [0; NUMBER_OF_CELLS as usize];
+        let first_row = NUMBER_OF_ROWS as usize - rows.len();
+        for (index, row) in rows.iter().enumerate() {
+            let cells: Vec<char> = row.chars().collect();
+            if cells.len() != NUMBER_OF_COLUMNS as usize {
+                return Err(format!(
+                    "Puzzle board row '{0}' must have exactly {1} cells",
+                    row, NUMBER_OF_COLUMNS
+                ));
+            }
+
+            for (col, cell) in cells.iter().enumerate() {
+                let board_cell =
+                    Tetromino::get_cell_from_row_and_column((first_row + index) as u8, col as u8);
+                match cell {
+                    '.' => {}
+                    'X' | 'x' => board[board_cell as usize] = GARBAGE_CELL,
+                    _ => return Err(format!("Invalid puzzle board cell '{0}'", cell)),
+                }
+            }
+        }
+
+        Ok(board)
+    }
+}
+
+impl PuzzleProgress {
+    pub fn new(puzzle: Puzzle) -> Self {
+        Self {
+            puzzle,
+            used_pieces: 0,
+            status: PuzzleStatus::InProgress,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.used_pieces = 0;
+        self.status = PuzzleStatus::InProgress;
+    }
+
+    pub fn get_puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    pub fn get_status(&self) -> &PuzzleStatus {
+        &self.status
+    }
+
+    pub fn get_remaining_pieces(&self) -> u8 {
+        self.puzzle.get_pieces_limit() - self.used_pieces
+    }
+
+    // Validates the objective after a tetromino has been locked
+    pub fn record_lock(&mut self, outcome: &LockOutcome) -> &PuzzleStatus {
+        if self.status != PuzzleStatus::InProgress {
+            return &self.status;
+        }
+
+        self.used_pieces += 1;
+        let solved = match self.puzzle.objective {
+            PuzzleObjective::ClearAllLines => !outcome.garbage_left,
+            PuzzleObjective::TSpinTriple => outcome.t_spin && outcome.cleared_lines == 3,
+            PuzzleObjective::PerfectClear(_) => outcome.perfect_clear,
+        };
+
+        if solved {
+            self.status = PuzzleStatus::Solved;
+        } else if outcome.topped_out || self.used_pieces >= self.puzzle.get_pieces_limit() {
+            self.status = PuzzleStatus::Failed;
+        }
+
+        &self.status
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut name = String::from("Puzzle");
+        let mut objective = None;
+        let mut pieces = Vec::new();
+        let mut rows = Vec::new();
+        let mut reading_board = false;
+
+        for line in value.lines() {
+            let line = line.trim();
+            if reading_board {
+                if !line.is_empty() {
+                    rows.push(line);
+                }
+                continue;
+            }
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, content)) = line.split_once(':') else {
+                return Err(format!("Invalid puzzle line '{0}'", line));
+            };
+
+            let content = content.trim();
+            match key.trim() {
+                "name" => name = content.to_string(),
+                "objective" => objective = Some(content.parse::<PuzzleObjective>()?),
+                "pieces" => {
+                    for piece in content.chars().filter(|piece| !piece.is_whitespace()) {
+                        pieces.push(piece.to_string().parse::<TetrominoType>()?);
+                    }
+                }
+                "board" => reading_board = true,
+                _ => return Err(format!("Unknown puzzle key '{0}'", key)),
+            }
+        }
+
+        let Some(objective) = objective else {
+            return Err(String::from("Puzzle has no objective"));
+        };
+
+        if pieces.is_empty() {
+            return Err(String::from("Puzzle has no pieces"));
+        }
+
+        Ok(Self {
+            name,
+            objective,
+            board: Self::parse_board(&rows)?,
+            pieces,
+        })
+    }
+}
+
+impl FromStr for PuzzleObjective {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some("clear-all-lines"), None) => Ok(PuzzleObjective::ClearAllLines),
+            (Some("t-spin-triple"), None) => Ok(PuzzleObjective::TSpinTriple),
+            (Some("perfect-clear"), Some(pieces)) => match pieces.parse::<u8>() {
+                Ok(pieces) if pieces > 0 => Ok(PuzzleObjective::PerfectClear(pieces)),
+                _ => Err(format!("Invalid number of pieces '{0}'", pieces)),
+            },
+            _ => Err(format!("Unknown puzzle objective '{0}'", value)),
+        }
+    }
+}
+
+impl Display for PuzzleObjective {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PuzzleObjective::ClearAllLines => write!(f, "Clear all lines"),
+            PuzzleObjective::TSpinTriple => write!(f, "T-spin triple"),
+            PuzzleObjective::PerfectClear(pieces) => write!(f, "Perfect clear in {0}", pieces),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::consts::*;
+use crate::game::level::LevelProgression;
+use serde::{Deserialize, Serialize};
+
+// Values changing how the game plays, the same for every instance of a networked game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameplaySettings {
+    pub base_speed_ms: u64,
+    pub level_speed_delta_ms: u64,
+    pub min_speed_ms: u64,
+    pub clean_up_occupied_rows_ms: u64,
+    pub points_for_cleared_row: u32,
+    pub points_for_tetromino_dropped: u32,
+}
+
+// Points, cleared lines and level reached by a player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameScore {
+    pub level: u16,
+    pub start_level: u16,
+    pub level_progression: LevelProgression,
+    pub filled_up_lines: u32,
+    pub score: u32,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            base_speed_ms: BASE_SPEED_MS,
+            level_speed_delta_ms: LEVEL_SPEED_DELTA,
+            min_speed_ms: MIN_SPEED_MS,
+            clean_up_occupied_rows_ms: CLEAN_UP_OCCUPIED_ROWS_TIME_DELTA_MS,
+            points_for_cleared_row: POINTS_FOR_CLEARED_ROW,
+            points_for_tetromino_dropped: POINTS_FOR_TETROMINO_DROPPED,
+        }
+    }
+}
+
+impl GameplaySettings {
+    pub fn get_drop_down_ms(&self, level: u16) -> u64 {
+        let expected_speed_delta = (level - 1) as u64 * self.level_speed_delta_ms;
+        self.base_speed_ms
+            .saturating_sub(expected_speed_delta)
+            .max(self.min_speed_ms)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_speed_ms == 0 {
+            return Err(String::from("gameplay.min_speed_ms must be greater than 0"));
+        }
+        if self.base_speed_ms < self.min_speed_ms {
+            return Err(format!(
+                "gameplay.base_speed_ms ({0}) must not be lower than gameplay.min_speed_ms ({1})",
+                self.base_speed_ms, self.min_speed_ms
+            ));
+        }
+        if self.clean_up_occupied_rows_ms == 0 {
+            return Err(String::from(
+                "gameplay.clean_up_occupied_rows_ms must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl GameScore {
+    pub fn new(start_level: u16, level_progression: LevelProgression) -> Self {
+        Self {
+            level: start_level,
+            start_level,
+            level_progression,
+            filled_up_lines: 0,
+            score: 0,
+        }
+    }
+
+    // Scores a tetromino locked clearing some lines, the level following the lines cleared
+    pub fn record_lock(&mut self, cleared_lines: u8, gameplay: &GameplaySettings) -> u32 {
+        let points = gameplay.points_for_tetromino_dropped
+            + cleared_lines as u32 * gameplay.points_for_cleared_row;
+        self.score += points;
+        if cleared_lines > 0 {
+            self.filled_up_lines += cleared_lines as u32;
+            self.level = self
+                .level_progression
+                .get_level(self.start_level, self.filled_up_lines);
+        }
+        points
+    }
+}
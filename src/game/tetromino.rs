@@ -1,8 +1,11 @@
 use crate::game;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TetrominoType {
     I,
     O,
@@ -13,7 +16,7 @@ pub enum TetrominoType {
     Z,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TetrominoRotation {
     Zero,        // 0 degrees
     HalfPi,      // 90 degrees
@@ -21,29 +24,41 @@ pub enum TetrominoRotation {
     ThreeHalfPi, // 270 degrees
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TetrominoPosition {
     row: u8,
     col: u8,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Tetromino {
     tetromino: TetrominoType,
     position: TetrominoPosition,
     rotation: TetrominoRotation,
+    // Number of board cells per side covered by every mino (1 for classic, 2 for big)
+    scale: u8,
+    // Whether the last successful movement was a rotation, needed to detect T-spins
+    last_move_was_rotation: bool,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TetrominoProvider {
     current: Tetromino,
     next: Tetromino,
+    scale: u8,
+    // Scripted tetrominoes to hand out before falling back to random ones
+    queue: VecDeque<TetrominoType>,
+    // Tetromino put aside to be swapped later, once per locked tetromino
+    #[serde(default)]
+    held: Option<TetrominoType>,
+    #[serde(default)]
+    held_since_lock: bool,
 }
 
 #[derive(Debug)]
 pub enum DroppedStatus {
     Dropped,
-    NotDropped([u8; 4]),
+    NotDropped(Vec<u8>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -58,6 +73,42 @@ pub enum MoveDirection {
     Left,
 }
 
+#[derive(Debug)]
+pub enum RotationDirection {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+// Single command given to the current tetromino, as a player would
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlacementStep {
+    Left,
+    Right,
+    RotateClockwise,
+    RotateCounterClockwise,
+    RotateHalf,
+    SoftDrop,
+}
+
+// Cells a tetromino can lock in, and the commands taking it above them before the hard drop
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub cells: Vec<u8>, // Sorted
+    pub steps: Vec<PlacementStep>,
+}
+
+impl PlacementStep {
+    pub const ALL: [PlacementStep; 6] = [
+        PlacementStep::Left,
+        PlacementStep::Right,
+        PlacementStep::RotateClockwise,
+        PlacementStep::RotateCounterClockwise,
+        PlacementStep::RotateHalf,
+        PlacementStep::SoftDrop,
+    ];
+}
+
 #[derive(Debug)]
 pub enum CanSpawnMoreTetromino {
     Yes,
@@ -65,6 +116,16 @@ pub enum CanSpawnMoreTetromino {
 }
 
 impl TetrominoType {
+    pub const ALL: [TetrominoType; 7] = [
+        TetrominoType::I,
+        TetrominoType::O,
+        TetrominoType::T,
+        TetrominoType::J,
+        TetrominoType::L,
+        TetrominoType::S,
+        TetrominoType::Z,
+    ];
+
     fn random<R>(rng: &mut R) -> Self
     where
         R: Rng + ?Sized,
@@ -173,10 +234,7 @@ impl TetrominoType {
 }
 
 impl Tetromino {
-    fn new<R>(rng: &mut R) -> Self
-    where
-        R: Rng + ?Sized,
-    {
+    fn new(tetromino_type: TetrominoType, scale: u8) -> Self {
         fn get_starting_column(tetromino_type: &TetrominoType) -> u8 {
             match tetromino_type {
                 TetrominoType::I => 4,
@@ -189,8 +247,8 @@ impl Tetromino {
             }
         }
 
-        let tetromino_type = TetrominoType::random(rng);
-        let starting_column = get_starting_column(&tetromino_type);
+        // Scaled tetrominoes must start on a column aligned with the logical grid
+        let starting_column = get_starting_column(&tetromino_type) / scale * scale;
 
         Self {
             tetromino: tetromino_type,
@@ -199,10 +257,12 @@ impl Tetromino {
                 col: starting_column,
             },
             rotation: TetrominoRotation::Zero,
+            scale,
+            last_move_was_rotation: false,
         }
     }
 
-    fn get_cells(&self) -> [u8; 4] {
+    fn get_cells(&self) -> Vec<u8> {
         self.get_cells_from_position(&self.position, &self.rotation)
     }
 
@@ -210,6 +270,35 @@ impl Tetromino {
         &self,
         position: &TetrominoPosition,
         rotation: &TetrominoRotation,
+    ) -> Vec<(i8, i8)> {
+        let minos = self.get_mino_positions_from_position(position, rotation);
+        if self.scale == 1 {
+            return minos.to_vec();
+        }
+
+        // Every mino is expanded into a square of scale x scale cells, keeping
+        // the position of the tetromino as the top left corner of its pivot mino
+        let row = position.row as i8;
+        let col = position.col as i8;
+        let scale = self.scale as i8;
+        let mut positions = Vec::with_capacity(4 * self.scale as usize * self.scale as usize);
+        for (mino_row, mino_col) in minos {
+            let top = row + (mino_row - row) * scale;
+            let left = col + (mino_col - col) * scale;
+            for delta_row in 0..scale {
+                for delta_col in 0..scale {
+                    positions.push((top + delta_row, left + delta_col));
+                }
+            }
+        }
+
+        positions
+    }
+
+    fn get_mino_positions_from_position(
+        &self,
+        position: &TetrominoPosition,
+        rotation: &TetrominoRotation,
     ) -> [(i8, i8); 4] {
         fn handle_i(position: &TetrominoPosition, rotation: &TetrominoRotation) -> [(i8, i8); 4] {
             let row = position.row as i8;
@@ -372,30 +461,28 @@ impl Tetromino {
         }
 
         match self.tetromino {
-            TetrominoType::I => handle_i(&position, &rotation),
-            TetrominoType::O => handle_o(&position),
-            TetrominoType::T => handle_t(&position, &rotation),
-            TetrominoType::J => handle_j(&position, &rotation),
-            TetrominoType::L => handle_l(&position, &rotation),
-            TetrominoType::S => handle_s(&position, &rotation),
-            TetrominoType::Z => handle_z(&position, &rotation),
+            TetrominoType::I => handle_i(position, rotation),
+            TetrominoType::O => handle_o(position),
+            TetrominoType::T => handle_t(position, rotation),
+            TetrominoType::J => handle_j(position, rotation),
+            TetrominoType::L => handle_l(position, rotation),
+            TetrominoType::S => handle_s(position, rotation),
+            TetrominoType::Z => handle_z(position, rotation),
         }
     }
 
-    fn get_cells_from_positions(&self, positions: &[(i8, i8); 4]) -> [u8; 4] {
-        [
-            Tetromino::get_cell_from_row_and_column(positions[0].0 as u8, positions[0].1 as u8),
-            Tetromino::get_cell_from_row_and_column(positions[1].0 as u8, positions[1].1 as u8),
-            Tetromino::get_cell_from_row_and_column(positions[2].0 as u8, positions[2].1 as u8),
-            Tetromino::get_cell_from_row_and_column(positions[3].0 as u8, positions[3].1 as u8),
-        ]
+    fn get_cells_from_positions(&self, positions: &[(i8, i8)]) -> Vec<u8> {
+        positions
+            .iter()
+            .map(|(row, col)| Tetromino::get_cell_from_row_and_column(*row as u8, *col as u8))
+            .collect()
     }
 
     fn get_cells_from_position(
         &self,
         position: &TetrominoPosition,
         rotation: &TetrominoRotation,
-    ) -> [u8; 4] {
+    ) -> Vec<u8> {
         let positions = self.get_cell_positions_from_position(position, rotation);
         self.get_cells_from_positions(&positions)
     }
@@ -411,31 +498,34 @@ impl Tetromino {
         )
     }
 
+    fn height(&self) -> u8 {
+        self.tetromino.height(&self.rotation) * self.scale
+    }
+
     fn drop_down(&mut self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> DroppedStatus {
-        if self.position.row + self.tetromino.height(&self.rotation) == game::NUMBER_OF_ROWS {
+        if self.position.row + self.height() == game::NUMBER_OF_ROWS {
             let cells = self.get_cells();
             return DroppedStatus::NotDropped(cells);
         }
 
         let next_row = std::cmp::min(
-            game::NUMBER_OF_ROWS - self.tetromino.height(&self.rotation),
-            self.position.row + 1,
+            game::NUMBER_OF_ROWS - self.height(),
+            self.position.row + self.scale,
         );
 
         let next_position = TetrominoPosition {
             row: next_row,
             col: self.position.col,
         };
-        let targeted_cells = self.get_cells_from_position(&next_position, &self.rotation);
-
-        for target_cell in targeted_cells {
-            if board[target_cell as usize] != 0 {
-                let cells = self.get_cells();
-                return DroppedStatus::NotDropped(cells);
-            }
+        if let MoveStatus::NotMoved =
+            self.check_position_and_rotation_are_sound(&next_position, &self.rotation, board)
+        {
+            let cells = self.get_cells();
+            return DroppedStatus::NotDropped(cells);
         }
 
         self.position.row = next_row;
+        self.last_move_was_rotation = false;
         DroppedStatus::Dropped
     }
 
@@ -445,8 +535,8 @@ impl Tetromino {
         board: &[u8; game::NUMBER_OF_CELLS as usize],
     ) -> MoveStatus {
         let next_column = match direction {
-            MoveDirection::Left => self.position.col as i8 - 1,
-            MoveDirection::Right => self.position.col as i8 + 1,
+            MoveDirection::Left => self.position.col as i8 - self.scale as i8,
+            MoveDirection::Right => self.position.col as i8 + self.scale as i8,
         };
 
         // Compute the next position
@@ -456,28 +546,198 @@ impl Tetromino {
         };
 
         let moved =
-            self.check_position_and_rotation_are_sound(&next_position, &self.rotation, &board);
+            self.check_position_and_rotation_are_sound(&next_position, &self.rotation, board);
 
         // If the above checks are successful, then it means that the tetromino moved!
         if let MoveStatus::Moved = moved {
             self.position.col = next_column as u8;
+            self.last_move_was_rotation = false;
         }
 
         moved
     }
 
-    fn rotate(&mut self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> MoveStatus {
-        // Get the next potential rotation
-        let next_rotation = self.tetromino.next_rotation(&self.rotation);
-        let moved =
-            self.check_position_and_rotation_are_sound(&self.position, &next_rotation, &board);
+    fn rotate(
+        &mut self,
+        direction: RotationDirection,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> MoveStatus {
+        // Get the next potential rotation, turning clockwise as many times as needed
+        let turns = match direction {
+            RotationDirection::Clockwise => 1,
+            RotationDirection::Half => 2,
+            RotationDirection::CounterClockwise => 3,
+        };
+        let mut next_rotation = self.rotation.clone();
+        for _ in 0..turns {
+            next_rotation = self.tetromino.next_rotation(&next_rotation);
+        }
 
-        // If the above checks are successful, then it means that the tetromino moved!
-        if let MoveStatus::Moved = moved {
-            self.rotation = next_rotation;
+        // A tetromino not fitting where it is gets kicked aside, as with the Super Rotation
+        // System, a half turn staying in place
+        let kicks = match direction {
+            RotationDirection::Half => 1,
+            _ => 5,
+        };
+        let from = Tetromino::get_kick_offsets(&self.rotation);
+        let to = Tetromino::get_kick_offsets(&next_rotation);
+        for kick in 0..kicks {
+            // Offsets are in columns to the right and rows up, the board rows going down
+            let row = self.position.row as i8 - (from[kick].1 - to[kick].1) * self.scale as i8;
+            let col = self.position.col as i8 + (from[kick].0 - to[kick].0) * self.scale as i8;
+            if row < 0
+                || row >= game::NUMBER_OF_ROWS as i8
+                || col < 0
+                || col >= game::NUMBER_OF_COLUMNS as i8
+            {
+                continue;
+            }
+
+            let next_position = TetrominoPosition {
+                row: row as u8,
+                col: col as u8,
+            };
+            let moved =
+                self.check_position_and_rotation_are_sound(&next_position, &next_rotation, board);
+
+            // If the above checks are successful, then it means that the tetromino moved!
+            if let MoveStatus::Moved = moved {
+                self.position = next_position;
+                self.rotation = next_rotation;
+                self.last_move_was_rotation = true;
+                return MoveStatus::Moved;
+            }
         }
 
-        moved
+        MoveStatus::NotMoved
+    }
+
+    // Offsets of the Super Rotation System for J, L, S, T and Z, shared by the I: the kicks of
+    // a rotation are the offsets of the rotation turned from minus those of the one turned to.
+    // The T spawns pointing down, so its spawn rotation takes the offsets of the upside down one.
+    fn get_kick_offsets(rotation: &TetrominoRotation) -> [(i8, i8); 5] {
+        match rotation {
+            TetrominoRotation::Zero | TetrominoRotation::Pi => [(0, 0); 5],
+            TetrominoRotation::HalfPi => [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+            TetrominoRotation::ThreeHalfPi => [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        }
+    }
+
+    fn apply_step(
+        &mut self,
+        step: PlacementStep,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> MoveStatus {
+        match step {
+            PlacementStep::Left => self.move_with_direction(MoveDirection::Left, board),
+            PlacementStep::Right => self.move_with_direction(MoveDirection::Right, board),
+            PlacementStep::RotateClockwise => self.rotate(RotationDirection::Clockwise, board),
+            PlacementStep::RotateCounterClockwise => {
+                self.rotate(RotationDirection::CounterClockwise, board)
+            }
+            PlacementStep::RotateHalf => self.rotate(RotationDirection::Half, board),
+            PlacementStep::SoftDrop => match self.drop_down(board) {
+                DroppedStatus::Dropped => MoveStatus::Moved,
+                DroppedStatus::NotDropped(_) => MoveStatus::NotMoved,
+            },
+        }
+    }
+
+    // Identifies the position and the rotation, the pivot being always one of the cells of the board
+    fn get_state_index(&self) -> usize {
+        self.rotation.clone() as usize * game::NUMBER_OF_CELLS as usize
+            + Tetromino::get_cell_from_row_and_column(self.position.row, self.position.col) as usize
+    }
+
+    // Explores every position reachable with the commands of the players, keeping for every
+    // place where the tetromino can lock the shortest sequence of commands
+    fn find_placements(&self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> Vec<Placement> {
+        const STATES: usize = 4 * game::NUMBER_OF_CELLS as usize;
+        let mut visited = [false; STATES];
+        // Index of the placement where every state explored lands
+        let mut landings = [usize::MAX; STATES];
+        visited[self.get_state_index()] = true;
+        // States explored, with the one they have been reached from and the command used
+        let mut explored: Vec<(Tetromino, Option<(usize, PlacementStep)>)> =
+            vec![(self.clone(), None)];
+        let mut placements: Vec<Placement> = Vec::new();
+
+        let mut current = 0;
+        while current < explored.len() {
+            let tetromino = explored[current].0.clone();
+
+            // States falling straight down land in the same place, which is looked for once
+            let mut landed = tetromino.clone();
+            let mut falling = Vec::new();
+            let index = loop {
+                let state = landed.get_state_index();
+                if landings[state] != usize::MAX {
+                    break landings[state];
+                }
+                falling.push(state);
+                if let DroppedStatus::NotDropped(mut cells) = landed.drop_down(board) {
+                    cells.sort();
+                    let mut steps = Vec::new();
+                    let mut previous = explored[current].1;
+                    while let Some((parent, step)) = previous {
+                        steps.push(step);
+                        previous = explored[parent].1;
+                    }
+                    steps.reverse();
+                    placements.push(Placement { cells, steps });
+                    break placements.len() - 1;
+                }
+            };
+            for state in falling {
+                landings[state] = index;
+            }
+
+            for step in PlacementStep::ALL {
+                let mut next = tetromino.clone();
+                if next.apply_step(step, board) == MoveStatus::Moved {
+                    let state = next.get_state_index();
+                    if !visited[state] {
+                        visited[state] = true;
+                        explored.push((next, Some((current, step))));
+                    }
+                }
+            }
+            current += 1;
+        }
+
+        placements
+    }
+
+    // A T-spin is a T tetromino locked right after a rotation with at least
+    // three of the four cells diagonal to its center occupied (or out of the board)
+    fn is_t_spin(&self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> bool {
+        if self.tetromino != TetrominoType::T || !self.last_move_was_rotation {
+            return false;
+        }
+
+        let row = self.position.row as i8;
+        let col = self.position.col as i8;
+        let scale = self.scale as i8;
+        let mut occupied_corners = 0;
+        for (delta_row, delta_col) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let corner_row = row + delta_row * scale;
+            let corner_col = col + delta_col * scale;
+            if corner_row < 0
+                || corner_row >= game::NUMBER_OF_ROWS as i8
+                || corner_col < 0
+                || corner_col >= game::NUMBER_OF_COLUMNS as i8
+            {
+                occupied_corners += 1;
+                continue;
+            }
+
+            let cell = Tetromino::get_cell_from_row_and_column(corner_row as u8, corner_col as u8);
+            if board[cell as usize] != 0 {
+                occupied_corners += 1;
+            }
+        }
+
+        occupied_corners >= 3
     }
 
     fn check_position_and_rotation_are_sound(
@@ -486,11 +746,19 @@ impl Tetromino {
         next_rotation: &TetrominoRotation,
         board: &[u8; game::NUMBER_OF_CELLS as usize],
     ) -> MoveStatus {
-        // Check the tetromino rows and columns are within boundaries
-        let cells = self.get_cell_positions_from_position(&next_position, &next_rotation);
-        for cell in cells {
-            let col = cell.1;
-            let row = cell.0;
+        // Classic tetrominoes are checked mino by mino, sparing the allocation of the cells
+        let minos;
+        let scaled_cells;
+        let cells: &[(i8, i8)] = if self.scale == 1 {
+            minos = self.get_mino_positions_from_position(next_position, next_rotation);
+            &minos
+        } else {
+            scaled_cells = self.get_cell_positions_from_position(next_position, next_rotation);
+            &scaled_cells
+        };
+
+        for &(row, col) in cells {
+            // Check the tetromino rows and columns are within boundaries
             if row < 0
                 || row >= game::NUMBER_OF_ROWS as i8
                 || col < 0
@@ -498,11 +766,9 @@ impl Tetromino {
             {
                 return MoveStatus::NotMoved;
             }
-        }
 
-        // Check the tetromino is not crossing any cell already occupied
-        let targeted_cells = self.get_cells_from_positions(&cells);
-        for target_cell in targeted_cells {
+            // Check the tetromino is not crossing any cell already occupied
+            let target_cell = Tetromino::get_cell_from_row_and_column(row as u8, col as u8);
             if board[target_cell as usize] != 0 {
                 return MoveStatus::NotMoved;
             }
@@ -513,13 +779,36 @@ impl Tetromino {
 }
 
 impl TetrominoProvider {
-    pub fn new<R>(rng: &mut R) -> Self
+    pub fn new<R>(
+        rng: &mut R,
+        scale: u8,
+        script: &[TetrominoType],
+        held: Option<TetrominoType>,
+    ) -> Self
     where
         R: Rng + ?Sized,
     {
+        let mut queue: VecDeque<TetrominoType> = script.iter().cloned().collect();
+        let current = Tetromino::new(Self::pop_tetromino_type(&mut queue, rng), scale);
+        let next = Tetromino::new(Self::pop_tetromino_type(&mut queue, rng), scale);
+
         Self {
-            current: Tetromino::new(rng),
-            next: Tetromino::new(rng),
+            current,
+            next,
+            scale,
+            queue,
+            held,
+            held_since_lock: false,
+        }
+    }
+
+    fn pop_tetromino_type<R>(queue: &mut VecDeque<TetrominoType>, rng: &mut R) -> TetrominoType
+    where
+        R: Rng + ?Sized,
+    {
+        match queue.pop_front() {
+            Some(tetromino_type) => tetromino_type,
+            None => TetrominoType::random(rng),
         }
     }
 
@@ -531,8 +820,9 @@ impl TetrominoProvider {
     where
         R: Rng + ?Sized,
     {
-        self.current = (&self.next).clone();
-        self.next = Tetromino::new(rng);
+        self.current = self.next.clone();
+        self.next = Tetromino::new(Self::pop_tetromino_type(&mut self.queue, rng), self.scale);
+        self.held_since_lock = false;
 
         let new_current_cells = self.current.get_cells();
         for cell in new_current_cells {
@@ -552,16 +842,24 @@ impl TetrominoProvider {
         &self.next.tetromino
     }
 
-    pub fn get_current_tetromino_cells(&self) -> [u8; 4] {
+    pub fn get_current_tetromino_cells(&self) -> Vec<u8> {
         self.current.get_cells()
     }
 
-    pub fn get_upcoming_tetromino_cells(&self) -> [u8; 4] {
+    pub fn get_upcoming_tetromino_cells(&self) -> Vec<u8> {
         self.next.get_cells()
     }
 
     pub fn drop_down(&mut self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> DroppedStatus {
-        self.current.drop_down(&board)
+        self.current.drop_down(board)
+    }
+
+    pub fn apply_current_step(
+        &mut self,
+        step: PlacementStep,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> MoveStatus {
+        self.current.apply_step(step, board)
     }
 
     pub fn move_current(
@@ -569,14 +867,146 @@ impl TetrominoProvider {
         direction: MoveDirection,
         board: &[u8; game::NUMBER_OF_CELLS as usize],
     ) -> MoveStatus {
-        self.current.move_with_direction(direction, &board)
+        self.current.move_with_direction(direction, board)
+    }
+
+    pub fn rotate_current(
+        &mut self,
+        direction: RotationDirection,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> MoveStatus {
+        self.current.rotate(direction, board)
+    }
+
+    // Swaps the current tetromino with the held one, or with the next one when none is held.
+    // The swapped in tetromino starts again from the top and must fit there.
+    pub fn hold_current<R>(
+        &mut self,
+        rng: &mut R,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> MoveStatus
+    where
+        R: Rng + ?Sized,
+    {
+        if self.held_since_lock {
+            return MoveStatus::NotMoved;
+        }
+
+        let swapped = match &self.held {
+            Some(held) => Tetromino::new(held.clone(), self.scale),
+            None => self.next.clone(),
+        };
+        if swapped
+            .get_cells()
+            .iter()
+            .any(|cell| board[*cell as usize] != 0)
+        {
+            return MoveStatus::NotMoved;
+        }
+
+        if self.held.is_none() {
+            self.next = Tetromino::new(Self::pop_tetromino_type(&mut self.queue, rng), self.scale);
+        }
+        self.held = Some(self.current.tetromino.clone());
+        self.current = swapped;
+        self.held_since_lock = true;
+
+        MoveStatus::Moved
     }
 
-    pub fn rotate_current(&mut self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> MoveStatus {
-        self.current.rotate(&board)
+    pub fn get_held_tetromino_type(&self) -> Option<&TetrominoType> {
+        self.held.as_ref()
+    }
+
+    pub fn get_held_tetromino_cells(&self) -> Vec<u8> {
+        match &self.held {
+            Some(held) => Tetromino::new(held.clone(), self.scale).get_cells(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn find_current_placements(
+        &self,
+        board: &[u8; game::NUMBER_OF_CELLS as usize],
+    ) -> Vec<Placement> {
+        self.current.find_placements(board)
+    }
+
+    pub fn can_hold(&self) -> bool {
+        !self.held_since_lock
+    }
+
+    pub fn is_current_t_spin(&self, board: &[u8; game::NUMBER_OF_CELLS as usize]) -> bool {
+        self.current.is_t_spin(board)
     }
 }
 
+// Fewest presses taking a tetromino from where it spawns to the cells it locks in, falling being
+// free as the tetromino falls on its own and a direction held down sliding it to the wall
+pub fn get_fewest_steps(
+    tetromino: &TetrominoType,
+    scale: u8,
+    board: &[u8; game::NUMBER_OF_CELLS as usize],
+    cells: &[u8],
+) -> Option<usize> {
+    const STATES: usize = 4 * game::NUMBER_OF_CELLS as usize;
+    let mut cells = cells.to_vec();
+    cells.sort();
+
+    let spawned = Tetromino::new(tetromino.clone(), scale);
+    let mut fewest = [usize::MAX; STATES];
+    fewest[spawned.get_state_index()] = 0;
+    // States reached with the fewest steps come first, as falling adds none
+    let mut pending = VecDeque::from([(spawned, 0)]);
+
+    while let Some((tetromino, steps)) = pending.pop_front() {
+        if steps > fewest[tetromino.get_state_index()] {
+            continue;
+        }
+
+        for step in PlacementStep::ALL {
+            let mut next = tetromino.clone();
+            if step == PlacementStep::SoftDrop {
+                if let DroppedStatus::NotDropped(mut locked) = next.drop_down(board) {
+                    locked.sort();
+                    if locked == cells {
+                        return Some(steps);
+                    }
+                    continue;
+                }
+            } else if next.apply_step(step, board) == MoveStatus::NotMoved {
+                continue;
+            }
+
+            let state = next.get_state_index();
+            let next_steps = match step {
+                PlacementStep::SoftDrop => steps,
+                _ => steps + 1,
+            };
+            if next_steps < fewest[state] {
+                fewest[state] = next_steps;
+                match step {
+                    PlacementStep::SoftDrop => pending.push_front((next, next_steps)),
+                    _ => pending.push_back((next, next_steps)),
+                }
+            }
+        }
+
+        for step in [PlacementStep::Left, PlacementStep::Right] {
+            let mut next = tetromino.clone();
+            while next.apply_step(step, board) == MoveStatus::Moved {}
+
+            let state = next.get_state_index();
+            if steps + 1 < fewest[state] {
+                fewest[state] = steps + 1;
+                pending.push_back((next, steps + 1));
+            }
+        }
+    }
+
+    None
+}
+
 impl Display for TetrominoType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -591,6 +1021,23 @@ impl Display for TetrominoType {
     }
 }
 
+impl FromStr for TetrominoType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_uppercase().as_str() {
+            "I" => Ok(TetrominoType::I),
+            "O" => Ok(TetrominoType::O),
+            "T" => Ok(TetrominoType::T),
+            "J" => Ok(TetrominoType::J),
+            "L" => Ok(TetrominoType::L),
+            "S" => Ok(TetrominoType::S),
+            "Z" => Ok(TetrominoType::Z),
+            _ => Err(format!("Unknown tetromino type '{0}'", value)),
+        }
+    }
+}
+
 impl Display for TetrominoRotation {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -0,0 +1,175 @@
+use crate::controls::{ControlsSettings, InputAction};
+use crate::ecs::*;
+use crate::settings::Settings;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+use std::time::Duration;
+
+// Gamepad driving a player and the direction held down on it, repeated after a delay
+#[derive(Default)]
+struct GamepadSlot {
+    gamepad: Option<Entity>,
+    direction: Option<InputAction>,
+    held: Duration,
+}
+
+// Gamepads assigned to the local players in the order they get connected, a gamepad left out
+// takes the first player without one as soon as any of its buttons is pressed
+#[derive(Resource)]
+pub struct GamepadAssignment {
+    slots: Vec<GamepadSlot>,
+}
+
+impl GamepadAssignment {
+    pub fn new(players: usize) -> Self {
+        Self {
+            slots: (0..players).map(|_| GamepadSlot::default()).collect(),
+        }
+    }
+
+    fn assign(&mut self, gamepad: Entity) -> Option<usize> {
+        if self.slots.iter().any(|slot| slot.gamepad == Some(gamepad)) {
+            return None;
+        }
+
+        let player = self.slots.iter().position(|slot| slot.gamepad.is_none())?;
+        self.slots[player] = GamepadSlot {
+            gamepad: Some(gamepad),
+            ..default()
+        };
+        Some(player)
+    }
+
+    fn unassign(&mut self, gamepad: Entity) -> Option<usize> {
+        let player = self
+            .slots
+            .iter()
+            .position(|slot| slot.gamepad == Some(gamepad))?;
+        self.slots[player] = GamepadSlot::default();
+        Some(player)
+    }
+
+    // Gets the commands given with the gamepad of the player since the last frame
+    pub fn read_input(
+        &mut self,
+        player: u8,
+        gamepads: &Query<&Gamepad>,
+        controls: &ControlsSettings,
+        elapsed: Duration,
+    ) -> PlayerInput {
+        let Some(slot) = self.slots.get_mut(player as usize) else {
+            return PlayerInput::default();
+        };
+        let Some(gamepad) = slot.gamepad.and_then(|gamepad| gamepads.get(gamepad).ok()) else {
+            return PlayerInput::default();
+        };
+
+        let bindings = &controls.gamepad;
+        let direction = [InputAction::MoveLeft, InputAction::MoveRight]
+            .into_iter()
+            .find(|action| bindings.pressed(gamepad, *action));
+
+        // The move happens once when the direction is pressed, and again every arr_ms
+        // once it has been held down for das_ms
+        let repeat = direction == slot.direction;
+        let shift = if !repeat {
+            slot.direction = direction;
+            slot.held = Duration::ZERO;
+            direction.is_some()
+        } else if direction.is_some() {
+            let before = get_repeated_moves(slot.held, controls);
+            slot.held += elapsed;
+            get_repeated_moves(slot.held, controls) > before
+        } else {
+            false
+        };
+
+        PlayerInput {
+            left: shift && direction == Some(InputAction::MoveLeft),
+            right: shift && direction == Some(InputAction::MoveRight),
+            rotate: bindings.just_pressed(gamepad, InputAction::RotateCW),
+            down: bindings.pressed(gamepad, InputAction::SoftDrop),
+            rotate_ccw: bindings.just_pressed(gamepad, InputAction::RotateCCW),
+            rotate_180: bindings.just_pressed(gamepad, InputAction::Rotate180),
+            hard_drop: bindings.just_pressed(gamepad, InputAction::HardDrop),
+            hold: bindings.just_pressed(gamepad, InputAction::Hold),
+            repeat: shift && repeat,
+        }
+    }
+}
+
+fn get_repeated_moves(held: Duration, controls: &ControlsSettings) -> u128 {
+    let held = held.as_millis();
+    let das = controls.das_ms as u128;
+    match held < das {
+        true => 0,
+        false => 1 + (held - das) / controls.arr_ms as u128,
+    }
+}
+
+// Pausing and restarting work from any gamepad, assigned or not
+pub fn any_just_pressed(
+    gamepads: &Query<&Gamepad>,
+    controls: &ControlsSettings,
+    action: InputAction,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| controls.gamepad.just_pressed(gamepad, action))
+}
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                assign_gamepads,
+                read_gamepad_input.run_if(in_state(GameStatus::Running)),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn assign_gamepads(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    mut assignment: ResMut<GamepadAssignment>,
+    gamepads: Query<(Entity, &Gamepad)>,
+) {
+    for event in connection_events.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => match assignment.assign(event.gamepad) {
+                Some(player) => info!("Gamepad {0} plays as player {1}", name, player + 1),
+                None => info!("Gamepad {0} connected, every player has a gamepad", name),
+            },
+            GamepadConnection::Disconnected => {
+                if let Some(player) = assignment.unassign(event.gamepad) {
+                    info!("Gamepad of player {0} disconnected", player + 1);
+                }
+            }
+        }
+    }
+
+    // A player left without gamepad gets the first one used
+    for (entity, gamepad) in gamepads {
+        if gamepad.get_just_pressed().next().is_some()
+            && let Some(player) = assignment.assign(entity)
+        {
+            info!("Gamepad plays as player {0}", player + 1);
+        }
+    }
+}
+
+fn read_gamepad_input(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    gamepads: Query<&Gamepad>,
+    mut assignment: ResMut<GamepadAssignment>,
+    mut players: Query<(&Player, &mut PlayerInput), With<LocalControls>>,
+) {
+    for (player, mut input) in &mut players {
+        input.merge(&assignment.read_input(player.id, &gamepads, &settings.controls, time.delta()));
+    }
+}
@@ -0,0 +1,4 @@
+// Game logic without any window, shared by the game and the Python bindings
+pub mod consts;
+pub mod game;
+pub mod seed;
@@ -1,141 +1,601 @@
-mod consts;
+mod ai;
+mod bot;
+mod cli;
+mod controls;
 mod ecs;
-mod game;
-
+mod editor;
+mod env;
+mod finesse;
+mod fumen;
+mod gamepad;
+mod net;
+mod opener;
+mod pointer;
+mod remote;
+mod replay;
+mod save;
+mod scores;
+mod settings;
+mod spectator;
+mod storage;
+mod undo;
+mod versus;
+
+use blocchi::{consts, game, seed};
+
+use bevy::ecs::query::QueryFilter;
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy_prng::ChaCha8Rng;
 use bevy_rand::prelude::*;
+use clap::Parser;
 use consts::*;
 use ecs::*;
+use rand::SeedableRng;
 use std::time::Duration;
 
 fn main() {
+    let mut arguments = cli::CommandLineArguments::parse();
+
+    let settings_file = arguments.get_settings_file();
+    let mut settings = settings::Settings::load(&settings_file).unwrap_or_else(|error| {
+        eprintln!("{0}", error);
+        std::process::exit(1);
+    });
+
+    // Agents drive the game logic alone, without any window
+    if arguments.env {
+        let env = game::env::Environment::new(
+            arguments.get_mode(),
+            arguments.level,
+            arguments.level_progression.clone(),
+            settings.gameplay,
+        );
+        if let Err(error) = env::serve(env) {
+            eprintln!("{0}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(address) = &arguments.spectate {
+        let client = spectator::SpectatorClient::connect(address).unwrap_or_else(|error| {
+            eprintln!("{0}", error);
+            std::process::exit(1);
+        });
+
+        App::new()
+            .add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: format!("Blocchi - Spectating {0}", address),
+                    ..default()
+                }),
+                ..default()
+            }))
+            .add_plugins(spectator::SpectatorClientPlugin)
+            .add_plugins(settings::SettingsPlugin)
+            .insert_resource(settings::SettingsWatcher::new(settings_file, true))
+            .insert_resource(settings)
+            .insert_resource(client)
+            .run();
+        return;
+    }
+
+    // Networked games are set up by the host, joining blocks until the match starts
+    let seed = arguments.get_seed();
+    let lockstep = match net::connect(&mut arguments, seed.clone()) {
+        Ok(lockstep) => lockstep,
+        Err(error) => {
+            eprintln!("{0}", error);
+            std::process::exit(1);
+        }
+    };
+
+    // Replays are played again with the settings they were recorded with
+    if let Some(replay) = &arguments.replay {
+        arguments.mode = replay.get_mode().clone();
+        arguments.level = replay.get_level();
+        arguments.level_progression = replay.get_level_progression().clone();
+        arguments.versus = replay.get_players() > 1;
+    }
+
+    // Resumed games carry on with the settings they were saved with
+    let saved_game = match arguments.resume {
+        true => match save::SavedGame::load(&arguments.get_save_file()) {
+            Ok(saved_game) => {
+                arguments.mode = saved_game.get_mode().clone();
+                arguments.level = saved_game.get_level();
+                arguments.level_progression = saved_game.get_level_progression().clone();
+                arguments.versus = saved_game.get_players().len() > 1;
+                save::delete(&arguments.get_save_file());
+                Some(saved_game)
+            }
+            Err(error) => {
+                eprintln!("{0}", error);
+                std::process::exit(1);
+            }
+        },
+        false => None,
+    };
+
+    // The position edited is the fumen asked for, otherwise the one saved to the file of the editor
+    if let Some(file) = &arguments.editor
+        && arguments.fumen.is_none()
+    {
+        match editor::load_position(file) {
+            Ok(position) => arguments.fumen = position,
+            Err(error) => {
+                eprintln!("{0}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Both sides of a networked game and the replays simulate the game with the same gameplay
+    if lockstep.is_some() {
+        settings.gameplay = game::scoring::GameplaySettings::default();
+    }
+    if let Some(replay) = &arguments.replay {
+        settings.gameplay = replay.get_gameplay().clone();
+    }
+    // Recorded games are replayed with the gameplay they started with, so it cannot change either
+    let recorded = arguments.puzzle.is_none()
+        && arguments.replay.is_none()
+        && arguments.fumen.is_none()
+        && arguments.editor.is_none()
+        && arguments.opener.is_none()
+        && saved_game.is_none();
+    let fixed_gameplay = lockstep.is_some() || arguments.replay.is_some() || recorded;
+
+    let mode = arguments.get_mode();
+    let versus = arguments.versus || lockstep.is_some();
+
+    // Bots know the classic board only, they are ready once they have accepted the rules
+    let bot = arguments.bot.as_ref().map(|command| {
+        if mode.get_scale() != 1 {
+            eprintln!("Bots cannot play the {0} mode", mode);
+            std::process::exit(1);
+        }
+        bot::Bot::launch(command).unwrap_or_else(|error| {
+            eprintln!("{0}", error);
+            std::process::exit(1);
+        })
+    });
+
     let mut app = App::new();
-    app.add_plugins(DefaultPlugins)
-        .add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: match arguments.replay {
+                Some(_) => format!("Blocchi - {0} replay", mode),
+                None => format!("Blocchi - {0}", mode),
+            },
+            ..default()
+        }),
+        ..default()
+    }));
+
+    app.add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
+        .add_event::<TetrominoLocked>()
+        .add_event::<StatusRequest>()
+        .add_plugins(settings::SettingsPlugin)
+        .insert_resource(settings::SettingsWatcher::new(
+            settings_file,
+            fixed_gameplay,
+        ))
+        .insert_resource(settings.clone())
+        .insert_resource(Time::<Fixed>::from_hz(SIMULATION_STEPS_PER_SECOND))
+        .init_resource::<SimulationClock>()
         .add_systems(Startup, setup)
-        .add_systems(Startup, setup_text_and_scores)
-        .add_systems(Update, restart)
+        .add_systems(
+            Startup,
+            setup_puzzle_text.run_if(resource_exists::<game::puzzle::PuzzleProgress>),
+        )
         .add_systems(
             Update,
-            (move_and_rotate_tetromino, drop_tetromino_down)
+            read_keyboard_input.run_if(in_state(GameStatus::Running)),
+        )
+        .configure_sets(
+            FixedUpdate,
+            (GameplaySet::Input, GameplaySet::Play, GameplaySet::Garbage)
                 .chain()
                 .run_if(in_state(GameStatus::Running)),
         )
         .add_systems(
-            Update,
-            paint_tetromino_outline
-                .run_if(in_any_of_two_states(GameStatus::Running, GameStatus::Pause)),
+            FixedUpdate,
+            (
+                hold_tetromino,
+                move_and_rotate_tetromino,
+                drop_tetromino_down,
+                despawn_filled_up_rows,
+                check_game_over,
+                clear_player_input,
+                advance_simulation_clock,
+            )
+                .chain()
+                .in_set(GameplaySet::Play),
         )
         .add_systems(
-            Update,
-            despawn_filled_up_rows.run_if(in_state(GameStatus::RemovingFilledRows)),
+            FixedUpdate,
+            check_puzzle_progress
+                .after(GameplaySet::Play)
+                .run_if(resource_exists::<game::puzzle::PuzzleProgress>),
         )
+        .add_systems(Update, paint_tetromino_outline)
         .add_systems(Update, paint_occupied_cells_outline)
         .add_systems(Update, paint_board_border_outline)
-        .add_systems(
-            Update,
-            paint_upcoming_tetromino_outline
-                .after(restart)
-                .run_if(in_any_of_two_states(GameStatus::Running, GameStatus::Pause)),
-        )
-        .add_systems(Update, pause)
-        .insert_resource(game::GameBoard::new())
-        .insert_resource(GameSettings {
-            descend_timer: Timer::new(Duration::from_millis(BASE_SPEED_MS), TimerMode::Repeating),
-            last_despawned_cell: None,
-            remove_filled_cells_times: Timer::new(
-                Duration::from_millis(CLEAN_UP_OCCUPIED_ROWS_TIME_DELTA_MS),
-                TimerMode::Repeating,
+        .add_systems(Update, paint_upcoming_tetromino_outline)
+        .add_systems(Update, paint_held_tetromino_outline)
+        .add_systems(Update, update_seed_text)
+        .add_systems(OnEnter(GameStatus::GameOver), show_game_over)
+        .insert_resource(GameSession {
+            versus,
+            seed: lockstep
+                .as_ref()
+                .map(|lockstep| lockstep.get_seed().to_string())
+                .or(arguments
+                    .replay
+                    .as_ref()
+                    .map(|replay| replay.get_seed().to_string()))
+                .or(seed),
+            current_seed: String::new(),
+            countdown_timer: Timer::new(
+                Duration::from_secs(VERSUS_COUNTDOWN_SECONDS),
+                TimerMode::Once,
             ),
-            level: 1,
-            filled_up_lines: 0,
-            score: 0,
             last_status: None,
+            assisted: arguments.ai,
         })
-        .init_state::<GameStatus>();
-    app.run();
-}
+        .insert_state(if versus {
+            GameStatus::Countdown
+        } else if arguments.editor.is_some() {
+            GameStatus::Editor
+        } else {
+            GameStatus::Running
+        });
+
+    match lockstep {
+        Some(lockstep) => {
+            // Pausing or restarting one side only would stall the other one
+            app.add_plugins(versus::VersusPlugin)
+                .add_plugins(net::NetPlugin)
+                .add_plugins(gamepad::GamepadPlugin)
+                .insert_resource(gamepad::GamepadAssignment::new(1))
+                .insert_resource(lockstep);
+        }
+        None if arguments.replay.is_some() => {
+            app.add_plugins(replay::ReplayPlaybackPlugin)
+                .insert_resource(replay::ReplayPlayback::new(
+                    arguments.replay.clone().unwrap(),
+                ));
+            if arguments.versus {
+                app.add_plugins(versus::VersusPlugin);
+            }
+        }
+        None => {
+            // Typing the name of a high score or rebinding a key must not restart or pause the game
+            app.add_plugins(controls::ControlsPlugin)
+                .add_plugins(gamepad::GamepadPlugin)
+                .add_plugins(pointer::PointerPlugin)
+                .add_plugins(ai::AiPlugin)
+                .insert_resource(gamepad::GamepadAssignment::new(if versus { 2 } else { 1 }))
+                .add_systems(
+                    Update,
+                    (request_status_change, restart, pause).chain().run_if(
+                        not(resource_exists::<scores::NameEntry>)
+                            .and(not(resource_exists::<controls::ControlsScreen>)),
+                    ),
+                );
+            if let Some(file) = arguments.editor.clone() {
+                app.add_plugins(editor::EditorPlugin)
+                    .insert_resource(editor::Editor::new(file, arguments.fumen.as_ref()));
+            }
+            if arguments.puzzle.is_none()
+                && arguments.editor.is_none()
+                && arguments.opener.is_none()
+            {
+                app.add_plugins(save::SavePlugin)
+                    .insert_resource(save::SaveFile(arguments.get_save_file()));
+            }
+            // Starting from a diagram is not the same game as starting from an empty board
+            if arguments.puzzle.is_none()
+                && arguments.fumen.is_none()
+                && arguments.editor.is_none()
+                && arguments.opener.is_none()
+                && !arguments.versus
+                && !arguments.ai
+                && arguments.bot.is_none()
+            {
+                app.add_plugins(scores::HighScoresPlugin);
+            }
+            if arguments.versus {
+                app.add_plugins(versus::VersusPlugin);
+            }
+            // Practice games can take back the tetrominoes locked
+            if (arguments.puzzle.is_some()
+                || arguments.fumen.is_some()
+                || arguments.editor.is_some()
+                || arguments.opener.is_some())
+                && !arguments.versus
+            {
+                app.add_plugins(undo::UndoPlugin);
+            }
+        }
+    }
+
+    app.add_plugins(fumen::FumenPlugin);
+    if arguments.finesse {
+        app.add_plugins(finesse::FinessePlugin);
+    }
+    if let Some(opener) = arguments.opener.clone() {
+        app.add_plugins(opener::OpenerPlugin)
+            .insert_resource(game::opener::OpenerProgress::new(opener));
+    }
+
+    if let Some(bot) = bot {
+        app.add_plugins(bot::BotPlugin).insert_resource(bot);
+    }
+
+    if let Some(address) = &arguments.spectator_server {
+        match spectator::SpectatorServer::start(address) {
+            Ok(server) => {
+                app.add_plugins(spectator::SpectatorServerPlugin)
+                    .insert_resource(server);
+            }
+            Err(error) => {
+                eprintln!("{0}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(address) = &arguments.control {
+        match remote::RemoteControlServer::start(address) {
+            Ok(server) => {
+                app.add_plugins(remote::RemoteControlPlugin)
+                    .insert_resource(server);
+            }
+            Err(error) => {
+                eprintln!("{0}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(puzzle) = arguments.puzzle.clone() {
+        app.insert_resource(game::puzzle::PuzzleProgress::new(puzzle));
+    } else if recorded {
+        app.add_plugins(replay::ReplayRecorderPlugin)
+            .insert_resource(replay::ReplayRecorder::new(
+                arguments.get_replay_dir(),
+                replay::Replay::new(
+                    mode.clone(),
+                    arguments.level,
+                    arguments.level_progression.clone(),
+                    if versus { 2 } else { 1 },
+                    settings.gameplay.clone(),
+                ),
+            ));
+    }
 
-pub fn in_any_of_two_states<S: States>(
-    state1: S,
-    state2: S,
-) -> impl FnMut(Option<Res<State<S>>>) -> bool + Clone {
-    move |current_state: Option<Res<State<S>>>| match current_state {
-        Some(current_state) => *current_state == state1 || *current_state == state2,
-        None => false,
+    // A resumed game cannot be replayed from its seed, so it is not recorded
+    if let Some(saved_game) = saved_game {
+        app.insert_resource(saved_game);
     }
+
+    app.insert_resource(arguments);
+    app.run();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut config_store: ResMut<GizmoConfigStore>,
     mut rng: GlobalEntropy<ChaCha8Rng>,
-    mut game_board: ResMut<game::GameBoard>,
+    asset_server: Res<AssetServer>,
+    arguments: Res<cli::CommandLineArguments>,
+    mut game_session: ResMut<GameSession>,
+    lockstep: Option<Res<net::Lockstep>>,
+    saved_game: Option<Res<save::SavedGame>>,
+    mut clock: ResMut<SimulationClock>,
+    settings: Res<settings::Settings>,
 ) {
     commands.spawn(Camera2d);
 
+    let (config, _) = config_store.config_mut::<DefaultGizmoConfigGroup>();
+    config.line.width = 3.0;
+
     let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+    let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
 
-    for row in 0..(game::NUMBER_OF_ROWS + 2) {
-        for col in 0..(game::NUMBER_OF_COLUMNS + 2) {
-            if row != 0 && row != (game::NUMBER_OF_ROWS + 1) {
-                if col != 0 && col != (game::NUMBER_OF_COLUMNS + 1) {
-                    continue;
-                }
-            }
+    let players = if game_session.versus {
+        VERSUS_BOARD_OFFSETS.to_vec()
+    } else {
+        vec![0.0]
+    };
+    let players_count = players.len();
+
+    // Networked and replayed players are driven by the exchanged or the recorded inputs
+    let locally_controlled = lockstep.is_none() && arguments.replay.is_none();
+
+    // All the players share the same seed, so they get the same sequence of tetrominoes
+    game_session.current_seed = match &saved_game {
+        Some(saved_game) => saved_game.get_seed().to_string(),
+        None => game_session
+            .seed
+            .clone()
+            .unwrap_or_else(|| seed::get_random_seed_name(&mut rng)),
+    };
+    let seed = seed::get_seed(&game_session.current_seed);
 
-            commands.spawn((
-                BorderCell,
-                Mesh2d(shape.clone()),
-                MeshMaterial2d(materials.add(GRAY)),
-                get_transform_from_row_and_col(row, col),
-            ));
-        }
+    if let Some(saved_game) = &saved_game {
+        clock.tick = saved_game.get_tick();
     }
 
-    let (config, _) = config_store.config_mut::<DefaultGizmoConfigGroup>();
-    config.line.width = 3.0;
+    commands.spawn((
+        Text2d::new(get_seed_text(&game_session)),
+        TextFont {
+            font: font.clone(),
+            font_size: 18.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::TopLeft,
+        Transform::from_translation(Vec3::new(-620.00, 355.00, 0.0)),
+        SeedText,
+    ));
 
-    game_board.init(&mut rng);
+    for (id, offset) in players.into_iter().enumerate() {
+        let mut game_board = game::GameBoard::new(&arguments.get_mode());
+        if let Some(puzzle) = &arguments.puzzle {
+            game_board.set_initial_board(puzzle.get_board());
+            game_board.set_script(puzzle.get_pieces().to_vec());
+        } else if let Some(fumen) = &arguments.fumen {
+            game_board.set_initial_board(fumen.get_board());
+            game_board.set_script(fumen.get_queue());
+            game_board.set_initial_held(fumen.get_hold());
+        } else if let Some(opener) = &arguments.opener {
+            game_board.set_script(opener.get_pieces().to_vec());
+        }
 
-    let tetromino_type = game_board.get_current_tetromino_type();
-    let color = get_tetromino_color_by_type(&tetromino_type);
-    let current_cells = game_board.get_current_tetromino_cells();
+        let mut player_rng = Entropy::<ChaCha8Rng>::from_seed(seed);
+        let mut garbage_rng = Entropy::<ChaCha8Rng>::from_seed(seed::get_garbage_seed(seed));
+        game_board.init(&mut player_rng);
+
+        let mut game_settings = GameSettings::new(
+            arguments.level,
+            arguments.level_progression.clone(),
+            &settings.gameplay,
+        );
+
+        // Resumed players continue from where they were saved
+        if let Some(saved_player) = saved_game
+            .as_ref()
+            .and_then(|saved_game| saved_game.get_players().get(id))
+        {
+            game_board = saved_player.game_board.clone();
+            game_settings = saved_player.game_settings.clone();
+            player_rng = saved_player.rng.clone();
+            garbage_rng = saved_player.garbage_rng.clone();
+        }
 
-    for tetromino_cell in current_cells {
-        commands.spawn((
-            TetrominoCell,
-            Mesh2d(shape.clone()),
-            MeshMaterial2d(materials.add(*color)),
-            get_transform_by_board_cell(tetromino_cell),
+        let player = commands
+            .spawn((
+                Player { id: id as u8 },
+                Transform::from_xyz(offset, 0.0, 0.0),
+                Visibility::default(),
+            ))
+            .id();
+
+        spawn_board_border(
+            &mut commands,
+            player,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+
+        // Display the cells the board starts with
+        spawn_occupied_cells(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        // A game saved while removing the filled rows gets its tetromino once they are collapsed
+        if game_settings.board_status == BoardStatus::Playing {
+            do_spawn_tetromino(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape.clone(),
+                &settings.display,
+            );
+        }
+        spawn_upcoming_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        spawn_held_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+
+        let label = game_session.versus.then(|| format!("Player {0}", id + 1));
+        let hud = setup_text_and_scores(
+            &mut commands,
+            player,
+            font.clone(),
+            &game_settings,
+            label,
+            &settings.gameplay,
+        );
+
+        commands.entity(player).insert((
+            game_board,
+            game_settings,
+            PlayerInput::default(),
+            player_rng,
+            GarbageRng(garbage_rng),
+            hud,
         ));
+        // The bot plays the last board, the other ones are left to the keyboard
+        if arguments.bot.is_some() && id == players_count - 1 {
+            commands.entity(player).insert(BotControls);
+        } else if locally_controlled {
+            commands.entity(player).insert(LocalControls);
+        }
     }
+}
 
-    // Display upcoming tetromino
-    let upcoming_type = game_board.get_upcoming_tetromino_type();
-    let upcoming_cells = game_board.get_upcoming_tetromino_cells();
-    let upcoming_color = get_tetromino_color_by_type(&upcoming_type);
+fn spawn_board_border(
+    commands: &mut Commands,
+    player: Entity,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    shape: Handle<Mesh>,
+    display: &settings::DisplaySettings,
+) {
+    for row in 0..(game::NUMBER_OF_ROWS + 2) {
+        for col in 0..(game::NUMBER_OF_COLUMNS + 2) {
+            if row != 0
+                && row != (game::NUMBER_OF_ROWS + 1)
+                && col != 0
+                && col != (game::NUMBER_OF_COLUMNS + 1)
+            {
+                continue;
+            }
 
-    for upcoming_tetromino_cell in upcoming_cells {
-        commands.spawn((
-            UpcomingTetrominoCell,
-            Mesh2d(shape.clone()),
-            MeshMaterial2d(materials.add(*upcoming_color)),
-            get_upcoming_tetromino_position_for_cell(upcoming_tetromino_cell),
-        ));
+            commands.spawn((
+                BorderCell,
+                Mesh2d(shape.clone()),
+                MeshMaterial2d(materials.add(settings::get_color(&display.border))),
+                get_transform_from_row_and_col(row, col),
+                ChildOf(player),
+            ));
+        }
     }
 }
 
 fn setup_text_and_scores(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    game_settings: Res<GameSettings>,
-) {
-    let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
+    commands: &mut Commands,
+    player: Entity,
+    font: Handle<Font>,
+    game_settings: &GameSettings,
+    label: Option<String>,
+    gameplay: &game::scoring::GameplaySettings,
+) -> PlayerHud {
     let text_font = TextFont {
         font: font.clone(),
         font_size: 25.0,
@@ -147,23 +607,39 @@ fn setup_text_and_scores(
     const VARIABLE_TEXT_X: f32 = 300.00;
     const LINE_SIZE: f32 = 30.00;
 
+    if let Some(label) = label {
+        commands.spawn((
+            Text2d::new(label),
+            text_font.clone(),
+            TextLayout::new_with_justify(JustifyText::Left),
+            Anchor::BottomLeft,
+            Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP, 0.0)),
+            TextColor(BLUE),
+            ChildOf(player),
+        ));
+    }
+
     commands.spawn((
         Text2d::new("Scores"),
         text_font.clone(),
         TextLayout::new_with_justify(JustifyText::Left),
         Anchor::TopLeft,
         Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP, 0.0)),
+        ChildOf(player),
     ));
 
-    commands.spawn((
-        Text2d::new(game_settings.score.to_string()),
-        text_font.clone(),
-        TextLayout::new_with_justify(JustifyText::Left),
-        Anchor::TopLeft,
-        Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP, 0.0)),
-        TextColor(RED),
-        ScoreText,
-    ));
+    let score = commands
+        .spawn((
+            Text2d::new(game_settings.game_score.score.to_string()),
+            text_font.clone(),
+            TextLayout::new_with_justify(JustifyText::Left),
+            Anchor::TopLeft,
+            Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP, 0.0)),
+            TextColor(RED),
+            ScoreText,
+            ChildOf(player),
+        ))
+        .id();
 
     commands.spawn((
         Text2d::new("Level"),
@@ -171,17 +647,21 @@ fn setup_text_and_scores(
         TextLayout::new_with_justify(JustifyText::Left),
         Anchor::TopLeft,
         Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP - LINE_SIZE, 0.0)),
+        ChildOf(player),
     ));
 
-    commands.spawn((
-        Text2d::new(game_settings.level.to_string()),
-        text_font.clone(),
-        TextLayout::new_with_justify(JustifyText::Left),
-        Anchor::TopLeft,
-        Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP - LINE_SIZE, 0.0)),
-        TextColor(RED),
-        LevelText,
-    ));
+    let level = commands
+        .spawn((
+            Text2d::new(game_settings.game_score.level.to_string()),
+            text_font.clone(),
+            TextLayout::new_with_justify(JustifyText::Left),
+            Anchor::TopLeft,
+            Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP - LINE_SIZE, 0.0)),
+            TextColor(RED),
+            LevelText,
+            ChildOf(player),
+        ))
+        .id();
 
     commands.spawn((
         Text2d::new("Cleared"),
@@ -189,17 +669,25 @@ fn setup_text_and_scores(
         TextLayout::new_with_justify(JustifyText::Left),
         Anchor::TopLeft,
         Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP - LINE_SIZE * 2.00, 0.0)),
+        ChildOf(player),
     ));
 
-    commands.spawn((
-        Text2d::new(game_settings.filled_up_lines.to_string()),
-        text_font.clone(),
-        TextLayout::new_with_justify(JustifyText::Left),
-        Anchor::TopLeft,
-        Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP - LINE_SIZE * 2.00, 0.0)),
-        TextColor(RED),
-        ClearedText,
-    ));
+    let cleared = commands
+        .spawn((
+            Text2d::new(game_settings.game_score.filled_up_lines.to_string()),
+            text_font.clone(),
+            TextLayout::new_with_justify(JustifyText::Left),
+            Anchor::TopLeft,
+            Transform::from_translation(Vec3::new(
+                VARIABLE_TEXT_X,
+                TEXT_TOP - LINE_SIZE * 2.00,
+                0.0,
+            )),
+            TextColor(RED),
+            ClearedText,
+            ChildOf(player),
+        ))
+        .id();
 
     commands.spawn((
         Text2d::new("Δms"),
@@ -207,17 +695,29 @@ fn setup_text_and_scores(
         TextLayout::new_with_justify(JustifyText::Left),
         Anchor::TopLeft,
         Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP - LINE_SIZE * 3.00, 0.0)),
+        ChildOf(player),
     ));
 
-    commands.spawn((
-        Text2d::new(BASE_SPEED_MS.to_string()),
-        text_font.clone(),
-        TextLayout::new_with_justify(JustifyText::Left),
-        Anchor::TopLeft,
-        Transform::from_translation(Vec3::new(VARIABLE_TEXT_X, TEXT_TOP - LINE_SIZE * 3.00, 0.0)),
-        TextColor(RED),
-        DropDownMsText,
-    ));
+    let drop_down_ms = commands
+        .spawn((
+            Text2d::new(
+                gameplay
+                    .get_drop_down_ms(game_settings.game_score.level)
+                    .to_string(),
+            ),
+            text_font.clone(),
+            TextLayout::new_with_justify(JustifyText::Left),
+            Anchor::TopLeft,
+            Transform::from_translation(Vec3::new(
+                VARIABLE_TEXT_X,
+                TEXT_TOP - LINE_SIZE * 3.00,
+                0.0,
+            )),
+            TextColor(RED),
+            DropDownMsText,
+            ChildOf(player),
+        ))
+        .id();
 
     commands.spawn((
         Text2d::new("Next"),
@@ -225,7 +725,43 @@ fn setup_text_and_scores(
         TextLayout::new_with_justify(JustifyText::Left),
         Anchor::TopLeft,
         Transform::from_translation(Vec3::new(FIXED_TEXT_X, TEXT_TOP - LINE_SIZE * 4.00, 0.0)),
+        ChildOf(player),
     ));
+
+    PlayerHud {
+        score,
+        level,
+        cleared,
+        drop_down_ms,
+    }
+}
+
+fn update_text_and_scores(
+    hud: &PlayerHud,
+    game_settings: &GameSettings,
+    texts: &mut Query<&mut Text2d>,
+    gameplay: &game::scoring::GameplaySettings,
+) {
+    let values = [
+        (hud.score, game_settings.game_score.score.to_string()),
+        (hud.level, game_settings.game_score.level.to_string()),
+        (
+            hud.cleared,
+            game_settings.game_score.filled_up_lines.to_string(),
+        ),
+        (
+            hud.drop_down_ms,
+            gameplay
+                .get_drop_down_ms(game_settings.game_score.level)
+                .to_string(),
+        ),
+    ];
+
+    for (entity, value) in values {
+        if let Ok(mut text) = texts.get_mut(entity) {
+            text.0 = value;
+        }
+    }
 }
 
 fn get_transform_from_row_and_col(row: u8, col: u8) -> Transform {
@@ -236,212 +772,399 @@ fn get_transform_from_row_and_col(row: u8, col: u8) -> Transform {
     )
 }
 
-fn paint_board_border_outline(query: Query<&Transform, With<BorderCell>>, mut gizmos: Gizmos) {
-    for transform in query {
-        gizmos.rect_2d(
-            Isometry2d::from_xy(transform.translation.x, transform.translation.y),
-            Vec2::splat(SQUARE_SIZE),
+fn paint_cells_outline<F: QueryFilter>(
+    player_transform: &Transform,
+    children: &Children,
+    cells: &Query<&Transform, F>,
+    gizmos: &mut Gizmos,
+    color: Color,
+) {
+    for child in children.iter() {
+        if let Ok(transform) = cells.get(child) {
+            // Cells are positioned relatively to the board of their player
+            let translation = player_transform.transform_point(transform.translation);
+            gizmos.rect_2d(
+                Isometry2d::from_xy(translation.x, translation.y),
+                Vec2::splat(SQUARE_SIZE),
+                color,
+            )
+        }
+    }
+}
+
+fn paint_board_border_outline(
+    players: Query<(&Transform, &Children), With<Player>>,
+    border_cells: Query<&Transform, With<BorderCell>>,
+    mut gizmos: Gizmos,
+) {
+    for (player_transform, children) in players {
+        paint_cells_outline(
+            player_transform,
+            children,
+            &border_cells,
+            &mut gizmos,
             DARK_GRAY,
-        )
+        );
     }
 }
 
 fn paint_tetromino_outline(
-    query: Query<&mut Transform, With<TetrominoCell>>,
-    game_board: Res<game::GameBoard>,
+    players: Query<(&Transform, &game::GameBoard, &Children)>,
+    tetromino_cells: Query<&Transform, With<TetrominoCell>>,
     mut gizmos: Gizmos,
+    settings: Res<settings::Settings>,
 ) {
-    let tetromino_type = game_board.get_current_tetromino_type();
-    let color = get_tetromino_outline_color_by_type(&tetromino_type);
-
-    for transform in query {
-        gizmos.rect_2d(
-            Isometry2d::from_xy(transform.translation.x, transform.translation.y),
-            Vec2::splat(SQUARE_SIZE),
-            *color,
-        )
+    for (player_transform, game_board, children) in players {
+        let tetromino_type = game_board.get_current_tetromino_type();
+        let color = get_tetromino_outline_color_by_type(tetromino_type, &settings.display);
+        paint_cells_outline(
+            player_transform,
+            children,
+            &tetromino_cells,
+            &mut gizmos,
+            color,
+        );
     }
 }
 
 fn paint_upcoming_tetromino_outline(
-    query: Query<&mut Transform, With<UpcomingTetrominoCell>>,
-    game_board: Res<game::GameBoard>,
+    players: Query<(&Transform, &game::GameBoard, &Children)>,
+    upcoming_cells: Query<&Transform, With<UpcomingTetrominoCell>>,
     mut gizmos: Gizmos,
+    settings: Res<settings::Settings>,
 ) {
-    let tetromino_type = game_board.get_upcoming_tetromino_type();
-    let color = get_tetromino_outline_color_by_type(&tetromino_type);
-
-    for transform in query {
-        gizmos.rect_2d(
-            Isometry2d::from_xy(transform.translation.x, transform.translation.y),
-            Vec2::splat(SQUARE_SIZE),
-            *color,
-        )
+    for (player_transform, game_board, children) in players {
+        let tetromino_type = game_board.get_upcoming_tetromino_type();
+        let color = get_tetromino_outline_color_by_type(tetromino_type, &settings.display);
+        paint_cells_outline(
+            player_transform,
+            children,
+            &upcoming_cells,
+            &mut gizmos,
+            color,
+        );
     }
 }
 
-fn paint_occupied_cells_outline(query: Query<&Transform, With<OccupiedCell>>, mut gizmos: Gizmos) {
-    for transform in query {
-        gizmos.rect_2d(
-            Isometry2d::from_xy(transform.translation.x, transform.translation.y),
-            Vec2::splat(SQUARE_SIZE),
+fn paint_held_tetromino_outline(
+    players: Query<(&Transform, &game::GameBoard, &Children)>,
+    held_cells: Query<&Transform, With<HeldTetrominoCell>>,
+    mut gizmos: Gizmos,
+    settings: Res<settings::Settings>,
+) {
+    for (player_transform, game_board, children) in players {
+        let Some(tetromino_type) = game_board.get_held_tetromino_type() else {
+            continue;
+        };
+        let color = get_tetromino_outline_color_by_type(tetromino_type, &settings.display);
+        paint_cells_outline(player_transform, children, &held_cells, &mut gizmos, color);
+    }
+}
+
+fn paint_occupied_cells_outline(
+    players: Query<(&Transform, &Children), With<Player>>,
+    occupied_cells: Query<&Transform, With<OccupiedCell>>,
+    mut gizmos: Gizmos,
+) {
+    for (player_transform, children) in players {
+        paint_cells_outline(
+            player_transform,
+            children,
+            &occupied_cells,
+            &mut gizmos,
             GRAY,
-        )
+        );
     }
 }
 
-fn move_and_rotate_tetromino(
+fn read_keyboard_input(
     keys: Res<ButtonInput<KeyCode>>,
-    mut game_board: ResMut<game::GameBoard>,
-    mut query: Query<(Entity, &mut Transform), With<TetrominoCell>>,
+    settings: Res<settings::Settings>,
+    game_session: Res<GameSession>,
+    mut players: Query<(&Player, &mut PlayerInput), With<LocalControls>>,
 ) {
-    let moved;
-
-    if keys.just_released(KeyCode::ArrowRight) {
-        moved = game_board.move_tetromino(game::tetromino::MoveDirection::Right);
-    } else if keys.just_released(KeyCode::ArrowLeft) {
-        moved = game_board.move_tetromino(game::tetromino::MoveDirection::Left);
-    } else if keys.just_released(KeyCode::ArrowUp) {
-        moved = game_board.rotate_tetromino();
-    } else {
-        moved = game::tetromino::MoveStatus::NotMoved;
+    for (player, mut input) in &mut players {
+        let bindings = settings
+            .controls
+            .get_bindings(game_session.versus, player.id);
+        input.merge(&bindings.get_player_input(&keys));
     }
+}
 
-    if let game::tetromino::MoveStatus::Moved = moved {
-        update_tetromino_position_of_cells(&game_board, &mut query);
+fn clear_player_input(mut players: Query<&mut PlayerInput>) {
+    for mut input in &mut players {
+        *input = PlayerInput::default();
     }
 }
 
-fn drop_tetromino_down(
+fn advance_simulation_clock(mut clock: ResMut<SimulationClock>) {
+    clock.tick += 1;
+}
+
+fn move_and_rotate_tetromino(
+    mut players: Query<(&mut game::GameBoard, &GameSettings, &PlayerInput, &Children)>,
+    mut tetromino_cells: Query<&mut Transform, With<TetrominoCell>>,
+) {
+    for (mut game_board, game_settings, input, children) in &mut players {
+        if game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        let Some(step) = input.get_move() else {
+            continue;
+        };
+
+        if let game::tetromino::MoveStatus::Moved = game_board.apply_step(step) {
+            update_tetromino_position_of_cells(&game_board, children, &mut tetromino_cells);
+        }
+    }
+}
+
+// Swaps the current tetromino with the held one, spawning again the cells of both
+#[allow(clippy::type_complexity)]
+fn hold_tetromino(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform), With<TetrominoCell>>,
-    upcoming: Query<Entity, (With<UpcomingTetrominoCell>, Without<TetrominoCell>)>,
-    mut score_text: Single<&mut Text2d, With<ScoreText>>,
-    mut level_text: Single<&mut Text2d, (With<LevelText>, Without<ScoreText>)>,
-    mut cleared_text: Single<
-        &mut Text2d,
-        (With<ClearedText>, Without<LevelText>, Without<ScoreText>),
-    >,
-    mut drop_down_ms_text: Single<
-        &mut Text2d,
-        (
-            With<DropDownMsText>,
-            Without<LevelText>,
-            Without<ScoreText>,
-            Without<ClearedText>,
-        ),
+    mut players: Query<(
+        Entity,
+        &mut game::GameBoard,
+        &GameSettings,
+        &mut Entropy<ChaCha8Rng>,
+        &PlayerInput,
+        &Children,
+    )>,
+    cells: Query<
+        Entity,
+        Or<(
+            With<TetrominoCell>,
+            With<UpcomingTetrominoCell>,
+            With<HeldTetrominoCell>,
+        )>,
     >,
-    mut game_board: ResMut<game::GameBoard>,
-    time: Res<Time>,
-    mut game_settings: ResMut<GameSettings>,
-    mut rng: GlobalEntropy<ChaCha8Rng>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut next_state: ResMut<NextState<GameStatus>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<settings::Settings>,
+) {
+    for (player, mut game_board, game_settings, mut rng, input, children) in &mut players {
+        if !input.hold || game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        if game_board.hold_tetromino(&mut *rng) == game::tetromino::MoveStatus::NotMoved {
+            continue;
+        }
+
+        for child in children.iter() {
+            if cells.contains(child) {
+                commands.entity(child).despawn();
+            }
+        }
+
+        let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+        do_spawn_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        spawn_upcoming_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        spawn_held_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape,
+            &settings.display,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn drop_tetromino_down(
+    mut commands: Commands,
+    mut players: Query<(
+        Entity,
+        &mut game::GameBoard,
+        &mut GameSettings,
+        &mut Entropy<ChaCha8Rng>,
+        &mut GarbageRng,
+        &PlayerInput,
+        &PlayerHud,
+        &Children,
+        Option<&mut undo::UndoHistory>,
+    )>,
+    mut tetromino_cells: Query<&mut Transform, With<TetrominoCell>>,
+    upcoming_cells: Query<Entity, With<UpcomingTetrominoCell>>,
+    occupied_cells: Query<Entity, With<OccupiedCell>>,
+    mut texts: Query<&mut Text2d>,
+    clock: Res<SimulationClock>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut locked_events: EventWriter<TetrominoLocked>,
+    settings: Res<settings::Settings>,
 ) {
-    // tick the timer
-    game_settings.descend_timer.tick(time.delta());
+    for (
+        player,
+        mut game_board,
+        mut game_settings,
+        mut rng,
+        mut garbage_rng,
+        input,
+        hud,
+        children,
+        mut history,
+    ) in &mut players
+    {
+        if game_settings.board_status == BoardStatus::ToppedOut {
+            continue;
+        }
 
-    let down_key_pressed = keys.pressed(KeyCode::ArrowDown);
-    let timer_just_finished = game_settings.descend_timer.just_finished();
+        // Received garbage waits even while the filled rows are being removed
+        game_board.tick_garbage(clock.step);
 
-    if timer_just_finished || down_key_pressed {
-        let dropped = game_board.drop_down();
+        if game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        // tick the timer
+        game_settings.descend_timer.tick(clock.step);
+
+        let down_key_pressed = input.down;
+        let timer_just_finished = game_settings.descend_timer.just_finished();
+
+        if !timer_just_finished && !down_key_pressed && !input.hard_drop {
+            continue;
+        }
+
+        // A hard drop takes the tetromino straight to the bottom and locks it
+        let mut dropped = game_board.drop_down();
+        while input.hard_drop && matches!(dropped, game::tetromino::DroppedStatus::Dropped) {
+            dropped = game_board.drop_down();
+        }
 
         match dropped {
             game::tetromino::DroppedStatus::Dropped => {
-                update_tetromino_position_of_cells(&game_board, &mut query);
+                update_tetromino_position_of_cells(&game_board, children, &mut tetromino_cells);
             }
             game::tetromino::DroppedStatus::NotDropped(cells) => {
-                game_settings.score += POINTS_FOR_TETROMINO_DROPPED;
+                let tetromino = game_board.get_current_tetromino_type().clone();
+                let locked_cells = cells.clone();
+
+                // Clearing lines attacks the opponents, the garbage they sent enters the board otherwise
+                let locked = game_board.lock_tetromino(
+                    &mut *rng,
+                    &mut garbage_rng.0,
+                    &mut game_settings.game_score,
+                    &settings.gameplay,
+                );
 
                 // Despawn the current tetromino
-                for (entity, _) in query {
-                    commands.entity(entity).despawn();
+                for child in children.iter() {
+                    if tetromino_cells.contains(child) {
+                        commands.entity(child).despawn();
+                    }
                 }
 
-                // Spawn in its place the filled cells blocks
                 let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
-                for cell in cells {
-                    commands.spawn((
-                        OccupiedCell,
-                        Mesh2d(shape.clone()),
-                        MeshMaterial2d(materials.add(DARK_GRAY)),
-                        get_transform_by_board_cell(cell),
-                    ));
-                }
-
-                // Is game-over?
-                let can_spawn_more_tetromino = game_board.next_tetromino(&mut rng);
-                match can_spawn_more_tetromino {
-                    game::tetromino::CanSpawnMoreTetromino::Yes => {
-                        // Display upcoming tetromino
-                        let upcoming_type = game_board.get_upcoming_tetromino_type();
-                        let upcoming_cells = game_board.get_upcoming_tetromino_cells();
-                        let upcoming_color = get_tetromino_color_by_type(&upcoming_type);
-
-                        for entity in upcoming {
-                            commands.entity(entity).despawn();
-                        }
 
-                        for upcoming_tetromino_cell in upcoming_cells {
-                            commands.spawn((
-                                UpcomingTetrominoCell,
-                                Mesh2d(shape.clone()),
-                                MeshMaterial2d(materials.add(*upcoming_color)),
-                                get_upcoming_tetromino_position_for_cell(upcoming_tetromino_cell),
-                            ));
+                if locked.garbage_added {
+                    // The whole board has been pushed up, so spawn again all the occupied cells
+                    for child in children.iter() {
+                        if occupied_cells.contains(child) {
+                            commands.entity(child).despawn();
                         }
+                    }
 
-                        // If not-dropped we need to check if any line has been filled up so they can be exploded
-                        let number_of_filled_rows = game_board.get_number_of_filled_rows();
-                        if number_of_filled_rows > 0 {
-                            game_settings.last_despawned_cell = None;
-                            game_settings.remove_filled_cells_times.reset();
-
-                            game_settings.score +=
-                                number_of_filled_rows as u32 * POINTS_FOR_CLEARED_ROW;
-                            game_settings.filled_up_lines += number_of_filled_rows as u32;
-                            game_settings.level = std::cmp::min(
-                                MAX_LEVEL,
-                                game_settings.filled_up_lines as u16 / CLEARED_UP_LINES_PER_LEVEL
-                                    + 1,
-                            );
-
-                            next_state.set(GameStatus::RemovingFilledRows);
-                        } else {
-                            do_spawn_tetromino(
-                                &mut commands,
-                                &mut game_board,
-                                materials,
-                                shape.clone(),
-                            );
-                        }
+                    spawn_occupied_cells(
+                        &mut commands,
+                        player,
+                        &game_board,
+                        &mut materials,
+                        shape.clone(),
+                        &settings.display,
+                    );
+                } else {
+                    // Spawn in its place the filled cells blocks
+                    for cell in cells {
+                        commands.spawn((
+                            OccupiedCell,
+                            Mesh2d(shape.clone()),
+                            MeshMaterial2d(
+                                materials.add(settings::get_color(&settings.display.occupied)),
+                            ),
+                            get_transform_by_board_cell(cell),
+                            ChildOf(player),
+                        ));
                     }
-                    game::tetromino::CanSpawnMoreTetromino::No => {
-                        next_state.set(GameStatus::GameOver);
-                        for entity in upcoming {
-                            commands.entity(entity).despawn();
-                        }
+                }
+
+                // Display upcoming tetromino
+                for child in children.iter() {
+                    if upcoming_cells.contains(child) {
+                        commands.entity(child).despawn();
                     }
                 }
 
-                // Update the timer
-                let mut expected_speed_delta = (game_settings.level - 1) as u64 * LEVEL_SPEED_DELTA;
-                if expected_speed_delta > BASE_SPEED_MS - MIN_SPEED_MS {
-                    expected_speed_delta = BASE_SPEED_MS - MIN_SPEED_MS;
+                // Is game-over?
+                if locked.outcome.topped_out {
+                    game_settings.board_status = BoardStatus::ToppedOut;
+                } else {
+                    spawn_upcoming_tetromino(
+                        &mut commands,
+                        player,
+                        &game_board,
+                        &mut materials,
+                        shape.clone(),
+                        &settings.display,
+                    );
+
+                    // If not-dropped we need to check if any line has been filled up so they can be exploded
+                    if locked.outcome.cleared_lines > 0 {
+                        game_settings.last_despawned_cell = None;
+                        game_settings.remove_filled_cells_times.reset();
+                        game_settings.board_status = BoardStatus::RemovingFilledRows;
+                    } else {
+                        do_spawn_tetromino(
+                            &mut commands,
+                            player,
+                            &game_board,
+                            &mut materials,
+                            shape.clone(),
+                            &settings.display,
+                        );
+                    }
                 }
 
-                let drop_down_ms = BASE_SPEED_MS - expected_speed_delta;
+                // Update the timer
+                let drop_down_ms = settings
+                    .gameplay
+                    .get_drop_down_ms(game_settings.game_score.level);
                 game_settings.descend_timer =
                     Timer::new(Duration::from_millis(drop_down_ms), TimerMode::Repeating);
 
                 // Update the text messages
-                score_text.0 = game_settings.score.to_string();
-                cleared_text.0 = game_settings.filled_up_lines.to_string();
-                level_text.0 = game_settings.level.to_string();
-                drop_down_ms_text.0 = drop_down_ms.to_string();
+                update_text_and_scores(hud, &game_settings, &mut texts, &settings.gameplay);
+
+                // Practice games keep every position reached, to take the tetrominoes back
+                if let Some(history) = &mut history {
+                    history.record(&game_board, &game_settings, &rng);
+                }
+
+                locked_events.write(TetrominoLocked {
+                    player,
+                    outcome: locked.outcome,
+                    attack_lines: locked.attack_lines,
+                    tetromino,
+                    cells: locked_cells,
+                });
             }
         }
 
@@ -453,48 +1176,57 @@ fn drop_tetromino_down(
 
 fn despawn_filled_up_rows(
     mut commands: Commands,
-    query: Query<(Entity, &mut Transform), With<OccupiedCell>>,
-    mut game_board: ResMut<game::GameBoard>,
-    mut next_state: ResMut<NextState<GameStatus>>,
-    mut game_settings: ResMut<GameSettings>,
+    mut players: Query<(Entity, &mut game::GameBoard, &mut GameSettings, &Children)>,
+    occupied_cells: Query<&Transform, With<OccupiedCell>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    time: Res<Time>,
+    clock: Res<SimulationClock>,
+    settings: Res<settings::Settings>,
 ) {
-    // tick the timer
-    game_settings.remove_filled_cells_times.tick(time.delta());
+    for (player, mut game_board, mut game_settings, children) in &mut players {
+        if game_settings.board_status != BoardStatus::RemovingFilledRows {
+            continue;
+        }
+
+        // tick the timer
+        game_settings.remove_filled_cells_times.tick(clock.step);
+
+        if !game_settings.remove_filled_cells_times.just_finished() {
+            continue;
+        }
 
-    if game_settings.remove_filled_cells_times.just_finished() {
         match game_board.get_next_cell_from_filled_row_after(game_settings.last_despawned_cell) {
             None => {
                 // Collapse filled up cells
                 game_board.collapse_filled_rows();
 
                 // Despawn all the remaining filled cells
-                for (entity, _) in query {
-                    commands.entity(entity).despawn();
+                for child in children.iter() {
+                    if occupied_cells.contains(child) {
+                        commands.entity(child).despawn();
+                    }
                 }
 
                 // Spawn again all the occupied cells
                 let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
-                for row in 0..game::NUMBER_OF_ROWS {
-                    for col in 0..game::NUMBER_OF_COLUMNS {
-                        let cell =
-                            game::tetromino::Tetromino::get_cell_from_row_and_column(row, col);
-                        if game_board.is_cell_occupied(cell) {
-                            commands.spawn((
-                                OccupiedCell,
-                                Mesh2d(shape.clone()),
-                                MeshMaterial2d(materials.add(DARK_GRAY)),
-                                get_transform_by_board_cell(cell),
-                            ));
-                        }
-                    }
-                }
+                spawn_occupied_cells(
+                    &mut commands,
+                    player,
+                    &game_board,
+                    &mut materials,
+                    shape.clone(),
+                    &settings.display,
+                );
 
                 // Spawn tetromino
-                let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
-                do_spawn_tetromino(&mut commands, &mut game_board, materials, shape);
+                do_spawn_tetromino(
+                    &mut commands,
+                    player,
+                    &game_board,
+                    &mut materials,
+                    shape,
+                    &settings.display,
+                );
 
                 // Reset the last cell to despawn
                 game_settings.last_despawned_cell = None;
@@ -503,14 +1235,16 @@ fn despawn_filled_up_rows(
                 game_settings.descend_timer.reset();
 
                 // Change status
-                next_state.set(GameStatus::Running);
+                game_settings.board_status = BoardStatus::Playing;
             }
             Some(cell_to_despawn) => {
                 let transformation_of_the_cell_to_despawn =
                     get_transform_by_board_cell(cell_to_despawn);
-                for (entity, transformation) in query {
-                    if transformation.eq(&transformation_of_the_cell_to_despawn) {
-                        commands.entity(entity).despawn();
+                for child in children.iter() {
+                    if let Ok(transformation) = occupied_cells.get(child)
+                        && transformation.eq(&transformation_of_the_cell_to_despawn)
+                    {
+                        commands.entity(child).despawn();
                         break;
                     }
                 }
@@ -523,64 +1257,144 @@ fn despawn_filled_up_rows(
     }
 }
 
+fn check_game_over(players: Query<&GameSettings>, mut next_state: ResMut<NextState<GameStatus>>) {
+    for game_settings in players {
+        if game_settings.board_status == BoardStatus::ToppedOut {
+            next_state.set(GameStatus::GameOver);
+        }
+    }
+}
+
+fn spawn_occupied_cells(
+    commands: &mut Commands,
+    player: Entity,
+    game_board: &game::GameBoard,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    shape: Handle<Mesh>,
+    display: &settings::DisplaySettings,
+) {
+    for row in 0..game::NUMBER_OF_ROWS {
+        for col in 0..game::NUMBER_OF_COLUMNS {
+            let cell = game::tetromino::Tetromino::get_cell_from_row_and_column(row, col);
+            if game_board.is_cell_occupied(cell) {
+                // Cells painted or imported with the color of a tetromino keep it
+                let color = match game::get_cell_tetromino(game_board.get_cell(cell)) {
+                    Some(tetromino) => get_tetromino_color_by_type(&tetromino, display),
+                    None => settings::get_color(&display.occupied),
+                };
+                commands.spawn((
+                    OccupiedCell,
+                    Mesh2d(shape.clone()),
+                    MeshMaterial2d(materials.add(color)),
+                    get_transform_by_board_cell(cell),
+                    ChildOf(player),
+                ));
+            }
+        }
+    }
+}
+
 fn do_spawn_tetromino(
     commands: &mut Commands,
-    game_board: &mut ResMut<game::GameBoard>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    player: Entity,
+    game_board: &game::GameBoard,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
     shape: Handle<Mesh>,
+    display: &settings::DisplaySettings,
 ) {
     // Span the new tetromino
     let tetromino_type = game_board.get_current_tetromino_type();
-    let color = get_tetromino_color_by_type(&tetromino_type);
+    let color = get_tetromino_color_by_type(tetromino_type, display);
     let current_cells = game_board.get_current_tetromino_cells();
 
     for tetromino_cell in current_cells {
         commands.spawn((
             TetrominoCell,
             Mesh2d(shape.clone()),
-            MeshMaterial2d(materials.add(*color)),
+            MeshMaterial2d(materials.add(color)),
             get_transform_by_board_cell(tetromino_cell),
+            ChildOf(player),
         ));
     }
 }
 
-fn update_tetromino_position_of_cells(
-    game_board: &ResMut<game::GameBoard>,
-    query: &mut Query<(Entity, &mut Transform), With<TetrominoCell>>,
+fn spawn_upcoming_tetromino(
+    commands: &mut Commands,
+    player: Entity,
+    game_board: &game::GameBoard,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    shape: Handle<Mesh>,
+    display: &settings::DisplaySettings,
 ) {
-    let cells = game_board.get_current_tetromino_cells();
-    for (index, (_, ref mut transform)) in query.iter_mut().enumerate() {
-        let updated_transformation = get_transform_by_board_cell(cells[index]);
+    let upcoming_type = game_board.get_upcoming_tetromino_type();
+    let upcoming_cells = game_board.get_upcoming_tetromino_cells();
+    let upcoming_color = get_tetromino_color_by_type(upcoming_type, display);
 
-        transform.translation.x = updated_transformation.translation.x;
-        transform.translation.y = updated_transformation.translation.y;
+    for upcoming_tetromino_cell in upcoming_cells {
+        commands.spawn((
+            UpcomingTetrominoCell,
+            Mesh2d(shape.clone()),
+            MeshMaterial2d(materials.add(upcoming_color)),
+            get_upcoming_tetromino_position_for_cell(upcoming_tetromino_cell),
+            ChildOf(player),
+        ));
+    }
+}
+
+fn spawn_held_tetromino(
+    commands: &mut Commands,
+    player: Entity,
+    game_board: &game::GameBoard,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    shape: Handle<Mesh>,
+    display: &settings::DisplaySettings,
+) {
+    let Some(held_type) = game_board.get_held_tetromino_type() else {
+        return;
+    };
+    let held_color = get_tetromino_color_by_type(held_type, display);
+
+    for held_tetromino_cell in game_board.get_held_tetromino_cells() {
+        commands.spawn((
+            HeldTetrominoCell,
+            Mesh2d(shape.clone()),
+            MeshMaterial2d(materials.add(held_color)),
+            get_held_tetromino_position_for_cell(held_tetromino_cell, game_board.get_scale()),
+            ChildOf(player),
+        ));
     }
 }
 
-fn get_tetromino_color_by_type(tetromino_type: &game::tetromino::TetrominoType) -> &'static Color {
-    match tetromino_type {
-        game::tetromino::TetrominoType::I => &PINK,
-        game::tetromino::TetrominoType::O => &GREEN,
-        game::tetromino::TetrominoType::T => &YELLOW,
-        game::tetromino::TetrominoType::J => &BLUE,
-        game::tetromino::TetrominoType::L => &VIOLET,
-        game::tetromino::TetrominoType::S => &ORANGE,
-        game::tetromino::TetrominoType::Z => &RED,
+fn update_tetromino_position_of_cells(
+    game_board: &game::GameBoard,
+    children: &Children,
+    tetromino_cells: &mut Query<&mut Transform, With<TetrominoCell>>,
+) {
+    let cells = game_board.get_current_tetromino_cells();
+    let mut index = 0;
+    for child in children.iter() {
+        if let Ok(mut transform) = tetromino_cells.get_mut(child) {
+            let updated_transformation = get_transform_by_board_cell(cells[index]);
+
+            transform.translation.x = updated_transformation.translation.x;
+            transform.translation.y = updated_transformation.translation.y;
+            index += 1;
+        }
     }
 }
 
+fn get_tetromino_color_by_type(
+    tetromino_type: &game::tetromino::TetrominoType,
+    display: &settings::DisplaySettings,
+) -> Color {
+    settings::get_color(&display.get_tetromino_colors(tetromino_type).fill)
+}
+
 fn get_tetromino_outline_color_by_type(
     tetromino_type: &game::tetromino::TetrominoType,
-) -> &'static Color {
-    match tetromino_type {
-        game::tetromino::TetrominoType::I => &RED,
-        game::tetromino::TetrominoType::O => &DARK_GREEN,
-        game::tetromino::TetrominoType::T => &ORANGE,
-        game::tetromino::TetrominoType::J => &DARK_BLUE,
-        game::tetromino::TetrominoType::L => &BLUE,
-        game::tetromino::TetrominoType::S => &YELLOW,
-        game::tetromino::TetrominoType::Z => &PINK,
-    }
+    display: &settings::DisplaySettings,
+) -> Color {
+    settings::get_color(&display.get_tetromino_colors(tetromino_type).outline)
 }
 
 fn get_transform_by_board_cell(cell: u8) -> Transform {
@@ -592,6 +1406,20 @@ fn get_transform_by_board_cell(cell: u8) -> Transform {
     )
 }
 
+// Gets the board cell under a position relative to the board, if any
+fn get_board_cell_by_position(position: Vec2) -> Option<u8> {
+    let col = (position.x / SQUARE_SIZE + 5.0).floor();
+    let row = (10.0 - position.y / SQUARE_SIZE).floor();
+    if !(0.0..game::NUMBER_OF_COLUMNS as f32).contains(&col)
+        || !(0.0..game::NUMBER_OF_ROWS as f32).contains(&row)
+    {
+        return None;
+    }
+    Some(game::tetromino::Tetromino::get_cell_from_row_and_column(
+        row as u8, col as u8,
+    ))
+}
+
 fn get_upcoming_tetromino_position_for_cell(cell: u8) -> Transform {
     let (row, col) = game::tetromino::Tetromino::get_row_and_column_by_cell(cell);
     Transform::from_xyz(
@@ -602,19 +1430,69 @@ fn get_upcoming_tetromino_position_for_cell(cell: u8) -> Transform {
     )
 }
 
+// The held tetromino is displayed below the upcoming one, leaving room for the tallest tetromino
+fn get_held_tetromino_position_for_cell(cell: u8, scale: u8) -> Transform {
+    let mut transform = get_upcoming_tetromino_position_for_cell(cell);
+    transform.translation.y -= (4 * scale + 1) as f32 * SQUARE_SIZE;
+    transform
+}
+
+// Pausing and restarting with the keys and the gamepad buttons, from any player
+fn request_status_change(
+    state: Res<State<GameStatus>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    settings: Res<settings::Settings>,
+    game_session: Res<GameSession>,
+    mut status_requests: EventWriter<StatusRequest>,
+) {
+    let just_pressed = |action| {
+        settings
+            .controls
+            .just_pressed(&keys, game_session.versus, action)
+            || gamepad::any_just_pressed(&gamepads, &settings.controls, action)
+    };
+
+    // The editor starts the game itself, once the position is ready
+    if *state.get() == GameStatus::Editor {
+        return;
+    }
+
+    if just_pressed(controls::InputAction::Restart) {
+        status_requests.write(StatusRequest::Restart);
+    }
+    if just_pressed(controls::InputAction::Pause) {
+        status_requests.write(match state.get() {
+            GameStatus::Pause => StatusRequest::Resume,
+            _ => StatusRequest::Pause,
+        });
+    }
+}
+
 fn pause(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     state: Res<State<GameStatus>>,
     mut next_state: ResMut<NextState<GameStatus>>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut game_settings: ResMut<GameSettings>,
+    mut game_session: ResMut<GameSession>,
     paused_text: Query<Entity, With<PausedText>>,
+    mut status_requests: EventReader<StatusRequest>,
 ) {
-    if keys.just_pressed(KeyCode::Space) {
+    let paused = *state.get() == GameStatus::Pause;
+    let requested = status_requests
+        .read()
+        .filter(|request| match request {
+            StatusRequest::Pause => !paused,
+            StatusRequest::Resume => paused,
+            StatusRequest::Restart => false,
+        })
+        .count()
+        > 0;
+
+    if requested {
         match state.get() {
             GameStatus::Pause => {
-                if let Some(previous_state) = &game_settings.last_status {
+                if let Some(previous_state) = &game_session.last_status {
                     next_state.set(previous_state.clone());
                 }
 
@@ -623,7 +1501,7 @@ fn pause(
                 }
             }
             _ => {
-                game_settings.last_status = Some(state.get().clone());
+                game_session.last_status = Some(state.get().clone());
                 next_state.set(GameStatus::Pause);
 
                 let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
@@ -646,88 +1524,301 @@ fn pause(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn restart(
     mut commands: Commands,
-    mut game_settings: ResMut<GameSettings>,
+    mut game_session: ResMut<GameSession>,
     mut next_state: ResMut<NextState<GameStatus>>,
-    keys: Res<ButtonInput<KeyCode>>,
     mut rng: GlobalEntropy<ChaCha8Rng>,
-    mut game_board: ResMut<game::GameBoard>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    tetromino_cells: Query<Entity, With<TetrominoCell>>,
-    occupied_cells: Query<Entity, (With<OccupiedCell>, Without<TetrominoCell>)>,
-    paused_text: Query<
-        Entity,
+    mut players: Query<
         (
-            With<PausedText>,
-            Without<OccupiedCell>,
-            Without<TetrominoCell>,
+            Entity,
+            &mut game::GameBoard,
+            &mut GameSettings,
+            &mut Entropy<ChaCha8Rng>,
+            &mut GarbageRng,
+            &mut PlayerInput,
+            &PlayerHud,
         ),
+        Without<Global>,
     >,
-    upcoming_tetromino: Query<
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    cells: Query<
         Entity,
-        (
+        Or<(
+            With<TetrominoCell>,
+            With<OccupiedCell>,
             With<UpcomingTetrominoCell>,
-            Without<PausedText>,
-            Without<OccupiedCell>,
-            Without<TetrominoCell>,
-        ),
+            With<HeldTetrominoCell>,
+        )>,
+    >,
+    overlay_texts: Query<
+        Entity,
+        Or<(
+            With<PausedText>,
+            With<PuzzleStatusText>,
+            With<CountdownText>,
+            With<WinnerText>,
+            With<GameOverText>,
+        )>,
     >,
+    mut texts: Query<&mut Text2d>,
+    puzzle_progress: Option<ResMut<game::puzzle::PuzzleProgress>>,
+    mut clock: ResMut<SimulationClock>,
+    recorder: Option<ResMut<replay::ReplayRecorder>>,
+    settings: Res<settings::Settings>,
+    mut status_requests: EventReader<StatusRequest>,
 ) {
-    if keys.just_pressed(KeyCode::KeyN) {
-        // Status back to Running
-        next_state.set(GameStatus::Running);
-
-        // Reset game settings
-        game_settings.last_status = None;
-        game_settings.descend_timer =
-            Timer::new(Duration::from_millis(BASE_SPEED_MS), TimerMode::Repeating);
-        game_settings.level = 1;
-        game_settings.score = 0;
-        game_settings.last_despawned_cell = None;
-        game_settings.filled_up_lines = 0;
-        game_settings.remove_filled_cells_times.reset();
+    let requested = status_requests
+        .read()
+        .filter(|request| **request == StatusRequest::Restart)
+        .count()
+        > 0;
+
+    if requested {
+        // Status back to Running, after a countdown when playing versus
+        if game_session.versus {
+            next_state.set(GameStatus::Countdown);
+        } else {
+            next_state.set(GameStatus::Running);
+        }
+
+        game_session.last_status = None;
 
-        // Despawn paused text
-        for entity in paused_text {
+        // Despawn paused, puzzle, countdown, winner and game over texts
+        for entity in overlay_texts {
             commands.entity(entity).despawn();
         }
 
-        // Reset the board
-        game_board.reset(&mut rng);
-
-        // Despawn filled up cells
-        for entity in occupied_cells {
+        // Despawn tetrominoes and filled up cells
+        for entity in cells {
             commands.entity(entity).despawn();
         }
 
-        // Spawn upcoming
-        let upcoming_type = game_board.get_upcoming_tetromino_type();
-        let upcoming_cells = game_board.get_upcoming_tetromino_cells();
-        let upcoming_color = get_tetromino_color_by_type(&upcoming_type);
-        let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+        // Reset the puzzle
+        if let Some(mut puzzle_progress) = puzzle_progress {
+            puzzle_progress.reset();
+        }
 
-        for upcoming_tetromino_cell in upcoming_cells {
-            commands.spawn((
-                UpcomingTetrominoCell,
-                Mesh2d(shape.clone()),
-                MeshMaterial2d(materials.add(*upcoming_color)),
-                get_upcoming_tetromino_position_for_cell(upcoming_tetromino_cell),
-            ));
+        // A seed asked for is played again, otherwise a new one is drawn
+        game_session.current_seed = game_session
+            .seed
+            .clone()
+            .unwrap_or_else(|| seed::get_random_seed_name(&mut rng));
+        let seed = seed::get_seed(&game_session.current_seed);
+
+        // Save the replay of the game left unfinished and record the new one
+        if let Some(mut recorder) = recorder {
+            recorder.save(clock.tick);
+            recorder.start(game_session.current_seed.clone(), &settings.gameplay);
         }
+        clock.tick = 0;
 
-        // Despawn tetromino
-        for entity in tetromino_cells {
-            commands.entity(entity).despawn();
+        let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+
+        for (
+            player,
+            mut game_board,
+            mut game_settings,
+            mut player_rng,
+            mut garbage_rng,
+            mut input,
+            hud,
+        ) in &mut players
+        {
+            *input = PlayerInput::default();
+
+            // Reset game settings
+            *game_settings = GameSettings::new(
+                game_settings.game_score.start_level,
+                game_settings.game_score.level_progression.clone(),
+                &settings.gameplay,
+            );
+            update_text_and_scores(hud, &game_settings, &mut texts, &settings.gameplay);
+
+            // Reset the board
+            *player_rng = Entropy::<ChaCha8Rng>::from_seed(seed);
+            garbage_rng.0 = Entropy::<ChaCha8Rng>::from_seed(seed::get_garbage_seed(seed));
+            game_board.reset(&mut *player_rng);
+
+            // Spawn the cells the board starts with, the upcoming and the current tetromino
+            spawn_occupied_cells(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape.clone(),
+                &settings.display,
+            );
+            spawn_upcoming_tetromino(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape.clone(),
+                &settings.display,
+            );
+            do_spawn_tetromino(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape.clone(),
+                &settings.display,
+            );
         }
+    }
+}
 
-        // Spawn tetromino
-        do_spawn_tetromino(&mut commands, &mut game_board, materials, shape);
+fn get_seed_text(game_session: &GameSession) -> String {
+    format!("Seed: {0}", game_session.current_seed)
+}
 
-        // Despawn upcoming tetromino
-        for entity in upcoming_tetromino {
-            commands.entity(entity).despawn();
+fn update_seed_text(
+    game_session: Res<GameSession>,
+    mut seed_text: Single<&mut Text2d, With<SeedText>>,
+) {
+    let text = get_seed_text(&game_session);
+    if seed_text.0 != text {
+        seed_text.0 = text;
+    }
+}
+
+// Shows the seed once the game is over, so the same game can be played again
+fn show_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_session: Res<GameSession>,
+    puzzle_progress: Option<Res<game::puzzle::PuzzleProgress>>,
+    game_over_text: Query<Entity, With<GameOverText>>,
+) {
+    // Resuming from the pause keeps the texts already displayed
+    if !game_over_text.is_empty() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
+
+    // Versus and puzzles display their own outcome
+    if !game_session.versus && puzzle_progress.is_none() {
+        commands.spawn((
+            Text2d::new("Game over"),
+            TextFont {
+                font: font.clone(),
+                font_size: 90.0,
+                ..default()
+            },
+            Anchor::Center,
+            TextColor(RED),
+            Transform::from_translation(Vec3::new(0.00, 0.00, 10.0)),
+            GameOverText,
+        ));
+    }
+
+    commands.spawn((
+        Text2d::new(get_seed_text(&game_session)),
+        TextFont {
+            font,
+            font_size: 30.0,
+            ..default()
+        },
+        Anchor::Center,
+        TextColor(BLUE),
+        Transform::from_translation(Vec3::new(0.00, -170.00, 10.0)),
+        GameOverText,
+    ));
+}
+
+fn setup_puzzle_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    puzzle_progress: Res<game::puzzle::PuzzleProgress>,
+) {
+    let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
+    let text_font = TextFont {
+        font: font.clone(),
+        font_size: 25.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text2d::new(get_puzzle_text(&puzzle_progress)),
+        text_font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::TopLeft,
+        Transform::from_translation(Vec3::new(200.00, 0.00, 0.0)),
+        PuzzleText,
+    ));
+}
+
+fn get_puzzle_text(puzzle_progress: &game::puzzle::PuzzleProgress) -> String {
+    let puzzle = puzzle_progress.get_puzzle();
+    format!(
+        "{0}\n{1}\nPieces left: {2}",
+        puzzle.get_name(),
+        puzzle.get_objective(),
+        puzzle_progress.get_remaining_pieces()
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_puzzle_progress(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut locked_events: EventReader<TetrominoLocked>,
+    mut puzzle_progress: ResMut<game::puzzle::PuzzleProgress>,
+    game_settings: Single<&GameSettings>,
+    state: Res<State<GameStatus>>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    mut puzzle_text: Single<&mut Text2d, With<PuzzleText>>,
+    puzzle_status_text: Query<Entity, With<PuzzleStatusText>>,
+) {
+    for event in locked_events.read() {
+        puzzle_progress.record_lock(&event.outcome);
+    }
+
+    if puzzle_progress.is_changed() {
+        puzzle_text.0 = get_puzzle_text(&puzzle_progress);
+    }
+
+    let (message, color) = match puzzle_progress.get_status() {
+        game::puzzle::PuzzleStatus::InProgress => return,
+        game::puzzle::PuzzleStatus::Solved => ("Solved!", GREEN),
+        game::puzzle::PuzzleStatus::Failed => ("Failed", RED),
+    };
+
+    // The outcome has already been displayed
+    if !puzzle_status_text.is_empty() {
+        return;
+    }
+
+    match state.get() {
+        GameStatus::Running => {
+            // Let the filled rows be cleared before ending the puzzle
+            if game_settings.board_status == BoardStatus::RemovingFilledRows {
+                return;
+            }
+
+            next_state.set(GameStatus::GameOver);
         }
+        GameStatus::GameOver => {}
+        _ => return,
     }
+
+    let font = asset_server.load("fonts/NovaSquare-Regular.ttf");
+    let text_font = TextFont {
+        font: font.clone(),
+        font_size: 90.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text2d::new(message),
+        text_font.clone(),
+        Anchor::Center,
+        TextColor(color),
+        Transform::from_translation(Vec3::new(0.00, 0.00, 10.0)),
+        PuzzleStatusText,
+    ));
 }
@@ -0,0 +1,503 @@
+use crate::cli::CommandLineArguments;
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game;
+use crate::game::GameMode;
+use crate::game::level::LevelProgression;
+use crate::gamepad::GamepadAssignment;
+use crate::seed;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver};
+
+const PROTOCOL_VERSION: u32 = 3;
+// Inputs are played a few steps after being read, hiding the latency of the network
+const INPUT_DELAY_TICKS: u64 = 3;
+// Steps between two comparisons of the boards of the two instances
+const HASH_INTERVAL_TICKS: u64 = 64;
+
+// Networked versus between two instances: both simulate both boards step by step,
+// advancing only once the inputs of the two players for the step are known
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            FixedUpdate,
+            (GameplaySet::Play, GameplaySet::Garbage).run_if(is_lockstep_ready),
+        )
+        .add_systems(
+            Update,
+            (
+                read_local_input.run_if(in_state(GameStatus::Running)),
+                receive_messages,
+            ),
+        )
+        .add_systems(FixedUpdate, exchange_inputs.in_set(GameplaySet::Input))
+        .add_systems(
+            FixedUpdate,
+            advance_tick
+                .after(GameplaySet::Garbage)
+                .run_if(in_state(GameStatus::Running).and(is_lockstep_ready)),
+        );
+    }
+}
+
+// Lines exchanged by the two instances, one message per line
+#[derive(Debug)]
+enum Message {
+    Hello(u32),
+    Match {
+        seed: String, // Name of the seed, last as it can contain spaces
+        mode: GameMode,
+        level: u16,
+        level_progression: LevelProgression,
+    },
+    Input {
+        tick: u64,
+        input: PlayerInput,
+    },
+    Garbage {
+        tick: u64,
+        lines: u8,
+    },
+    Hash {
+        tick: u64,
+        hash: u64,
+    },
+}
+
+#[derive(Resource)]
+pub struct Lockstep {
+    stream: TcpStream,
+    receiver: Mutex<Receiver<Result<Message, String>>>,
+    seed: String,
+    local_player: u8,
+    tick: u64,
+    ready: bool,
+    local_input: PlayerInput, // Read since the last input sent
+    next_input_tick: u64,
+    local_inputs: BTreeMap<u64, PlayerInput>,
+    remote_inputs: BTreeMap<u64, PlayerInput>,
+    // Attacks and board hashes of the two instances, compared in order
+    simulated_garbage: VecDeque<(u64, u64)>,
+    received_garbage: VecDeque<(u64, u64)>,
+    local_hashes: VecDeque<(u64, u64)>,
+    remote_hashes: VecDeque<(u64, u64)>,
+}
+
+// Hosts or joins a networked game when asked by the command line, the host decides
+// the seed and the rules of the game
+pub fn connect(
+    arguments: &mut CommandLineArguments,
+    seed: Option<String>,
+) -> Result<Option<Lockstep>, String> {
+    if let Some(address) = arguments.host.clone() {
+        let listener = TcpListener::bind(&address)
+            .map_err(|error| format!("Cannot host on '{0}': {1}", address, error))?;
+        println!("Waiting for an opponent on {0}", address);
+
+        let (stream, peer) = listener
+            .accept()
+            .map_err(|error| format!("Cannot accept the opponent: {0}", error))?;
+        println!("{0} joined the game", peer);
+
+        let reader = handshake(&stream)?;
+        let seed = seed.unwrap_or_else(|| seed::get_random_seed_name(&mut rand::rng()));
+        send_message(
+            &stream,
+            &Message::Match {
+                seed: seed.clone(),
+                mode: arguments.get_mode(),
+                level: arguments.level,
+                level_progression: arguments.level_progression.clone(),
+            },
+        )?;
+
+        Ok(Some(Lockstep::new(stream, reader, seed, 0)))
+    } else if let Some(address) = arguments.join.clone() {
+        let stream = TcpStream::connect(&address)
+            .map_err(|error| format!("Cannot join '{0}': {1}", address, error))?;
+
+        let mut reader = handshake(&stream)?;
+        match read_message(&mut reader)? {
+            Message::Match {
+                seed,
+                mode,
+                level,
+                level_progression,
+            } => {
+                println!("Joined the game on {0}", address);
+                arguments.mode = mode;
+                arguments.level = level;
+                arguments.level_progression = level_progression;
+                Ok(Some(Lockstep::new(stream, reader, seed, 1)))
+            }
+            message => Err(format!("Unexpected message from the host: {0}", message)),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+fn handshake(stream: &TcpStream) -> Result<BufReader<TcpStream>, String> {
+    stream
+        .set_nodelay(true)
+        .map_err(|error| error.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|error| error.to_string())?);
+
+    send_message(stream, &Message::Hello(PROTOCOL_VERSION))?;
+    match read_message(&mut reader)? {
+        Message::Hello(version) if version == PROTOCOL_VERSION => Ok(reader),
+        Message::Hello(version) => Err(format!(
+            "The opponent speaks the protocol version {0}, expected {1}",
+            version, PROTOCOL_VERSION
+        )),
+        message => Err(format!(
+            "Unexpected message from the opponent: {0}",
+            message
+        )),
+    }
+}
+
+fn send_message(stream: &TcpStream, message: &Message) -> Result<(), String> {
+    let mut stream = stream;
+    writeln!(stream, "{0}", message)
+        .map_err(|error| format!("Cannot send '{0}': {1}", message, error))
+}
+
+fn read_message(reader: &mut BufReader<TcpStream>) -> Result<Message, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err(String::from("Connection closed by the opponent")),
+        Ok(_) => line.trim().parse(),
+        Err(error) => Err(format!("Cannot receive from the opponent: {0}", error)),
+    }
+}
+
+impl Lockstep {
+    fn new(
+        stream: TcpStream,
+        mut reader: BufReader<TcpStream>,
+        seed: String,
+        local_player: u8,
+    ) -> Self {
+        // Messages are read in the background and collected every frame
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            loop {
+                let message = read_message(&mut reader);
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        // Nothing is played during the first steps, while the first inputs travel
+        let mut local_inputs = BTreeMap::new();
+        let mut remote_inputs = BTreeMap::new();
+        for tick in 0..INPUT_DELAY_TICKS {
+            local_inputs.insert(tick, PlayerInput::default());
+            remote_inputs.insert(tick, PlayerInput::default());
+        }
+
+        Self {
+            stream,
+            receiver: Mutex::new(receiver),
+            seed,
+            local_player,
+            tick: 0,
+            ready: false,
+            local_input: PlayerInput::default(),
+            next_input_tick: INPUT_DELAY_TICKS,
+            local_inputs,
+            remote_inputs,
+            simulated_garbage: VecDeque::new(),
+            received_garbage: VecDeque::new(),
+            local_hashes: VecDeque::new(),
+            remote_hashes: VecDeque::new(),
+        }
+    }
+
+    pub fn get_seed(&self) -> &str {
+        &self.seed
+    }
+
+    fn send(&self, message: &Message) {
+        // A broken connection is reported by the reading side
+        if let Err(error) = send_message(&self.stream, message) {
+            warn!("{0}", error);
+        }
+    }
+}
+
+// Checks the values computed by the two instances for the same steps are equal
+fn verify(local: &mut VecDeque<(u64, u64)>, remote: &mut VecDeque<(u64, u64)>) -> bool {
+    while let (Some(local_value), Some(remote_value)) = (local.front(), remote.front()) {
+        if local_value != remote_value {
+            return false;
+        }
+
+        local.pop_front();
+        remote.pop_front();
+    }
+
+    true
+}
+
+fn is_lockstep_ready(lockstep: Res<Lockstep>) -> bool {
+    lockstep.ready
+}
+
+// Every side plays with the keys of the single player games or with its first gamepad
+fn read_local_input(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    settings: Res<Settings>,
+    mut assignment: ResMut<GamepadAssignment>,
+    mut lockstep: ResMut<Lockstep>,
+) {
+    lockstep
+        .local_input
+        .merge(&settings.controls.solo.get_player_input(&keys));
+    lockstep.local_input.merge(&assignment.read_input(
+        0,
+        &gamepads,
+        &settings.controls,
+        time.delta(),
+    ));
+}
+
+fn receive_messages(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut lockstep: ResMut<Lockstep>,
+    state: Res<State<GameStatus>>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    network_status_text: Query<Entity, With<NetworkStatusText>>,
+) {
+    let messages: Vec<Result<Message, String>> = match lockstep.receiver.lock() {
+        Ok(receiver) => receiver.try_iter().collect(),
+        Err(_) => return,
+    };
+
+    for message in messages {
+        match message {
+            Ok(Message::Input { tick, input }) => {
+                lockstep.remote_inputs.insert(tick, input);
+            }
+            Ok(Message::Garbage { tick, lines }) => {
+                lockstep.received_garbage.push_back((tick, lines as u64));
+            }
+            Ok(Message::Hash { tick, hash }) => {
+                lockstep.remote_hashes.push_back((tick, hash));
+            }
+            Ok(message) => warn!("Unexpected message from the opponent: {0}", message),
+            Err(error) => {
+                warn!("{0}", error);
+                if network_status_text.is_empty() {
+                    show_network_status(&mut commands, &asset_server, "Disconnected");
+                }
+                if *state.get() != GameStatus::GameOver {
+                    next_state.set(GameStatus::GameOver);
+                }
+            }
+        }
+    }
+}
+
+fn exchange_inputs(
+    mut lockstep: ResMut<Lockstep>,
+    mut players: Query<(&Player, &mut PlayerInput)>,
+) {
+    // Send once per step what has been read, to be played a few steps later
+    let input_tick = lockstep.tick + INPUT_DELAY_TICKS;
+    if lockstep.next_input_tick == input_tick {
+        let input = std::mem::take(&mut lockstep.local_input);
+        lockstep.send(&Message::Input {
+            tick: input_tick,
+            input: input.clone(),
+        });
+        lockstep.local_inputs.insert(input_tick, input);
+        lockstep.next_input_tick += 1;
+    }
+
+    let tick = lockstep.tick;
+    let (Some(local_input), Some(remote_input)) = (
+        lockstep.local_inputs.get(&tick).cloned(),
+        lockstep.remote_inputs.get(&tick).cloned(),
+    ) else {
+        // Wait for the opponent
+        lockstep.ready = false;
+        return;
+    };
+
+    for (player, mut input) in &mut players {
+        *input = if player.id == lockstep.local_player {
+            local_input.clone()
+        } else {
+            remote_input.clone()
+        };
+    }
+
+    lockstep.ready = true;
+}
+
+fn advance_tick(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut lockstep: ResMut<Lockstep>,
+    mut locked_events: EventReader<TetrominoLocked>,
+    players: Query<(&Player, &game::GameBoard)>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+) {
+    let tick = lockstep.tick;
+
+    for event in locked_events.read() {
+        let Ok((player, _)) = players.get(event.player) else {
+            continue;
+        };
+
+        if event.attack_lines == 0 {
+            continue;
+        }
+
+        if player.id == lockstep.local_player {
+            lockstep.send(&Message::Garbage {
+                tick,
+                lines: event.attack_lines,
+            });
+        } else {
+            lockstep
+                .simulated_garbage
+                .push_back((tick, event.attack_lines as u64));
+        }
+    }
+
+    if tick.is_multiple_of(HASH_INTERVAL_TICKS) {
+        let mut boards: Vec<(u8, u64)> = players
+            .iter()
+            .map(|(player, game_board)| (player.id, game_board.get_hash()))
+            .collect();
+        boards.sort();
+
+        let hash = boards.iter().fold(0, |hash: u64, (_, board_hash)| {
+            hash.rotate_left(7) ^ board_hash
+        });
+        lockstep.send(&Message::Hash { tick, hash });
+        lockstep.local_hashes.push_back((tick, hash));
+    }
+
+    lockstep.local_inputs.remove(&tick);
+    lockstep.remote_inputs.remove(&tick);
+    lockstep.tick += 1;
+    lockstep.ready = false;
+
+    let lockstep = &mut *lockstep;
+    let garbage_matches = verify(
+        &mut lockstep.simulated_garbage,
+        &mut lockstep.received_garbage,
+    );
+    let hashes_match = verify(&mut lockstep.local_hashes, &mut lockstep.remote_hashes);
+    if !garbage_matches || !hashes_match {
+        error!(
+            "The game is out of sync with the opponent at step {0}",
+            tick
+        );
+        show_network_status(&mut commands, &asset_server, "Desync");
+        next_state.set(GameStatus::GameOver);
+    }
+}
+
+fn show_network_status(commands: &mut Commands, asset_server: &AssetServer, message: &str) {
+    commands.spawn((
+        Text2d::new(message),
+        TextFont {
+            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+            font_size: 60.0,
+            ..default()
+        },
+        Anchor::Center,
+        TextColor(RED),
+        Transform::from_translation(Vec3::new(0.00, -100.00, 10.0)),
+        NetworkStatusText,
+    ));
+}
+
+fn parse_number<T: FromStr>(value: Option<&str>) -> Result<T, String> {
+    let value = value.ok_or(String::from("Missing value in the message"))?;
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value '{0}' in the message", value))
+}
+
+impl FromStr for Message {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split_whitespace();
+        let message = match parts.next() {
+            Some("HELLO") => Message::Hello(parse_number(parts.next())?),
+            Some("MATCH") => {
+                let mut parts = value.splitn(5, ' ').skip(1);
+                Message::Match {
+                    mode: parts.next().unwrap_or_default().parse()?,
+                    level: parse_number(parts.next())?,
+                    level_progression: parts.next().unwrap_or_default().parse()?,
+                    seed: parts
+                        .next()
+                        .filter(|seed| !seed.is_empty())
+                        .ok_or(String::from("Missing seed in the message"))?
+                        .to_string(),
+                }
+            }
+            Some("INPUT") => Message::Input {
+                tick: parse_number(parts.next())?,
+                input: PlayerInput::from_bits(parse_number(parts.next())?),
+            },
+            Some("GARBAGE") => Message::Garbage {
+                tick: parse_number(parts.next())?,
+                lines: parse_number(parts.next())?,
+            },
+            Some("HASH") => Message::Hash {
+                tick: parse_number(parts.next())?,
+                hash: parse_number(parts.next())?,
+            },
+            _ => return Err(format!("Unknown message '{0}'", value)),
+        };
+
+        Ok(message)
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "HELLO {0}", version),
+            Message::Match {
+                seed,
+                mode,
+                level,
+                level_progression,
+            } => {
+                write!(
+                    f,
+                    "MATCH {0} {1} {2} {3}",
+                    mode, level, level_progression, seed
+                )
+            }
+            Message::Input { tick, input } => write!(f, "INPUT {0} {1}", tick, input.to_bits()),
+            Message::Garbage { tick, lines } => write!(f, "GARBAGE {0} {1}", tick, lines),
+            Message::Hash { tick, hash } => write!(f, "HASH {0} {1}", tick, hash),
+        }
+    }
+}
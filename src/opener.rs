@@ -0,0 +1,120 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game::opener::{OpenerProgress, OpenerStatus};
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+// Shows where the tetrominoes of an opener go and checks every placement against them
+pub struct OpenerPlugin;
+
+impl Plugin for OpenerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_opener_text)
+            .add_systems(FixedUpdate, restart_opener.in_set(GameplaySet::Input))
+            .add_systems(
+                FixedUpdate,
+                check_opener_placements.in_set(GameplaySet::Garbage),
+            )
+            .add_systems(Update, (update_opener_text, paint_opener_targets));
+    }
+}
+
+fn setup_opener_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    opener_progress: Res<OpenerProgress>,
+) {
+    commands.spawn((
+        Text2d::new(get_opener_text(&opener_progress)),
+        TextFont {
+            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+            font_size: 25.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::TopLeft,
+        Transform::from_translation(Vec3::new(200.00, 0.00, 0.0)),
+        OpenerText,
+    ));
+}
+
+fn get_opener_text(opener_progress: &OpenerProgress) -> String {
+    let opener = opener_progress.get_opener();
+    let mut text = format!(
+        "{0}\nPlaced: {1}/{2}",
+        opener.get_name(),
+        opener_progress.get_placed_count(),
+        opener.get_placements().len()
+    );
+
+    match (
+        opener_progress.get_status(),
+        opener_progress.get_last_lock(),
+    ) {
+        (OpenerStatus::Completed, _) => text.push_str("\nOpener completed!"),
+        (OpenerStatus::Failed, Some((tetromino, _))) => {
+            text.push_str(&format!("\n{0} misplaced, restart to try again", tetromino))
+        }
+        (_, Some((tetromino, true))) => text.push_str(&format!("\n{0} well placed", tetromino)),
+        _ => {}
+    }
+    text
+}
+
+// A new game starts the opener over
+fn restart_opener(clock: Res<SimulationClock>, mut opener_progress: ResMut<OpenerProgress>) {
+    if clock.tick == 0 {
+        opener_progress.reset();
+    }
+}
+
+fn check_opener_placements(
+    mut locked_events: EventReader<TetrominoLocked>,
+    mut opener_progress: ResMut<OpenerProgress>,
+) {
+    for event in locked_events.read() {
+        opener_progress.record_lock(&event.tetromino, &event.cells);
+    }
+}
+
+fn update_opener_text(
+    opener_progress: Res<OpenerProgress>,
+    mut opener_text: Single<(&mut Text2d, &mut TextColor), With<OpenerText>>,
+) {
+    if !opener_progress.is_changed() {
+        return;
+    }
+
+    let (text, color) = &mut *opener_text;
+    text.0 = get_opener_text(&opener_progress);
+    color.0 = match opener_progress.get_status() {
+        OpenerStatus::InProgress => Color::WHITE,
+        OpenerStatus::Completed => GREEN,
+        OpenerStatus::Failed => RED,
+    };
+}
+
+// Outlines the cells of the tetrominoes still to place, smaller than the cells of the board
+fn paint_opener_targets(
+    opener_progress: Res<OpenerProgress>,
+    players: Query<&Transform, With<Player>>,
+    mut gizmos: Gizmos,
+    settings: Res<Settings>,
+) {
+    for player_transform in players {
+        for placement in opener_progress.get_remaining_placements() {
+            let color =
+                crate::get_tetromino_outline_color_by_type(&placement.tetromino, &settings.display);
+            for cell in &placement.cells {
+                let translation = player_transform
+                    .transform_point(crate::get_transform_by_board_cell(*cell).translation);
+                gizmos.rect_2d(
+                    Isometry2d::from_xy(translation.x, translation.y),
+                    Vec2::splat(SQUARE_SIZE / 2.0),
+                    color,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+use std::time::Duration;
+
+// Distance to drag down or up before the gesture drops or holds the tetromino
+const SWIPE_DISTANCE: f32 = SQUARE_SIZE * 3.0;
+// Longest press still taken as a tap, and longest swipe down still taken as a hard drop
+const TAP_MS: u64 = 250;
+const FLICK_MS: u64 = 300;
+
+const BUTTON_SIZE: f32 = 80.0;
+const BUTTONS_LEFT: f32 = -560.0;
+const BUTTONS_TOP: f32 = -160.0;
+
+// Commands of the on-screen buttons, by row
+const BUTTONS: [[(PointerButton, &str); 4]; 2] = [
+    [
+        (PointerButton::RotateCCW, "CCW"),
+        (PointerButton::RotateCW, "CW"),
+        (PointerButton::Rotate180, "180"),
+        (PointerButton::Hold, "Hold"),
+    ],
+    [
+        (PointerButton::Left, "Left"),
+        (PointerButton::SoftDrop, "Down"),
+        (PointerButton::Right, "Right"),
+        (PointerButton::HardDrop, "Drop"),
+    ],
+];
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerButton {
+    Left,
+    Right,
+    SoftDrop,
+    HardDrop,
+    RotateCW,
+    RotateCCW,
+    Rotate180,
+    Hold,
+}
+
+// Identifies the mouse or the finger dragging
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PointerId {
+    Mouse,
+    Touch(u64),
+}
+
+// Drag going on, from the moment the mouse button or the finger is pressed to when it is released
+struct Gesture {
+    pointer: PointerId,
+    player: u8,
+    start: Vec2,
+    anchor_x: f32, // Moves once the pointer gets a square away from here
+    elapsed: Duration,
+    started: bool, // Whether the first frame of the gesture has been handled
+    moved: bool,
+    button: Option<PointerButton>, // Pressed on-screen button, the gesture does nothing else
+}
+
+#[derive(Resource, Default)]
+pub struct PointerControls {
+    gesture: Option<Gesture>,
+}
+
+// Swipes, taps and on-screen buttons driving the tetromino with a mouse or on a touchscreen
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointerControls>()
+            .add_systems(Startup, setup_buttons)
+            .add_systems(Update, show_buttons.run_if(resource_changed::<Settings>))
+            .add_systems(
+                Update,
+                read_pointer_input.run_if(in_state(GameStatus::Running)),
+            );
+    }
+}
+
+fn setup_buttons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_session: Res<GameSession>,
+) {
+    // Buttons are left of the single board, where the board of the first player is in versus
+    if game_session.versus {
+        return;
+    }
+
+    let text_font = TextFont {
+        font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    for (row, buttons) in BUTTONS.iter().enumerate() {
+        for (col, (button, label)) in buttons.iter().enumerate() {
+            commands
+                .spawn((
+                    Sprite::from_color(DARK_GRAY, Vec2::splat(BUTTON_SIZE - 10.0)),
+                    Transform::from_xyz(
+                        BUTTONS_LEFT + BUTTON_SIZE * (col as f32 + 0.5),
+                        BUTTONS_TOP - BUTTON_SIZE * (row as f32 + 0.5),
+                        5.0,
+                    ),
+                    Visibility::Hidden,
+                    *button,
+                ))
+                .with_child((
+                    Text2d::new(*label),
+                    text_font.clone(),
+                    Anchor::Center,
+                    Transform::from_xyz(0.0, 0.0, 1.0),
+                ));
+        }
+    }
+}
+
+fn show_buttons(settings: Res<Settings>, mut buttons: Query<&mut Visibility, With<PointerButton>>) {
+    for mut visibility in &mut buttons {
+        *visibility = match settings.controls.touch_buttons {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
+// Gets the pointer just pressed, the one being dragged and whether it has just been released
+fn get_pointer(
+    gesture: Option<&Gesture>,
+    mouse_buttons: &ButtonInput<MouseButton>,
+    touches: &Touches,
+    window: &Window,
+) -> Option<(PointerId, Vec2, bool)> {
+    match gesture.map(|gesture| gesture.pointer) {
+        Some(PointerId::Mouse) => {
+            let position = window.cursor_position()?;
+            Some((
+                PointerId::Mouse,
+                position,
+                !mouse_buttons.pressed(MouseButton::Left),
+            ))
+        }
+        Some(PointerId::Touch(id)) => match touches.get_pressed(id) {
+            Some(touch) => Some((PointerId::Touch(id), touch.position(), false)),
+            None => touches
+                .iter_just_released()
+                .find(|touch| touch.id() == id)
+                .map(|touch| (PointerId::Touch(id), touch.position(), true)),
+        },
+        None if mouse_buttons.just_pressed(MouseButton::Left) => window
+            .cursor_position()
+            .map(|position| (PointerId::Mouse, position, false)),
+        None => touches
+            .iter_just_pressed()
+            .next()
+            .map(|touch| (PointerId::Touch(touch.id()), touch.position(), false)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_pointer_input(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut pointer_controls: ResMut<PointerControls>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    buttons: Query<(&PointerButton, &GlobalTransform, &ViewVisibility)>,
+    mut players: Query<(&Player, &GlobalTransform, &mut PlayerInput), With<LocalControls>>,
+) {
+    let Some((pointer, position, released)) = get_pointer(
+        pointer_controls.gesture.as_ref(),
+        &mouse_buttons,
+        &touches,
+        &window,
+    ) else {
+        // The pointer left the window
+        pointer_controls.gesture = None;
+        return;
+    };
+
+    let (camera, camera_transform) = *camera;
+    let Ok(position) = camera.viewport_to_world_2d(camera_transform, position) else {
+        return;
+    };
+
+    let gesture = pointer_controls.gesture.get_or_insert_with(|| {
+        let button = buttons
+            .iter()
+            .find(|(_, transform, visibility)| {
+                let distance = (transform.translation().truncate() - position).abs();
+                visibility.get() && distance.max_element() <= BUTTON_SIZE / 2.0
+            })
+            .map(|(button, ..)| *button);
+
+        // The closest board takes the gesture
+        let player = players
+            .iter()
+            .min_by(|(_, a, _), (_, b, _)| {
+                let a = (a.translation().x - position.x).abs();
+                let b = (b.translation().x - position.x).abs();
+                a.total_cmp(&b)
+            })
+            .map(|(player, ..)| player.id)
+            .unwrap_or_default();
+
+        Gesture {
+            pointer,
+            player,
+            start: position,
+            anchor_x: position.x,
+            elapsed: Duration::ZERO,
+            started: false,
+            moved: false,
+            button,
+        }
+    });
+    gesture.elapsed += time.delta();
+    let just_started = !gesture.started;
+    gesture.started = true;
+
+    let mut input = PlayerInput::default();
+    match gesture.button {
+        Some(button) => {
+            // Held buttons act once when pressed, the soft drop goes on until released
+            match button {
+                PointerButton::SoftDrop => input.down = !released,
+                _ if !just_started => {}
+                PointerButton::Left => input.left = true,
+                PointerButton::Right => input.right = true,
+                PointerButton::HardDrop => input.hard_drop = true,
+                PointerButton::RotateCW => input.rotate = true,
+                PointerButton::RotateCCW => input.rotate_ccw = true,
+                PointerButton::Rotate180 => input.rotate_180 = true,
+                PointerButton::Hold => input.hold = true,
+            }
+        }
+        None => {
+            // Dragging sideways moves the tetromino a square at a time, following the pointer
+            let shift = position.x - gesture.anchor_x;
+            if shift.abs() >= SQUARE_SIZE {
+                input.left = shift < 0.0;
+                input.right = shift > 0.0;
+                gesture.anchor_x += SQUARE_SIZE * shift.signum();
+                gesture.moved = true;
+            }
+
+            let fall = gesture.start.y - position.y;
+            if fall >= SQUARE_SIZE && fall.abs() > (position.x - gesture.start.x).abs() {
+                input.down = !released;
+                gesture.moved = true;
+            }
+
+            if released {
+                let elapsed = gesture.elapsed.as_millis() as u64;
+                if fall >= SWIPE_DISTANCE && elapsed <= FLICK_MS {
+                    input.hard_drop = true;
+                } else if -fall >= SWIPE_DISTANCE {
+                    input.hold = true;
+                } else if !gesture.moved && elapsed <= TAP_MS {
+                    input.rotate = true;
+                }
+            }
+        }
+    }
+
+    let player = gesture.player;
+    if released {
+        pointer_controls.gesture = None;
+    }
+
+    // Pointer controls are off while the settings disable them
+    if !settings.controls.pointer {
+        return;
+    }
+
+    for (_, _, mut player_input) in players
+        .iter_mut()
+        .filter(|(candidate, ..)| candidate.id == player)
+    {
+        player_input.merge(&input);
+    }
+}
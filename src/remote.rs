@@ -0,0 +1,316 @@
+use crate::ecs::*;
+use crate::game;
+use crate::spectator;
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Error codes of JSON-RPC 2.0, and the one of the actions refused while the game is not running
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NOT_RUNNING: i64 = -32000;
+
+// JSON-RPC 2.0 requests, one per line, such as:
+//
+//   {"jsonrpc":"2.0","id":1,"method":"get_state"}
+//   {"jsonrpc":"2.0","id":2,"method":"move_left","params":{"player":0}}
+#[derive(Deserialize, Debug)]
+struct Request {
+    jsonrpc: String,
+    id: Option<Value>, // Notifications have none and get no response
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct Params {
+    player: u8,
+}
+
+// Request waiting for the game to answer it, and the way back to the controller
+struct Call {
+    request: Request,
+    responder: Sender<Option<String>>,
+}
+
+// Lets external processes query the game and play it, on a local address
+pub struct RemoteControlPlugin;
+
+#[derive(Resource)]
+pub struct RemoteControlServer {
+    calls: Mutex<Receiver<Call>>,
+    actions: BTreeMap<u8, VecDeque<PlayerInput>>, // Played one per simulation step by every player
+}
+
+impl Plugin for RemoteControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, answer_calls)
+            .add_systems(FixedUpdate, play_remote_actions.in_set(GameplaySet::Input));
+    }
+}
+
+impl RemoteControlServer {
+    pub fn start(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|error| {
+            format!(
+                "Cannot serve the remote control on '{0}': {1}",
+                address, error
+            )
+        })?;
+        // Anyone reaching the address could play, so it stays on this computer
+        match listener.local_addr() {
+            Ok(local_address) if local_address.ip().is_loopback() => {}
+            _ => {
+                return Err(format!(
+                    "The remote control only listens on the local host, such as 127.0.0.1:7880, not '{0}'",
+                    address
+                ));
+            }
+        }
+        println!("Remote control listening on {0}", address);
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    let _ = stream.set_nodelay(true);
+                    let Ok(reader) = stream.try_clone() else {
+                        return;
+                    };
+                    let mut stream = stream;
+                    let (responder, responses) = mpsc::channel();
+
+                    for line in BufReader::new(reader).lines() {
+                        let Ok(line) = line else {
+                            return;
+                        };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+
+                        let response = match parse_request(&line) {
+                            Ok(request) => {
+                                let call = Call {
+                                    request,
+                                    responder: responder.clone(),
+                                };
+                                // The game is gone once it stops answering
+                                if sender.send(call).is_err() {
+                                    return;
+                                }
+                                match responses.recv() {
+                                    Ok(response) => response,
+                                    Err(_) => return,
+                                }
+                            }
+                            Err(response) => Some(response),
+                        };
+
+                        if let Some(response) = response
+                            && writeln!(stream, "{0}", response).is_err()
+                        {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            calls: Mutex::new(receiver),
+            actions: BTreeMap::new(),
+        })
+    }
+}
+
+fn parse_request(line: &str) -> Result<Request, String> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|error| get_error(&Value::Null, PARSE_ERROR, &error.to_string()))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    match serde_json::from_value::<Request>(value) {
+        Ok(request) if request.jsonrpc == "2.0" => Ok(request),
+        Ok(_) => Err(get_error(
+            &id,
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is spoken",
+        )),
+        Err(error) => Err(get_error(&id, INVALID_REQUEST, &error.to_string())),
+    }
+}
+
+fn get_result(id: &Value, result: Value) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string()
+}
+
+fn get_error(id: &Value, code: i64, message: &str) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}}).to_string()
+}
+
+fn get_status_name(status: &GameStatus) -> &'static str {
+    match status {
+        GameStatus::Countdown => "countdown",
+        GameStatus::Running => "running",
+        GameStatus::GameOver => "game_over",
+        GameStatus::Pause => "paused",
+        GameStatus::Editor => "editing",
+    }
+}
+
+// Commands of a player sent by the actions, played during a single simulation step
+fn get_action_input(method: &str) -> Option<PlayerInput> {
+    let input = match method {
+        "move_left" => PlayerInput {
+            left: true,
+            ..default()
+        },
+        "move_right" => PlayerInput {
+            right: true,
+            ..default()
+        },
+        "rotate_clockwise" => PlayerInput {
+            rotate: true,
+            ..default()
+        },
+        "rotate_counter_clockwise" => PlayerInput {
+            rotate_ccw: true,
+            ..default()
+        },
+        "rotate_180" => PlayerInput {
+            rotate_180: true,
+            ..default()
+        },
+        "soft_drop" => PlayerInput {
+            down: true,
+            ..default()
+        },
+        "hard_drop" => PlayerInput {
+            hard_drop: true,
+            ..default()
+        },
+        "hold" => PlayerInput {
+            hold: true,
+            ..default()
+        },
+        _ => return None,
+    };
+    Some(input)
+}
+
+fn answer_calls(
+    mut server: ResMut<RemoteControlServer>,
+    state: Res<State<GameStatus>>,
+    game_session: Res<GameSession>,
+    mut status_requests: EventWriter<StatusRequest>,
+    players: Query<(&Player, &game::GameBoard, &GameSettings)>,
+) {
+    let calls: Vec<Call> = match server.calls.lock() {
+        Ok(calls) => calls.try_iter().collect(),
+        Err(_) => return,
+    };
+
+    for call in calls {
+        let request = call.request;
+        let id = request.id.clone().unwrap_or(Value::Null);
+
+        let params = match request.params {
+            Value::Null => Ok(Params::default()),
+            params => serde_json::from_value::<Params>(params),
+        };
+        let response = match params {
+            Err(error) => get_error(&id, INVALID_PARAMS, &error.to_string()),
+            Ok(params)
+                if !players
+                    .iter()
+                    .any(|(player, _, _)| player.id == params.player) =>
+            {
+                let message = format!("There is no player {0}", params.player);
+                get_error(&id, INVALID_PARAMS, &message)
+            }
+            Ok(params) => match request.method.as_str() {
+                "get_state" => {
+                    let number_of_players = players.iter().len() as u8;
+                    let boards: Vec<spectator::BoardSnapshot> = players
+                        .iter()
+                        .map(|(player, game_board, game_settings)| {
+                            spectator::get_board_snapshot(
+                                player,
+                                number_of_players,
+                                game_board,
+                                game_settings,
+                            )
+                        })
+                        .collect();
+                    let result = json!({
+                        "status": get_status_name(state.get()),
+                        "seed": game_session.current_seed,
+                        "players": boards,
+                    });
+                    get_result(&id, result)
+                }
+                "pause" => {
+                    status_requests.write(StatusRequest::Pause);
+                    get_result(&id, Value::Null)
+                }
+                "resume" => {
+                    status_requests.write(StatusRequest::Resume);
+                    get_result(&id, Value::Null)
+                }
+                "restart" => {
+                    server.actions.clear();
+                    status_requests.write(StatusRequest::Restart);
+                    get_result(&id, Value::Null)
+                }
+                method => match get_action_input(method) {
+                    Some(_) if *state.get() != GameStatus::Running => {
+                        get_error(&id, NOT_RUNNING, "The game is not running")
+                    }
+                    Some(input) => {
+                        server
+                            .actions
+                            .entry(params.player)
+                            .or_default()
+                            .push_back(input);
+                        get_result(&id, Value::Null)
+                    }
+                    None => {
+                        let message = format!("Unknown method '{0}'", method);
+                        get_error(&id, METHOD_NOT_FOUND, &message)
+                    }
+                },
+            },
+        };
+
+        let response = request.id.map(|_| response);
+        let _ = call.responder.send(response);
+    }
+}
+
+// Plays the next action of every player, waiting for the filled rows to be removed
+fn play_remote_actions(
+    mut server: ResMut<RemoteControlServer>,
+    mut players: Query<(&Player, &GameSettings, &mut PlayerInput)>,
+) {
+    for (player, game_settings, mut input) in &mut players {
+        if game_settings.board_status != BoardStatus::Playing {
+            continue;
+        }
+
+        if let Some(action) = server
+            .actions
+            .get_mut(&player.id)
+            .and_then(|actions| actions.pop_front())
+        {
+            input.merge(&action);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game::GameMode;
+use crate::game::level::LevelProgression;
+use crate::game::scoring::GameplaySettings;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const REPLAY_VERSION: u32 = 1;
+
+// Playback speeds selectable with the up and down arrows
+const PLAYBACK_SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED_INDEX: usize = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub player: u8,
+    pub input: PlayerInput,
+}
+
+// Everything needed to simulate a game again: the seed, the settings and the inputs of every step
+#[derive(Clone, Debug)]
+pub struct Replay {
+    seed: String, // Name of the seed
+    mode: GameMode,
+    level: u16,
+    level_progression: LevelProgression,
+    players: u8,
+    gameplay: GameplaySettings, // Speeds and points of the settings file when recorded
+    ticks: u64,                 // Steps simulated before the recording stopped
+    inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    pub fn new(
+        mode: GameMode,
+        level: u16,
+        level_progression: LevelProgression,
+        players: u8,
+        gameplay: GameplaySettings,
+    ) -> Self {
+        Self {
+            seed: String::new(),
+            mode,
+            level,
+            level_progression,
+            players,
+            gameplay,
+            ticks: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    // Loads a replay from a file made of a header and the inputs, such as:
+    //
+    //   version: 1
+    //   seed: 2025-06-21
+    //   mode: marathon
+    //   level: 1
+    //   level-progression: fixed
+    //   players: 1
+    //   gameplay: (base_speed_ms:800,level_speed_delta_ms:25,...)
+    //   ticks: 1200
+    //   inputs:
+    //   35 0 2
+    //   36 0 8
+    //
+    // Every input line holds the step, the player and the pressed keys, only steps with some
+    // key pressed are written. Replays without the gameplay use the default one.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read replay '{0}': {1}", path, error))?;
+        content.parse()
+    }
+
+    pub fn get_seed(&self) -> &str {
+        &self.seed
+    }
+
+    pub fn get_mode(&self) -> &GameMode {
+        &self.mode
+    }
+
+    pub fn get_level(&self) -> u16 {
+        self.level
+    }
+
+    pub fn get_level_progression(&self) -> &LevelProgression {
+        &self.level_progression
+    }
+
+    pub fn get_players(&self) -> u8 {
+        self.players
+    }
+
+    pub fn get_gameplay(&self) -> &GameplaySettings {
+        &self.gameplay
+    }
+}
+
+impl FromStr for Replay {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut seed = None;
+        let mut mode = None;
+        let mut level = None;
+        let mut level_progression = None;
+        let mut players = 1;
+        let mut gameplay = GameplaySettings::default();
+        let mut ticks = 0;
+        let mut inputs = Vec::new();
+        let mut reading_inputs = false;
+
+        for line in value.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if reading_inputs {
+                let mut parts = line.split_whitespace();
+                let input = ReplayInput {
+                    tick: parse_number(parts.next(), line)?,
+                    player: parse_number(parts.next(), line)?,
+                    input: PlayerInput::from_bits(parse_number(parts.next(), line)?),
+                };
+                if input.player >= players {
+                    return Err(format!("Invalid player in the replay line '{0}'", line));
+                }
+                inputs.push(input);
+                continue;
+            }
+
+            let Some((key, content)) = line.split_once(':') else {
+                return Err(format!("Invalid replay line '{0}'", line));
+            };
+
+            let content = content.trim();
+            match key.trim() {
+                "version" => version = Some(parse_number::<u32>(Some(content), line)?),
+                "seed" => seed = Some(content.to_string()),
+                "mode" => mode = Some(content.parse::<GameMode>()?),
+                "level" => level = Some(parse_number(Some(content), line)?),
+                "level-progression" => level_progression = Some(content.parse()?),
+                "players" => players = parse_number(Some(content), line)?,
+                "gameplay" => {
+                    gameplay = ron::from_str(content)
+                        .map_err(|error| format!("Invalid replay gameplay: {0}", error))?;
+                }
+                "ticks" => ticks = parse_number(Some(content), line)?,
+                "inputs" => reading_inputs = true,
+                _ => return Err(format!("Unknown replay key '{0}'", key)),
+            }
+        }
+
+        if version != Some(REPLAY_VERSION) {
+            return Err(String::from("Unsupported replay version"));
+        }
+
+        let (Some(seed), Some(mode), Some(level), Some(level_progression)) =
+            (seed, mode, level, level_progression)
+        else {
+            return Err(String::from(
+                "Replay has no seed, mode, level or level progression",
+            ));
+        };
+
+        if mode == GameMode::Puzzle || !(1..=2).contains(&players) {
+            return Err(String::from("Unsupported replay game"));
+        }
+
+        if !(1..=MAX_LEVEL).contains(&level) {
+            return Err(format!("Invalid level {0} in the replay", level));
+        }
+
+        // The playback walks the inputs in order
+        inputs.sort_by_key(|input: &ReplayInput| (input.tick, input.player));
+
+        Ok(Self {
+            seed,
+            mode,
+            level,
+            level_progression,
+            players,
+            gameplay,
+            ticks,
+            inputs,
+        })
+    }
+}
+
+impl Display for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "version: {0}", REPLAY_VERSION)?;
+        writeln!(f, "seed: {0}", self.seed)?;
+        writeln!(f, "mode: {0}", self.mode)?;
+        writeln!(f, "level: {0}", self.level)?;
+        writeln!(f, "level-progression: {0}", self.level_progression)?;
+        writeln!(f, "players: {0}", self.players)?;
+        let gameplay = ron::to_string(&self.gameplay).map_err(|_| std::fmt::Error)?;
+        writeln!(f, "gameplay: {0}", gameplay)?;
+        writeln!(f, "ticks: {0}", self.ticks)?;
+        writeln!(f, "inputs:")?;
+        for input in &self.inputs {
+            writeln!(
+                f,
+                "{0} {1} {2}",
+                input.tick,
+                input.player,
+                input.input.to_bits()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_number<T: FromStr>(value: Option<&str>, line: &str) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(format!("Invalid replay line '{0}'", line))
+}
+
+// Records the inputs of the game being played, saving them once the game ends
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    directory: PathBuf,
+    replay: Replay,
+    saved: bool,
+}
+
+impl ReplayRecorder {
+    pub fn new(directory: PathBuf, replay: Replay) -> Self {
+        Self {
+            directory,
+            replay,
+            saved: false,
+        }
+    }
+
+    // Starts recording a new game, dropping the inputs of the previous one
+    pub fn start(&mut self, seed: String, gameplay: &GameplaySettings) {
+        self.replay.seed = seed;
+        self.replay.gameplay = gameplay.clone();
+        self.replay.ticks = 0;
+        self.replay.inputs.clear();
+        self.saved = false;
+    }
+
+    pub fn save(&mut self, ticks: u64) {
+        if self.saved || ticks == 0 {
+            return;
+        }
+
+        self.saved = true;
+        self.replay.ticks = ticks;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self.directory.join(format!(
+            "blocchi-{0}-{1}.replay",
+            self.replay.mode, timestamp
+        ));
+
+        let result = std::fs::create_dir_all(&self.directory)
+            .and_then(|_| std::fs::write(&path, self.replay.to_string()));
+        match result {
+            Ok(_) => println!("Replay saved to {0}", path.display()),
+            Err(error) => eprintln!("Cannot save replay '{0}': {1}", path.display(), error),
+        }
+    }
+}
+
+pub struct ReplayRecorderPlugin;
+
+impl Plugin for ReplayRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, start_recording)
+            .add_systems(
+                FixedUpdate,
+                record_inputs
+                    .in_set(GameplaySet::Play)
+                    .before(crate::move_and_rotate_tetromino),
+            )
+            .add_systems(OnEnter(GameStatus::GameOver), save_replay)
+            .add_systems(Last, save_replay_on_exit);
+    }
+}
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    game_session: Res<GameSession>,
+    settings: Res<Settings>,
+) {
+    recorder.start(game_session.current_seed.clone(), &settings.gameplay);
+}
+
+fn record_inputs(
+    mut recorder: ResMut<ReplayRecorder>,
+    clock: Res<SimulationClock>,
+    players: Query<(&Player, &PlayerInput)>,
+) {
+    for (player, input) in players {
+        if !input.is_empty() {
+            recorder.replay.inputs.push(ReplayInput {
+                tick: clock.tick,
+                player: player.id,
+                input: input.clone(),
+            });
+        }
+    }
+}
+
+fn save_replay(mut recorder: ResMut<ReplayRecorder>, clock: Res<SimulationClock>) {
+    recorder.save(clock.tick);
+}
+
+// Games left unfinished are saved as well
+fn save_replay_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut recorder: ResMut<ReplayRecorder>,
+    clock: Res<SimulationClock>,
+) {
+    if exit_events.read().next().is_some() {
+        recorder.save(clock.tick);
+    }
+}
+
+// Replays a recorded game, the players are driven by the recorded inputs instead of the keyboard
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    next_input: usize,
+    speed_index: usize,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_input: 0,
+            speed_index: NORMAL_SPEED_INDEX,
+        }
+    }
+
+    fn is_finished(&self, tick: u64) -> bool {
+        tick >= self.replay.ticks
+    }
+}
+
+pub struct ReplayPlaybackPlugin;
+
+impl Plugin for ReplayPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_playback_text)
+            .add_systems(
+                FixedUpdate,
+                play_inputs
+                    .in_set(GameplaySet::Input)
+                    .run_if(not(is_playback_finished)),
+            )
+            .add_systems(Update, (control_playback, step_playback).chain())
+            .add_systems(Update, update_playback_text);
+    }
+}
+
+fn is_playback_finished(playback: Res<ReplayPlayback>, clock: Res<SimulationClock>) -> bool {
+    playback.is_finished(clock.tick)
+}
+
+fn play_inputs(
+    mut playback: ResMut<ReplayPlayback>,
+    clock: Res<SimulationClock>,
+    mut players: Query<(&Player, &mut PlayerInput)>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    while let Some(recorded) = playback.replay.inputs.get(playback.next_input) {
+        if recorded.tick > clock.tick {
+            break;
+        }
+
+        if recorded.tick == clock.tick {
+            for (player, mut input) in &mut players {
+                if player.id == recorded.player {
+                    *input = recorded.input.clone();
+                }
+            }
+        }
+        playback.next_input += 1;
+    }
+
+    // Unfinished games stop where the recording stopped
+    if playback.is_finished(clock.tick + 1) {
+        time.pause();
+    }
+}
+
+fn control_playback(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if keys.just_pressed(KeyCode::ArrowUp) && playback.speed_index + 1 < PLAYBACK_SPEEDS.len() {
+        playback.speed_index += 1;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) && playback.speed_index > 0 {
+        playback.speed_index -= 1;
+    }
+    if playback.is_changed() {
+        time.set_relative_speed(PLAYBACK_SPEEDS[playback.speed_index]);
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        if time.is_paused() {
+            time.unpause();
+        } else {
+            time.pause();
+        }
+    }
+}
+
+// While paused, the right arrow simulates a single step
+fn step_playback(world: &mut World) {
+    let step = world
+        .resource::<ButtonInput<KeyCode>>()
+        .just_pressed(KeyCode::ArrowRight);
+    let finished = world
+        .resource::<ReplayPlayback>()
+        .is_finished(world.resource::<SimulationClock>().tick);
+    if step && !finished && world.resource::<Time<Virtual>>().is_paused() {
+        world.run_schedule(FixedUpdate);
+    }
+}
+
+fn setup_playback_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+            font_size: 25.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Right),
+        Anchor::BottomRight,
+        Transform::from_translation(Vec3::new(620.00, -340.00, 0.0)),
+        TextColor(BLUE),
+        PlaybackText,
+    ));
+}
+
+fn update_playback_text(
+    playback: Res<ReplayPlayback>,
+    clock: Res<SimulationClock>,
+    time: Res<Time<Virtual>>,
+    mut playback_text: Single<&mut Text2d, With<PlaybackText>>,
+) {
+    let status = if playback.is_finished(clock.tick) {
+        "End of replay"
+    } else if time.is_paused() {
+        "Paused"
+    } else {
+        "Playing"
+    };
+
+    let text = format!(
+        "Replay {0}x\n{1}\nStep {2}/{3}",
+        PLAYBACK_SPEEDS[playback.speed_index], status, clock.tick, playback.replay.ticks
+    );
+    if playback_text.0 != text {
+        playback_text.0 = text;
+    }
+}
@@ -0,0 +1,204 @@
+use crate::cli::CommandLineArguments;
+use crate::ecs::*;
+use crate::game;
+use crate::game::GameMode;
+use crate::game::level::LevelProgression;
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Bumped whenever the saved structures change, older saves cannot be resumed
+const SAVE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub game_board: game::GameBoard,
+    pub game_settings: GameSettings,
+    pub rng: Entropy<ChaCha8Rng>,
+    pub garbage_rng: Entropy<ChaCha8Rng>,
+}
+
+// Game in progress saved to a file, to be resumed on a later launch
+#[derive(Serialize, Deserialize, Resource)]
+pub struct SavedGame {
+    version: u32,
+    mode: GameMode,
+    level: u16,
+    level_progression: LevelProgression,
+    seed: String,
+    tick: u64,
+    players: Vec<SavedPlayer>,
+}
+
+impl SavedGame {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read saved game '{0}': {1}", path.display(), error))?;
+
+        // Check the version first, the rest of the file is only known for the current version
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|error| format!("Invalid saved game '{0}': {1}", path.display(), error))?;
+        let version = value.get("version").and_then(|version| version.as_u64());
+        if version != Some(SAVE_VERSION as u64) {
+            return Err(format!(
+                "Saved game '{0}' has an unsupported version",
+                path.display()
+            ));
+        }
+
+        let saved_game: Self = serde_json::from_value(value)
+            .map_err(|error| format!("Invalid saved game '{0}': {1}", path.display(), error))?;
+        if !(1..=2).contains(&saved_game.players.len()) || saved_game.mode == GameMode::Puzzle {
+            return Err(format!(
+                "Saved game '{0}' cannot be resumed",
+                path.display()
+            ));
+        }
+
+        Ok(saved_game)
+    }
+
+    pub fn get_mode(&self) -> &GameMode {
+        &self.mode
+    }
+
+    pub fn get_level(&self) -> u16 {
+        self.level
+    }
+
+    pub fn get_level_progression(&self) -> &LevelProgression {
+        &self.level_progression
+    }
+
+    pub fn get_seed(&self) -> &str {
+        &self.seed
+    }
+
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get_players(&self) -> &[SavedPlayer] {
+        &self.players
+    }
+}
+
+type SavedPlayers<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Player,
+        &'static game::GameBoard,
+        &'static GameSettings,
+        &'static Entropy<ChaCha8Rng>,
+        &'static GarbageRng,
+    ),
+    Without<Global>,
+>;
+
+// Where the game is saved, F5 or closing the window saves it
+#[derive(Resource)]
+pub struct SaveFile(pub PathBuf);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, save_on_key)
+            .add_systems(Last, save_on_exit)
+            .add_systems(OnEnter(GameStatus::GameOver), delete_on_game_over);
+    }
+}
+
+fn save_on_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameStatus>>,
+    save_file: Res<SaveFile>,
+    arguments: Res<CommandLineArguments>,
+    game_session: Res<GameSession>,
+    clock: Res<SimulationClock>,
+    players: SavedPlayers,
+) {
+    if keys.just_pressed(KeyCode::F5) && *state.get() != GameStatus::GameOver {
+        save(&save_file, &arguments, &game_session, &clock, players);
+    }
+}
+
+fn save_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    state: Res<State<GameStatus>>,
+    save_file: Res<SaveFile>,
+    arguments: Res<CommandLineArguments>,
+    game_session: Res<GameSession>,
+    clock: Res<SimulationClock>,
+    players: SavedPlayers,
+) {
+    if exit_events.read().next().is_some() && *state.get() != GameStatus::GameOver {
+        save(&save_file, &arguments, &game_session, &clock, players);
+    }
+}
+
+// A game lost cannot be resumed any more
+fn delete_on_game_over(save_file: Res<SaveFile>) {
+    delete(&save_file.0);
+}
+
+// Removes the saved game, once resumed or over, so that it is only played once
+pub fn delete(path: &Path) {
+    if let Err(error) = std::fs::remove_file(path)
+        && error.kind() != std::io::ErrorKind::NotFound
+    {
+        eprintln!(
+            "Cannot delete the saved game '{0}': {1}",
+            path.display(),
+            error
+        );
+    }
+}
+
+fn save(
+    save_file: &SaveFile,
+    arguments: &CommandLineArguments,
+    game_session: &GameSession,
+    clock: &SimulationClock,
+    players: SavedPlayers,
+) {
+    let mut players: Vec<_> = players.iter().collect();
+    players.sort_by_key(|(player, ..)| player.id);
+
+    let saved_game = SavedGame {
+        version: SAVE_VERSION,
+        mode: arguments.get_mode(),
+        level: arguments.level,
+        level_progression: arguments.level_progression.clone(),
+        seed: game_session.current_seed.clone(),
+        tick: clock.tick,
+        players: players
+            .into_iter()
+            .map(
+                |(_, game_board, game_settings, rng, garbage_rng)| SavedPlayer {
+                    game_board: game_board.clone(),
+                    game_settings: game_settings.clone(),
+                    rng: rng.clone(),
+                    garbage_rng: garbage_rng.0.clone(),
+                },
+            )
+            .collect(),
+    };
+
+    let path = &save_file.0;
+    let result = serde_json::to_string(&saved_game)
+        .map_err(|error| error.to_string())
+        .and_then(|content| {
+            if let Some(directory) = path.parent() {
+                std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+            }
+            std::fs::write(path, content).map_err(|error| error.to_string())
+        });
+    match result {
+        Ok(_) => println!("Game saved to {0}", path.display()),
+        Err(error) => eprintln!("Cannot save the game to '{0}': {1}", path.display(), error),
+    }
+}
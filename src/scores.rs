@@ -0,0 +1,334 @@
+use crate::cli::CommandLineArguments;
+use crate::consts::*;
+use crate::controls::ControlsScreen;
+use crate::ecs::*;
+use crate::seed;
+use crate::storage;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const HIGH_SCORES_VERSION: u32 = 1;
+const HIGH_SCORES_PER_MODE: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub date: String,
+    pub score: u32,
+    pub level: u16,
+    pub lines: u32,
+    pub seconds: u64, // Time played
+    pub seed: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HighScoresFile {
+    version: u32,
+    last_name: String,
+    tables: BTreeMap<String, Vec<HighScore>>, // Best scores first, by mode
+}
+
+// Top scores of every mode, kept in the data directory
+#[derive(Resource)]
+pub struct HighScores {
+    path: PathBuf,
+    file: HighScoresFile,
+    recorded: bool, // Whether the score of the game just over has been handled
+}
+
+impl HighScores {
+    // A missing file starts an empty table, a corrupted one is set aside and replaced
+    pub fn load() -> Self {
+        let path = storage::get_data_dir().join("highscores.json");
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<HighScoresFile>(&content) {
+                Ok(file) if file.version == HIGH_SCORES_VERSION => file,
+                _ => {
+                    let backup = path.with_extension("json.corrupted");
+                    eprintln!(
+                        "Invalid high scores '{0}', moved to '{1}'",
+                        path.display(),
+                        backup.display()
+                    );
+                    let _ = std::fs::rename(&path, &backup);
+                    HighScoresFile::default()
+                }
+            },
+            Err(_) => HighScoresFile::default(),
+        };
+
+        Self {
+            path,
+            file: HighScoresFile {
+                version: HIGH_SCORES_VERSION,
+                ..file
+            },
+            recorded: false,
+        }
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(&self.file)
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                if let Some(directory) = self.path.parent() {
+                    std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+                }
+                std::fs::write(&self.path, content).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            eprintln!(
+                "Cannot save the high scores to '{0}': {1}",
+                self.path.display(),
+                error
+            );
+        }
+    }
+
+    pub fn get_table(&self, mode: &str) -> &[HighScore] {
+        self.file
+            .tables
+            .get(mode)
+            .map(|table| table.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn is_high_score(&self, mode: &str, score: u32) -> bool {
+        let table = self.get_table(mode);
+        score > 0
+            && (table.len() < HIGH_SCORES_PER_MODE
+                || table.last().is_some_and(|lowest| score > lowest.score))
+    }
+
+    fn add(&mut self, mode: &str, high_score: HighScore) {
+        self.file.last_name = high_score.name.clone();
+
+        let table = self.file.tables.entry(mode.to_string()).or_default();
+        let position = table
+            .iter()
+            .position(|other| high_score.score > other.score)
+            .unwrap_or(table.len());
+        table.insert(position, high_score);
+        table.truncate(HIGH_SCORES_PER_MODE);
+
+        self.save();
+    }
+}
+
+// Score waiting for the name of the player before entering the table
+#[derive(Resource)]
+pub struct NameEntry {
+    name: String,
+    high_score: HighScore,
+}
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .add_systems(Startup, setup_high_scores_text)
+            .add_systems(OnEnter(GameStatus::Running), reset_recorded_score)
+            .add_systems(OnEnter(GameStatus::GameOver), record_score)
+            .add_systems(
+                Update,
+                (
+                    enter_name.run_if(resource_exists::<NameEntry>),
+                    toggle_high_scores.run_if(
+                        not(resource_exists::<NameEntry>)
+                            .and(not(resource_exists::<ControlsScreen>)),
+                    ),
+                    update_high_scores_text,
+                ),
+            );
+    }
+}
+
+fn get_text_font(asset_server: &AssetServer, font_size: f32) -> TextFont {
+    TextFont {
+        font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+        font_size,
+        ..default()
+    }
+}
+
+fn setup_high_scores_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text2d::new(""),
+        get_text_font(&asset_server, 16.0),
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::TopLeft,
+        Transform::from_translation(Vec3::new(-620.00, 300.00, 0.0)),
+        Visibility::Hidden,
+        HighScoresText,
+    ));
+}
+
+// A new game hides the table shown when the previous one was over
+fn reset_recorded_score(
+    mut high_scores: ResMut<HighScores>,
+    mut high_scores_text: Single<&mut Visibility, With<HighScoresText>>,
+) {
+    if high_scores.recorded {
+        high_scores.recorded = false;
+        **high_scores_text = Visibility::Hidden;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_score(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut high_scores: ResMut<HighScores>,
+    arguments: Res<CommandLineArguments>,
+    game_session: Res<GameSession>,
+    clock: Res<SimulationClock>,
+    game_settings: Single<&GameSettings>,
+    mut high_scores_text: Single<&mut Visibility, With<HighScoresText>>,
+) {
+    // Resuming from the pause does not record the score again
+    if high_scores.recorded {
+        return;
+    }
+    high_scores.recorded = true;
+
+    **high_scores_text = Visibility::Inherited;
+
+    let mode = arguments.get_mode().to_string();
+    if game_session.assisted || !high_scores.is_high_score(&mode, game_settings.game_score.score) {
+        return;
+    }
+
+    let high_score = HighScore {
+        name: String::new(),
+        date: seed::get_today(),
+        score: game_settings.game_score.score,
+        level: game_settings.game_score.level,
+        lines: game_settings.game_score.filled_up_lines,
+        seconds: (clock.tick as f64 * clock.step.as_secs_f64()) as u64,
+        seed: game_session.current_seed.clone(),
+    };
+
+    let name = match high_scores.file.last_name.as_str() {
+        "" => std::env::var("USER").unwrap_or_default(),
+        last_name => last_name.to_string(),
+    };
+    let name: String = name.chars().take(MAX_NAME_LENGTH).collect();
+
+    commands.spawn((
+        Text2d::new(get_name_entry_text(&name)),
+        get_text_font(&asset_server, 30.0),
+        Anchor::Center,
+        TextColor(GREEN),
+        Transform::from_translation(Vec3::new(0.00, -90.00, 10.0)),
+        NameEntryText,
+    ));
+    commands.insert_resource(NameEntry { name, high_score });
+}
+
+fn get_name_entry_text(name: &str) -> String {
+    format!("New high score! Name: {0}_", name)
+}
+
+fn enter_name(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut name_entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+    arguments: Res<CommandLineArguments>,
+    name_entry_text: Single<(Entity, &mut Text2d), With<NameEntryText>>,
+) {
+    let (entity, mut text) = name_entry_text.into_inner();
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => {
+                let mut high_score = name_entry.high_score.clone();
+                high_score.name = match name_entry.name.trim() {
+                    "" => String::from("Player"),
+                    name => name.to_string(),
+                };
+                high_scores.add(&arguments.get_mode().to_string(), high_score);
+
+                commands.entity(entity).despawn();
+                commands.remove_resource::<NameEntry>();
+                return;
+            }
+            Key::Backspace => {
+                name_entry.name.pop();
+            }
+            Key::Character(characters) => {
+                for character in characters.chars() {
+                    if (character.is_alphanumeric() || character == ' ')
+                        && name_entry.name.chars().count() < MAX_NAME_LENGTH
+                    {
+                        name_entry.name.push(character);
+                    }
+                }
+            }
+            Key::Space if name_entry.name.chars().count() < MAX_NAME_LENGTH => {
+                name_entry.name.push(' ');
+            }
+            _ => {}
+        }
+    }
+
+    if name_entry.is_changed() {
+        text.0 = get_name_entry_text(&name_entry.name);
+    }
+}
+
+fn toggle_high_scores(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut high_scores_text: Single<&mut Visibility, With<HighScoresText>>,
+) {
+    if keys.just_pressed(KeyCode::KeyH) {
+        **high_scores_text = match **high_scores_text {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_high_scores_text(
+    high_scores: Res<HighScores>,
+    arguments: Res<CommandLineArguments>,
+    mut high_scores_text: Single<&mut Text2d, With<HighScoresText>>,
+) {
+    if !high_scores.is_changed() {
+        return;
+    }
+
+    let mode = arguments.get_mode().to_string();
+    let mut text = format!("High scores - {0}\n", mode);
+    let table = high_scores.get_table(&mode);
+    if table.is_empty() {
+        text.push_str("No scores yet");
+    }
+    for (rank, high_score) in table.iter().enumerate() {
+        text.push_str(&format!(
+            "{0}. {1}  {2}\n    level {3}, {4} lines, {5}:{6:02}, {7}, seed {8}\n",
+            rank + 1,
+            high_score.name,
+            high_score.score,
+            high_score.level,
+            high_score.lines,
+            high_score.seconds / 60,
+            high_score.seconds % 60,
+            high_score.date,
+            high_score.seed
+        ));
+    }
+
+    high_scores_text.0 = text;
+}
@@ -0,0 +1,82 @@
+use bevy_prng::ChaCha8Rng;
+use rand::{Rng, SeedableRng};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Games are seeded by a name that can be shared, such as a number, a word or a date. The name
+// can also be the seed itself, written as 64 hexadecimal digits, two for each byte
+pub fn get_seed(name: &str) -> [u8; 32] {
+    if let Ok(seed) = parse_seed(name) {
+        return seed;
+    }
+
+    ChaCha8Rng::seed_from_u64(get_fnv_hash(name.bytes())).random()
+}
+
+// FNV-1a hash of some bytes, stable across platforms and versions unlike the standard hasher
+pub fn get_fnv_hash(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes
+        .into_iter()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+// Gets the seed of the garbage holes of a game, different from the seed of its tetrominoes
+pub fn get_garbage_seed(seed: [u8; 32]) -> [u8; 32] {
+    seed.map(|byte| !byte)
+}
+
+pub fn get_random_seed_name<R: Rng>(rng: &mut R) -> String {
+    rng.random::<u32>().to_string()
+}
+
+// Everyone playing the daily challenge on the same day gets the same seed, such as 2025-06-21
+pub fn get_daily_seed_name() -> String {
+    get_today()
+}
+
+// Gets the current date (UTC), such as 2025-06-21
+pub fn get_today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECONDS_PER_DAY;
+    let (year, month, day) = get_date_from_days(days as i64);
+    format!("{0:04}-{1:02}-{2:02}", year, month, day)
+}
+
+// Converts the days since 1970-01-01 to the gregorian calendar date (UTC)
+fn get_date_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153; // Months starting from March
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn parse_seed(value: &str) -> Result<[u8; 32], String> {
+    let mut seed = [0; 32];
+    if value.len() != seed.len() * 2 || !value.is_ascii() {
+        return Err(format!("Invalid seed '{0}'", value));
+    }
+
+    for (index, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[index * 2..index * 2 + 2], 16)
+            .map_err(|_| format!("Invalid seed '{0}'", value))?;
+    }
+
+    Ok(seed)
+}
@@ -0,0 +1,340 @@
+use crate::consts::*;
+use crate::controls::ControlsSettings;
+use crate::ecs::*;
+use crate::game;
+use crate::game::scoring::GameplaySettings;
+use crate::game::tetromino::TetrominoType;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// How often the settings file is checked for changes
+const SETTINGS_CHECK_INTERVAL_MS: u64 = 1000;
+
+// Linear red, green and blue between 0 and 1
+pub type Rgb = [f32; 3];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TetrominoColors {
+    pub fill: Rgb,
+    pub outline: Rgb,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplaySettings {
+    pub square_size: f32, // The whole view is zoomed to get squares of this size
+    pub border: Rgb,
+    pub occupied: Rgb,
+    pub i: TetrominoColors,
+    pub o: TetrominoColors,
+    pub t: TetrominoColors,
+    pub j: TetrominoColors,
+    pub l: TetrominoColors,
+    pub s: TetrominoColors,
+    pub z: TetrominoColors,
+}
+
+// Tunables loaded from the settings file, the file can leave out any value to keep its default
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub gameplay: GameplaySettings,
+    pub display: DisplaySettings,
+    pub controls: ControlsSettings,
+}
+
+fn get_rgb(color: Color) -> Rgb {
+    let color = color.to_linear();
+    [color.red, color.green, color.blue]
+}
+
+pub fn get_color(rgb: &Rgb) -> Color {
+    Color::linear_rgb(rgb[0], rgb[1], rgb[2])
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        let colors = |fill: Color, outline: Color| TetrominoColors {
+            fill: get_rgb(fill),
+            outline: get_rgb(outline),
+        };
+
+        Self {
+            square_size: SQUARE_SIZE,
+            border: get_rgb(GRAY),
+            occupied: get_rgb(DARK_GRAY),
+            i: colors(PINK, RED),
+            o: colors(GREEN, DARK_GREEN),
+            t: colors(YELLOW, ORANGE),
+            j: colors(BLUE, DARK_BLUE),
+            l: colors(VIOLET, BLUE),
+            s: colors(ORANGE, YELLOW),
+            z: colors(RED, PINK),
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn get_tetromino_colors(&self, tetromino_type: &TetrominoType) -> &TetrominoColors {
+        match tetromino_type {
+            TetrominoType::I => &self.i,
+            TetrominoType::O => &self.o,
+            TetrominoType::T => &self.t,
+            TetrominoType::J => &self.j,
+            TetrominoType::L => &self.l,
+            TetrominoType::S => &self.s,
+            TetrominoType::Z => &self.z,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(5.0..=100.0).contains(&self.square_size) {
+            return Err(format!(
+                "display.square_size ({0}) must be between 5 and 100",
+                self.square_size
+            ));
+        }
+
+        let mut colors = vec![("border", &self.border), ("occupied", &self.occupied)];
+        for (name, tetromino_colors) in [
+            ("i", &self.i),
+            ("o", &self.o),
+            ("t", &self.t),
+            ("j", &self.j),
+            ("l", &self.l),
+            ("s", &self.s),
+            ("z", &self.z),
+        ] {
+            colors.push((name, &tetromino_colors.fill));
+            colors.push((name, &tetromino_colors.outline));
+        }
+
+        for (name, color) in colors {
+            if color
+                .iter()
+                .any(|component| !(0.0..=1.0).contains(component))
+            {
+                return Err(format!(
+                    "display.{0} has the color {1:?}, its components must be between 0 and 1",
+                    name, color
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Settings {
+    // A missing file keeps the defaults
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(error) => {
+                return Err(format!(
+                    "Cannot read the settings '{0}': {1}",
+                    path.display(),
+                    error
+                ));
+            }
+        };
+
+        let mut settings: Self = ron::from_str(&content)
+            .map_err(|error| format!("Invalid settings '{0}': {1}", path.display(), error))?;
+        settings.controls.fill_missing_actions();
+        settings
+            .gameplay
+            .validate()
+            .and_then(|_| settings.display.validate())
+            .and_then(|_| settings.controls.validate())
+            .map_err(|error| format!("Invalid settings '{0}': {1}", path.display(), error))?;
+
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                if let Some(directory) = path.parent() {
+                    std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+                }
+                std::fs::write(path, content).map_err(|error| error.to_string())
+            })
+            .map_err(|error| {
+                format!(
+                    "Cannot save the settings to '{0}': {1}",
+                    path.display(),
+                    error
+                )
+            })
+    }
+}
+
+// Watches the settings file, applying its changes while playing
+#[derive(Resource)]
+pub struct SettingsWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    timer: Timer,
+    fixed_gameplay: bool, // Networked, replayed and recorded games keep the gameplay they started with
+}
+
+impl SettingsWatcher {
+    pub fn new(path: PathBuf, fixed_gameplay: bool) -> Self {
+        Self {
+            modified: get_modified_time(&path),
+            path,
+            timer: Timer::new(
+                Duration::from_millis(SETTINGS_CHECK_INTERVAL_MS),
+                TimerMode::Repeating,
+            ),
+            fixed_gameplay,
+        }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, reload_settings).add_systems(
+            PostUpdate,
+            (zoom_camera, update_timers, recolor_cells).run_if(resource_changed::<Settings>),
+        );
+    }
+}
+
+fn reload_settings(
+    time: Res<Time<Real>>,
+    mut watcher: ResMut<SettingsWatcher>,
+    mut settings: ResMut<Settings>,
+) {
+    watcher.timer.tick(time.delta());
+    if !watcher.timer.just_finished() {
+        return;
+    }
+
+    let modified = get_modified_time(&watcher.path);
+    if modified == watcher.modified {
+        return;
+    }
+    watcher.modified = modified;
+
+    // Invalid changes are reported and ignored, the game goes on with the previous settings
+    match Settings::load(&watcher.path) {
+        Ok(mut loaded) => {
+            if watcher.fixed_gameplay {
+                loaded.gameplay = settings.gameplay.clone();
+            }
+            if *settings != loaded {
+                *settings = loaded;
+                info!("Settings reloaded from {0}", watcher.path.display());
+            }
+        }
+        Err(error) => error!("{0}", error),
+    }
+}
+
+fn zoom_camera(settings: Res<Settings>, mut projections: Query<&mut Projection, With<Camera2d>>) {
+    for mut projection in &mut projections {
+        if let Projection::Orthographic(orthographic) = &mut *projection {
+            orthographic.scale = SQUARE_SIZE / settings.display.square_size;
+        }
+    }
+}
+
+fn update_timers(
+    settings: Res<Settings>,
+    mut players: Query<(&mut GameSettings, &PlayerHud)>,
+    mut texts: Query<&mut Text2d>,
+) {
+    for (mut game_settings, hud) in &mut players {
+        let drop_down_ms = settings
+            .gameplay
+            .get_drop_down_ms(game_settings.game_score.level);
+        game_settings
+            .descend_timer
+            .set_duration(Duration::from_millis(drop_down_ms));
+        game_settings
+            .remove_filled_cells_times
+            .set_duration(Duration::from_millis(
+                settings.gameplay.clean_up_occupied_rows_ms,
+            ));
+        crate::update_text_and_scores(hud, &game_settings, &mut texts, &settings.gameplay);
+    }
+}
+
+// Changed colors apply to the cells already on the screen
+#[allow(clippy::type_complexity)]
+fn recolor_cells(
+    settings: Res<Settings>,
+    players: Query<(&game::GameBoard, &Children)>,
+    cells: Query<(
+        &MeshMaterial2d<ColorMaterial>,
+        &Transform,
+        Has<TetrominoCell>,
+        Has<UpcomingTetrominoCell>,
+        Has<HeldTetrominoCell>,
+        Has<OccupiedCell>,
+        Has<BorderCell>,
+    )>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let display = &settings.display;
+    for (game_board, children) in players {
+        for child in children.iter() {
+            let Ok((material, transform, tetromino, upcoming, held, occupied, border)) =
+                cells.get(child)
+            else {
+                continue;
+            };
+
+            let color = if tetromino {
+                display
+                    .get_tetromino_colors(game_board.get_current_tetromino_type())
+                    .fill
+            } else if upcoming {
+                display
+                    .get_tetromino_colors(game_board.get_upcoming_tetromino_type())
+                    .fill
+            } else if held {
+                let Some(held_type) = game_board.get_held_tetromino_type() else {
+                    continue;
+                };
+                display.get_tetromino_colors(held_type).fill
+            } else if occupied {
+                // Cells painted or imported with the color of a tetromino keep it
+                let tetromino = crate::get_board_cell_by_position(transform.translation.truncate())
+                    .and_then(|cell| game::get_cell_tetromino(game_board.get_cell(cell)));
+                match tetromino {
+                    Some(tetromino) => display.get_tetromino_colors(&tetromino).fill,
+                    None => display.occupied,
+                }
+            } else if border {
+                display.border
+            } else {
+                continue;
+            };
+
+            if let Some(material) = materials.get_mut(&material.0) {
+                material.color = get_color(&color);
+            }
+        }
+    }
+}
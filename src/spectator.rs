@@ -0,0 +1,570 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game;
+use crate::game::level::LevelProgression;
+use crate::game::tetromino::{Tetromino, TetrominoType};
+use crate::settings::{DisplaySettings, Settings, get_color};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Time after which a spectator too slow to read the feed gets dropped
+const SPECTATOR_WRITE_TIMEOUT_MS: u64 = 100;
+
+// Read-only feed of the game, one JSON message per line, such as:
+//
+//   {"type":"snapshot","player":0,"players":1,"board":["..........",...],
+//    "current":{"tetromino":"T","cells":[4,13,14,15]},"next":[{"tetromino":"I","cells":[...]}],
+//    "hold":{"tetromino":"O","cells":[...]},"score":120,"level":1,"lines":1,"incoming_garbage":0,"status":"playing"}
+//   {"type":"lock","player":0,"cleared_lines":1,"t_spin":false,"perfect_clear":false,
+//    "attack_lines":0,"topped_out":false}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SpectatorMessage {
+    Snapshot(BoardSnapshot),
+    Lock(LockEvent),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoardSnapshot {
+    player: u8,
+    players: u8,
+    board: Vec<String>, // Rows from the top: '.' is empty, '#' locked and 'X' garbage
+    current: PieceSnapshot,
+    next: Vec<PieceSnapshot>,
+    #[serde(default)]
+    hold: Option<PieceSnapshot>,
+    score: u32,
+    level: u16,
+    lines: u32,
+    incoming_garbage: u8,
+    status: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct PieceSnapshot {
+    tetromino: String,
+    cells: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LockEvent {
+    player: u8,
+    cleared_lines: u8,
+    t_spin: bool,
+    perfect_clear: bool,
+    attack_lines: u8,
+    topped_out: bool,
+}
+
+// Broadcasts the game to the spectators connected to the address
+pub struct SpectatorServerPlugin;
+
+// The lines are written by a thread of their own, so a slow spectator does not stall the game
+#[derive(Resource)]
+pub struct SpectatorServer {
+    sender: Sender<FeedLine>,
+    snapshots: BTreeMap<u8, String>, // Latest snapshot sent of every board
+}
+
+#[derive(Default)]
+struct Spectators {
+    streams: Vec<TcpStream>,
+    snapshots: BTreeMap<u8, String>, // Latest snapshot of every board, sent to whoever connects
+}
+
+struct FeedLine {
+    line: String,
+    snapshot_of: Option<u8>, // Board of the snapshot, kept for the spectators connecting later
+}
+
+// Renders the game broadcast by another instance
+pub struct SpectatorClientPlugin;
+
+#[derive(Resource)]
+pub struct SpectatorClient {
+    receiver: Mutex<Receiver<Result<SpectatorMessage, String>>>,
+    boards: BTreeMap<u8, Entity>,
+}
+
+#[derive(Component)]
+struct SpectatedPieces {
+    current: TetrominoType,
+    next: Option<TetrominoType>,
+    hold: Option<TetrominoType>,
+}
+
+impl Plugin for SpectatorServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (broadcast_lock_events, broadcast_snapshots).chain());
+    }
+}
+
+impl Plugin for SpectatorClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_client)
+            .add_systems(Update, receive_snapshots)
+            .add_systems(Update, crate::paint_board_border_outline)
+            .add_systems(Update, crate::paint_occupied_cells_outline)
+            .add_systems(Update, paint_spectated_pieces_outline);
+    }
+}
+
+impl Spectators {
+    fn broadcast(&mut self, line: &str) {
+        self.streams
+            .retain_mut(|stream| writeln!(stream, "{0}", line).is_ok());
+    }
+}
+
+impl SpectatorServer {
+    pub fn start(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|error| format!("Cannot serve spectators on '{0}': {1}", address, error))?;
+        println!("Spectators can watch on {0}", address);
+
+        let spectators = Arc::new(Mutex::new(Spectators::default()));
+        let accepted_spectators = spectators.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_nodelay(true);
+                let _ = stream
+                    .set_write_timeout(Some(Duration::from_millis(SPECTATOR_WRITE_TIMEOUT_MS)));
+
+                let Ok(mut spectators) = accepted_spectators.lock() else {
+                    return;
+                };
+
+                let mut stream = stream;
+                let snapshots: Vec<String> = spectators.snapshots.values().cloned().collect();
+                if snapshots
+                    .iter()
+                    .all(|snapshot| writeln!(stream, "{0}", snapshot).is_ok())
+                {
+                    spectators.streams.push(stream);
+                }
+            }
+        });
+
+        let (sender, receiver) = mpsc::channel::<FeedLine>();
+        std::thread::spawn(move || {
+            for feed_line in receiver {
+                let Ok(mut spectators) = spectators.lock() else {
+                    return;
+                };
+
+                spectators.broadcast(&feed_line.line);
+                if let Some(player) = feed_line.snapshot_of {
+                    spectators.snapshots.insert(player, feed_line.line);
+                }
+            }
+        });
+
+        Ok(Self {
+            sender,
+            snapshots: BTreeMap::new(),
+        })
+    }
+}
+
+impl SpectatorClient {
+    pub fn connect(address: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|error| format!("Cannot spectate '{0}': {1}", address, error))?;
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let message = line
+                    .map_err(|error| format!("Cannot receive the game: {0}", error))
+                    .and_then(|line| {
+                        serde_json::from_str::<SpectatorMessage>(&line)
+                            .map_err(|error| format!("Invalid message '{0}': {1}", line, error))
+                    });
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+
+            let _ = sender.send(Err(String::from("The game is over")));
+        });
+
+        Ok(Self {
+            receiver: Mutex::new(receiver),
+            boards: BTreeMap::new(),
+        })
+    }
+}
+
+fn get_board_status_name(board_status: &BoardStatus) -> &'static str {
+    match board_status {
+        BoardStatus::Playing => "playing",
+        BoardStatus::RemovingFilledRows => "removing_filled_rows",
+        BoardStatus::ToppedOut => "topped_out",
+    }
+}
+
+pub fn get_board_snapshot(
+    player: &Player,
+    players: u8,
+    game_board: &game::GameBoard,
+    game_settings: &GameSettings,
+) -> BoardSnapshot {
+    let board = (0..game::NUMBER_OF_ROWS)
+        .map(|row| {
+            (0..game::NUMBER_OF_COLUMNS)
+                .map(|col| {
+                    match game_board.get_cell(Tetromino::get_cell_from_row_and_column(row, col)) {
+                        0 => '.',
+                        game::GARBAGE_CELL => 'X',
+                        _ => '#',
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    BoardSnapshot {
+        player: player.id,
+        players,
+        board,
+        current: PieceSnapshot {
+            tetromino: game_board.get_current_tetromino_type().to_string(),
+            cells: game_board.get_current_tetromino_cells(),
+        },
+        next: vec![PieceSnapshot {
+            tetromino: game_board.get_upcoming_tetromino_type().to_string(),
+            cells: game_board.get_upcoming_tetromino_cells(),
+        }],
+        hold: game_board
+            .get_held_tetromino_type()
+            .map(|held_type| PieceSnapshot {
+                tetromino: held_type.to_string(),
+                cells: game_board.get_held_tetromino_cells(),
+            }),
+        score: game_settings.game_score.score,
+        level: game_settings.game_score.level,
+        lines: game_settings.game_score.filled_up_lines,
+        incoming_garbage: game_board.get_incoming_garbage(),
+        status: String::from(get_board_status_name(&game_settings.board_status)),
+    }
+}
+
+fn broadcast_lock_events(
+    server: Res<SpectatorServer>,
+    mut locked_events: EventReader<TetrominoLocked>,
+    players: Query<&Player>,
+) {
+    for event in locked_events.read() {
+        let Ok(player) = players.get(event.player) else {
+            continue;
+        };
+
+        let message = SpectatorMessage::Lock(LockEvent {
+            player: player.id,
+            cleared_lines: event.outcome.cleared_lines,
+            t_spin: event.outcome.t_spin,
+            perfect_clear: event.outcome.perfect_clear,
+            attack_lines: event.attack_lines,
+            topped_out: event.outcome.topped_out,
+        });
+
+        if let Ok(line) = serde_json::to_string(&message) {
+            let _ = server.sender.send(FeedLine {
+                line,
+                snapshot_of: None,
+            });
+        }
+    }
+}
+
+// Sends the boards which changed since their last snapshot
+fn broadcast_snapshots(
+    mut server: ResMut<SpectatorServer>,
+    players: Query<(&Player, &game::GameBoard, &GameSettings)>,
+) {
+    let number_of_players = players.iter().len() as u8;
+    for (player, game_board, game_settings) in players {
+        let snapshot = get_board_snapshot(player, number_of_players, game_board, game_settings);
+        let Ok(line) = serde_json::to_string(&SpectatorMessage::Snapshot(snapshot)) else {
+            continue;
+        };
+
+        if server.snapshots.get(&player.id) != Some(&line) {
+            server.snapshots.insert(player.id, line.clone());
+            let _ = server.sender.send(FeedLine {
+                line,
+                snapshot_of: Some(player.id),
+            });
+        }
+    }
+}
+
+fn setup_client(mut commands: Commands, mut config_store: ResMut<GizmoConfigStore>) {
+    commands.spawn(Camera2d);
+
+    let (config, _) = config_store.config_mut::<DefaultGizmoConfigGroup>();
+    config.line.width = 3.0;
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn receive_snapshots(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut client: ResMut<SpectatorClient>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<(&PlayerHud, &Children)>,
+    cells: Query<
+        Entity,
+        Or<(
+            With<TetrominoCell>,
+            With<OccupiedCell>,
+            With<UpcomingTetrominoCell>,
+            With<HeldTetrominoCell>,
+        )>,
+    >,
+    mut texts: Query<&mut Text2d>,
+    network_status_text: Query<Entity, With<NetworkStatusText>>,
+    settings: Res<Settings>,
+) {
+    let messages: Vec<Result<SpectatorMessage, String>> = match client.receiver.lock() {
+        Ok(receiver) => receiver.try_iter().collect(),
+        Err(_) => return,
+    };
+
+    // Only the latest snapshot of every board matters
+    let mut snapshots = BTreeMap::new();
+    for message in messages {
+        match message {
+            Ok(SpectatorMessage::Snapshot(snapshot)) => {
+                snapshots.insert(snapshot.player, snapshot);
+            }
+            Ok(SpectatorMessage::Lock(event)) => {
+                info!(
+                    "Player {0} locked a tetromino: {1:?}",
+                    event.player + 1,
+                    event
+                );
+            }
+            Err(error) => {
+                warn!("{0}", error);
+                if network_status_text.is_empty() {
+                    commands.spawn((
+                        Text2d::new("Disconnected"),
+                        TextFont {
+                            font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+                            font_size: 60.0,
+                            ..default()
+                        },
+                        Anchor::Center,
+                        TextColor(RED),
+                        Transform::from_translation(Vec3::new(0.00, -100.00, 10.0)),
+                        NetworkStatusText,
+                    ));
+                }
+            }
+        }
+    }
+
+    let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+    for (id, snapshot) in snapshots {
+        let mut game_settings =
+            GameSettings::new(snapshot.level, LevelProgression::Fixed, &settings.gameplay);
+        game_settings.game_score.score = snapshot.score;
+        game_settings.game_score.filled_up_lines = snapshot.lines;
+
+        let player = match client.boards.get(&id) {
+            Some(player) => {
+                if let Ok((hud, children)) = players.get(*player) {
+                    crate::update_text_and_scores(
+                        hud,
+                        &game_settings,
+                        &mut texts,
+                        &settings.gameplay,
+                    );
+                    for child in children.iter() {
+                        if cells.contains(child) {
+                            commands.entity(child).despawn();
+                        }
+                    }
+                }
+
+                *player
+            }
+            None => {
+                let offset = if snapshot.players > 1 {
+                    VERSUS_BOARD_OFFSETS[id as usize % VERSUS_BOARD_OFFSETS.len()]
+                } else {
+                    0.0
+                };
+
+                let player = commands
+                    .spawn((
+                        Player { id },
+                        Transform::from_xyz(offset, 0.0, 0.0),
+                        Visibility::default(),
+                    ))
+                    .id();
+
+                crate::spawn_board_border(
+                    &mut commands,
+                    player,
+                    &mut materials,
+                    shape.clone(),
+                    &settings.display,
+                );
+                let label = (snapshot.players > 1).then(|| format!("Player {0}", id + 1));
+                let hud = crate::setup_text_and_scores(
+                    &mut commands,
+                    player,
+                    asset_server.load("fonts/NovaSquare-Regular.ttf"),
+                    &game_settings,
+                    label,
+                    &settings.gameplay,
+                );
+                commands.entity(player).insert(hud);
+                client.boards.insert(id, player);
+                player
+            }
+        };
+
+        spawn_snapshot_cells(
+            &mut commands,
+            player,
+            &snapshot,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+    }
+}
+
+fn spawn_snapshot_cells(
+    commands: &mut Commands,
+    player: Entity,
+    snapshot: &BoardSnapshot,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    shape: Handle<Mesh>,
+    display: &DisplaySettings,
+) {
+    for (row, cells) in snapshot.board.iter().enumerate() {
+        for (col, cell) in cells.chars().enumerate() {
+            if cell == '.' {
+                continue;
+            }
+
+            commands.spawn((
+                OccupiedCell,
+                Mesh2d(shape.clone()),
+                MeshMaterial2d(materials.add(get_color(&display.occupied))),
+                crate::get_transform_by_board_cell(Tetromino::get_cell_from_row_and_column(
+                    row as u8, col as u8,
+                )),
+                ChildOf(player),
+            ));
+        }
+    }
+
+    let Ok(current) = snapshot.current.tetromino.parse::<TetrominoType>() else {
+        return;
+    };
+
+    if snapshot.status != get_board_status_name(&BoardStatus::RemovingFilledRows) {
+        let color = crate::get_tetromino_color_by_type(&current, display);
+        for cell in &snapshot.current.cells {
+            commands.spawn((
+                TetrominoCell,
+                Mesh2d(shape.clone()),
+                MeshMaterial2d(materials.add(color)),
+                crate::get_transform_by_board_cell(*cell),
+                ChildOf(player),
+            ));
+        }
+    }
+
+    let next = snapshot
+        .next
+        .first()
+        .and_then(|piece| Some((piece, piece.tetromino.parse::<TetrominoType>().ok()?)));
+    if let Some((piece, next_type)) = &next {
+        let color = crate::get_tetromino_color_by_type(next_type, display);
+        for cell in &piece.cells {
+            commands.spawn((
+                UpcomingTetrominoCell,
+                Mesh2d(shape.clone()),
+                MeshMaterial2d(materials.add(color)),
+                crate::get_upcoming_tetromino_position_for_cell(*cell),
+                ChildOf(player),
+            ));
+        }
+    }
+
+    let hold = snapshot
+        .hold
+        .as_ref()
+        .and_then(|piece| Some((piece, piece.tetromino.parse::<TetrominoType>().ok()?)));
+    if let Some((piece, held_type)) = &hold {
+        // Every mino of a big tetromino covers 4 cells
+        let scale = (piece.cells.len() / 4).isqrt() as u8;
+        let color = crate::get_tetromino_color_by_type(held_type, display);
+        for cell in &piece.cells {
+            commands.spawn((
+                HeldTetrominoCell,
+                Mesh2d(shape.clone()),
+                MeshMaterial2d(materials.add(color)),
+                crate::get_held_tetromino_position_for_cell(*cell, scale),
+                ChildOf(player),
+            ));
+        }
+    }
+
+    commands.entity(player).insert(SpectatedPieces {
+        current,
+        next: next.map(|(_, next_type)| next_type),
+        hold: hold.map(|(_, held_type)| held_type),
+    });
+}
+
+fn paint_spectated_pieces_outline(
+    players: Query<(&Transform, &SpectatedPieces, &Children)>,
+    tetromino_cells: Query<&Transform, With<TetrominoCell>>,
+    upcoming_cells: Query<&Transform, With<UpcomingTetrominoCell>>,
+    held_cells: Query<&Transform, With<HeldTetrominoCell>>,
+    mut gizmos: Gizmos,
+    settings: Res<Settings>,
+) {
+    for (player_transform, pieces, children) in players {
+        let color = crate::get_tetromino_outline_color_by_type(&pieces.current, &settings.display);
+        crate::paint_cells_outline(
+            player_transform,
+            children,
+            &tetromino_cells,
+            &mut gizmos,
+            color,
+        );
+
+        if let Some(next) = &pieces.next {
+            let color = crate::get_tetromino_outline_color_by_type(next, &settings.display);
+            crate::paint_cells_outline(
+                player_transform,
+                children,
+                &upcoming_cells,
+                &mut gizmos,
+                color,
+            );
+        }
+
+        if let Some(hold) = &pieces.hold {
+            let color = crate::get_tetromino_outline_color_by_type(hold, &settings.display);
+            crate::paint_cells_outline(player_transform, children, &held_cells, &mut gizmos, color);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+// Directory of the files kept from one launch to the next: $XDG_DATA_HOME/blocchi,
+// ~/.local/share/blocchi when not set, %APPDATA%\blocchi on Windows
+pub fn get_data_dir() -> PathBuf {
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(data_home).join("blocchi");
+    }
+
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("blocchi");
+    }
+
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        return PathBuf::from(app_data).join("blocchi");
+    }
+
+    PathBuf::from(".")
+}
+
+// Directory of the files edited by the player: $XDG_CONFIG_HOME/blocchi, ~/.config/blocchi
+// when not set, %APPDATA%\blocchi on Windows
+pub fn get_config_dir() -> PathBuf {
+    if let Some(config_home) = std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(config_home).join("blocchi");
+    }
+
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".config").join("blocchi");
+    }
+
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        return PathBuf::from(app_data).join("blocchi");
+    }
+
+    PathBuf::from(".")
+}
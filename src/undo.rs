@@ -0,0 +1,288 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game;
+use crate::game::opener::OpenerProgress;
+use crate::game::puzzle::PuzzleProgress;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+use std::collections::VecDeque;
+
+const UNDO_KEY: KeyCode = KeyCode::F8;
+const REDO_KEY: KeyCode = KeyCode::F9;
+
+// Positions kept to go back to, the oldest ones being forgotten
+const HISTORY_SIZE: usize = 100;
+
+// Game of a player right after a tetromino has been locked, with the progress made by then
+#[derive(Clone)]
+struct Position {
+    game_board: game::GameBoardSnapshot,
+    game_settings: GameSettings,
+    rng: Entropy<ChaCha8Rng>,
+    // The progress is missing for the position the game starts from
+    puzzle_progress: Option<PuzzleProgress>,
+    opener_progress: Option<OpenerProgress>,
+}
+
+// Positions reached in a practice game, to take back the tetrominoes locked and play them again
+#[derive(Component, Default)]
+pub struct UndoHistory {
+    positions: VecDeque<Position>,
+    current: usize, // Index of the position played from
+    // Whether the last position recorded waits for the progress its tetromino made
+    pending_progress: bool,
+}
+
+// Takes back the last tetromino locked with F8, and plays it again with F9
+pub struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_history)
+            .add_systems(FixedUpdate, start_history.in_set(GameplaySet::Input))
+            .add_systems(
+                FixedUpdate,
+                record_progress
+                    .after(GameplaySet::Garbage)
+                    .after(crate::check_puzzle_progress),
+            )
+            .add_systems(Update, take_back);
+    }
+}
+
+impl UndoHistory {
+    // Keeps the position a tetromino has just been locked in, forgetting the ones taken back
+    pub fn record(
+        &mut self,
+        game_board: &game::GameBoard,
+        game_settings: &GameSettings,
+        rng: &Entropy<ChaCha8Rng>,
+    ) {
+        // The filled rows are already collapsed on the board taken
+        let mut game_settings = game_settings.clone();
+        if game_settings.board_status == BoardStatus::RemovingFilledRows {
+            game_settings.board_status = BoardStatus::Playing;
+            game_settings.last_despawned_cell = None;
+        }
+
+        self.positions.truncate(self.current + 1);
+        self.positions.push_back(Position {
+            game_board: game_board.snapshot(),
+            game_settings,
+            rng: rng.clone(),
+            puzzle_progress: None,
+            opener_progress: None,
+        });
+        if self.positions.len() > HISTORY_SIZE {
+            self.positions.pop_front();
+        }
+        self.current = self.positions.len() - 1;
+        self.pending_progress = true;
+    }
+
+    fn clear(&mut self) {
+        self.positions.clear();
+        self.current = 0;
+        self.pending_progress = false;
+    }
+
+    fn record_progress(
+        &mut self,
+        puzzle_progress: Option<&PuzzleProgress>,
+        opener_progress: Option<&OpenerProgress>,
+    ) {
+        if !self.pending_progress {
+            return;
+        }
+
+        if let Some(position) = self.positions.back_mut() {
+            position.puzzle_progress = puzzle_progress.cloned();
+            position.opener_progress = opener_progress.cloned();
+        }
+        self.pending_progress = false;
+    }
+
+    fn undo(&mut self) -> Option<&Position> {
+        self.current = self.current.checked_sub(1)?;
+        self.positions.get(self.current)
+    }
+
+    fn redo(&mut self) -> Option<&Position> {
+        let position = self.positions.get(self.current + 1)?;
+        self.current += 1;
+        Some(position)
+    }
+}
+
+fn setup_history(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for player in players {
+        commands.entity(player).insert(UndoHistory::default());
+    }
+}
+
+// A new game starts a new history from its first position
+fn start_history(
+    clock: Res<SimulationClock>,
+    mut players: Query<(
+        &game::GameBoard,
+        &GameSettings,
+        &Entropy<ChaCha8Rng>,
+        &mut UndoHistory,
+    )>,
+) {
+    if clock.tick != 0 {
+        return;
+    }
+
+    for (game_board, game_settings, rng, mut history) in &mut players {
+        history.clear();
+        history.record(game_board, game_settings, rng);
+        history.pending_progress = false;
+    }
+}
+
+// Completes the position of the tetromino just locked, once its placement has been checked
+fn record_progress(
+    mut locked_events: EventReader<TetrominoLocked>,
+    puzzle_progress: Option<Res<PuzzleProgress>>,
+    opener_progress: Option<Res<OpenerProgress>>,
+    mut players: Query<&mut UndoHistory>,
+) {
+    for event in locked_events.read() {
+        if let Ok(mut history) = players.get_mut(event.player) {
+            history.record_progress(puzzle_progress.as_deref(), opener_progress.as_deref());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn take_back(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameStatus>>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+    mut players: Query<(
+        Entity,
+        &mut game::GameBoard,
+        &mut GameSettings,
+        &mut Entropy<ChaCha8Rng>,
+        &mut UndoHistory,
+        &PlayerHud,
+        &Children,
+    )>,
+    cells: Query<
+        Entity,
+        Or<(
+            With<TetrominoCell>,
+            With<OccupiedCell>,
+            With<UpcomingTetrominoCell>,
+            With<HeldTetrominoCell>,
+        )>,
+    >,
+    overlay_texts: Query<Entity, Or<(With<PuzzleStatusText>, With<GameOverText>)>>,
+    mut texts: Query<&mut Text2d>,
+    mut puzzle_progress: Option<ResMut<PuzzleProgress>>,
+    mut opener_progress: Option<ResMut<OpenerProgress>>,
+) {
+    let undo = keys.just_pressed(UNDO_KEY);
+    let redo = keys.just_pressed(REDO_KEY);
+    if !undo && !redo {
+        return;
+    }
+
+    // A game over is taken back too, but not a paused game
+    if !matches!(state.get(), GameStatus::Running | GameStatus::GameOver) {
+        return;
+    }
+
+    for (player, mut game_board, mut game_settings, mut rng, mut history, hud, children) in
+        &mut players
+    {
+        // The rows being removed belong to the position already recorded
+        if game_settings.board_status == BoardStatus::RemovingFilledRows {
+            continue;
+        }
+
+        let position = match undo {
+            true => history.undo(),
+            false => history.redo(),
+        };
+        let Some(position) = position.cloned() else {
+            continue;
+        };
+
+        game_board.restore(&position.game_board);
+        *game_settings = position.game_settings;
+        *rng = position.rng;
+        if let Some(puzzle_progress) = &mut puzzle_progress {
+            match position.puzzle_progress {
+                Some(progress) => **puzzle_progress = progress,
+                None => puzzle_progress.reset(),
+            }
+        }
+        if let Some(opener_progress) = &mut opener_progress {
+            match position.opener_progress {
+                Some(progress) => **opener_progress = progress,
+                None => opener_progress.reset(),
+            }
+        }
+
+        for child in children.iter() {
+            if cells.contains(child) {
+                commands.entity(child).despawn();
+            }
+        }
+
+        let shape = meshes.add(Rectangle::new(SQUARE_SIZE, SQUARE_SIZE));
+        crate::spawn_occupied_cells(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        crate::spawn_held_tetromino(
+            &mut commands,
+            player,
+            &game_board,
+            &mut materials,
+            shape.clone(),
+            &settings.display,
+        );
+        // A topped out position has no room for its tetromino, the game is over again
+        if game_settings.board_status != BoardStatus::ToppedOut {
+            crate::do_spawn_tetromino(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape.clone(),
+                &settings.display,
+            );
+            crate::spawn_upcoming_tetromino(
+                &mut commands,
+                player,
+                &game_board,
+                &mut materials,
+                shape,
+                &settings.display,
+            );
+        }
+
+        crate::update_text_and_scores(hud, &game_settings, &mut texts, &settings.gameplay);
+
+        // The game goes on from the position, the outcome being checked again
+        if *state.get() == GameStatus::GameOver {
+            next_state.set(GameStatus::Running);
+            for entity in &overlay_texts {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::consts::*;
+use crate::ecs::*;
+use crate::game;
+use crate::game::NUMBER_OF_ROWS;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+// Countdown before every match, garbage exchange, incoming garbage bars and winner announcement of a versus game
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameStatus::Countdown), start_countdown)
+            .add_systems(Update, countdown.run_if(in_state(GameStatus::Countdown)))
+            .add_systems(PostStartup, setup_incoming_garbage_bars)
+            .add_systems(FixedUpdate, send_garbage.in_set(GameplaySet::Garbage))
+            .add_systems(Update, update_incoming_garbage_bars)
+            .add_systems(OnEnter(GameStatus::GameOver), show_winner);
+    }
+}
+
+fn get_text_font(asset_server: &AssetServer) -> TextFont {
+    TextFont {
+        font: asset_server.load("fonts/NovaSquare-Regular.ttf"),
+        font_size: 90.0,
+        ..default()
+    }
+}
+
+fn start_countdown(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_session: ResMut<GameSession>,
+    countdown_text: Query<Entity, With<CountdownText>>,
+) {
+    // Resuming from the pause keeps the countdown where it was
+    if !countdown_text.is_empty() {
+        return;
+    }
+
+    game_session.countdown_timer.reset();
+
+    commands.spawn((
+        Text2d::new(VERSUS_COUNTDOWN_SECONDS.to_string()),
+        get_text_font(&asset_server),
+        Anchor::Center,
+        TextColor(BLUE),
+        Transform::from_translation(Vec3::new(0.00, 0.00, 10.0)),
+        CountdownText,
+    ));
+}
+
+fn countdown(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut game_session: ResMut<GameSession>,
+    mut next_state: ResMut<NextState<GameStatus>>,
+    countdown_text: Single<(Entity, &mut Text2d), With<CountdownText>>,
+) {
+    let (entity, mut text) = countdown_text.into_inner();
+
+    game_session.countdown_timer.tick(time.delta());
+    if game_session.countdown_timer.finished() {
+        commands.entity(entity).despawn();
+        next_state.set(GameStatus::Running);
+    } else {
+        let remaining_seconds = game_session.countdown_timer.remaining_secs().ceil();
+        text.0 = remaining_seconds.to_string();
+    }
+}
+
+fn send_garbage(
+    mut locked_events: EventReader<TetrominoLocked>,
+    mut players: Query<(Entity, &mut game::GameBoard)>,
+) {
+    for event in locked_events.read() {
+        if event.attack_lines == 0 {
+            continue;
+        }
+
+        for (player, mut game_board) in &mut players {
+            if player != event.player {
+                game_board.receive_garbage(event.attack_lines);
+            }
+        }
+    }
+}
+
+fn setup_incoming_garbage_bars(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<Entity, With<Player>>,
+) {
+    let shape = meshes.add(Rectangle::new(GARBAGE_BAR_WIDTH, SQUARE_SIZE));
+    for player in players {
+        commands.spawn((
+            IncomingGarbageBar,
+            Mesh2d(shape.clone()),
+            MeshMaterial2d(materials.add(ORANGE)),
+            Transform::from_xyz(GARBAGE_BAR_X, GARBAGE_BAR_BOTTOM, 0.0),
+            Visibility::Hidden,
+            ChildOf(player),
+        ));
+    }
+}
+
+// Grows the bar beside the board with the incoming garbage, red once it is about to enter
+fn update_incoming_garbage_bars(
+    players: Query<(&game::GameBoard, &Children)>,
+    mut bars: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &MeshMaterial2d<ColorMaterial>,
+        ),
+        With<IncomingGarbageBar>,
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (game_board, children) in players {
+        let incoming_lines = std::cmp::min(game_board.get_incoming_garbage(), NUMBER_OF_ROWS);
+        for child in children.iter() {
+            let Ok((mut transform, mut visibility, material)) = bars.get_mut(child) else {
+                continue;
+            };
+
+            *visibility = if incoming_lines > 0 {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+
+            transform.scale.y = incoming_lines as f32;
+            transform.translation.y =
+                GARBAGE_BAR_BOTTOM + incoming_lines as f32 * SQUARE_SIZE / 2.0;
+
+            if let Some(material) = materials.get_mut(&material.0) {
+                material.color = if game_board.get_ready_garbage() > 0 {
+                    RED
+                } else {
+                    ORANGE
+                };
+            }
+        }
+    }
+}
+
+fn show_winner(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Query<(&Player, &GameSettings)>,
+    winner_text: Query<Entity, With<WinnerText>>,
+) {
+    // Resuming from the pause keeps the winner already displayed
+    if !winner_text.is_empty() {
+        return;
+    }
+
+    let survivors: Vec<&Player> = players
+        .iter()
+        .filter(|(_, game_settings)| game_settings.board_status != BoardStatus::ToppedOut)
+        .map(|(player, _)| player)
+        .collect();
+
+    let message = match survivors.as_slice() {
+        [winner] => format!("Player {0} wins!", winner.id + 1),
+        _ => String::from("Draw"),
+    };
+
+    commands.spawn((
+        Text2d::new(message),
+        get_text_font(&asset_server),
+        Anchor::Center,
+        TextColor(GREEN),
+        Transform::from_translation(Vec3::new(0.00, 0.00, 10.0)),
+        WinnerText,
+    ));
+}